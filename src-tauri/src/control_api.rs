@@ -0,0 +1,641 @@
+//! Local HTTP and WebSocket control API for home automation and stream deck
+//! style controllers. Off by default; every request needs the API token.
+//!
+//! `POST /api/playback/{command}` runs a command, with its arguments as a
+//! JSON body, e.g. `POST /api/playback/seek` with `{"time": 10}`.
+//! `GET /api/events` upgrades to a WebSocket that streams playback events
+//! and accepts the same commands as `{"command": "seek", "time": 10}`.
+
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::{mpsc::Sender, Arc},
+    time::Duration,
+};
+
+use axum::{
+    body::Bytes,
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Request, State,
+    },
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Router,
+};
+use futures_util::{SinkExt, StreamExt};
+use rand::Rng;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::AppHandle;
+use tokio::sync::{broadcast, oneshot, Mutex};
+
+use crate::mpv::{
+    AudioTrackChange, EOFEventChange, ErrorEventChange, FileLoadedChange, HttpOptions, LoadOptions,
+    NowPlayingChange, PlayBackStateChange, PlayBackTimeChange, PlaybackEvent, SpeedEventChange,
+    SubtitleTrackChange, VolumeEventChange,
+};
+use crate::sleep_timer::{SleepTimerChange, SleepTimerMode};
+use crate::store::GeneralSettings;
+
+const TOKEN_LENGTH: usize = 32;
+
+/// How long a restart waits for the previous server to release its port.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Highest volume the UI offers, in percent.
+const MAX_VOLUME: u8 = 100;
+
+/// Playback speeds mpv accepts.
+const MIN_SPEED: f64 = 0.01;
+const MAX_SPEED: f64 = 100.0;
+
+/// Which interfaces the control API listens on.
+#[derive(Serialize, Deserialize, specta::Type, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ControlApiBind {
+    /// Only this machine.
+    #[default]
+    Localhost,
+    /// Every interface, so other devices on the network can connect.
+    Lan,
+}
+
+/// Control API settings, stored in `GeneralSettings`.
+#[derive(Serialize, Deserialize, specta::Type, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ControlApiSettings {
+    pub enabled: bool,
+    pub bind: ControlApiBind,
+    pub port: u16,
+    /// Sent as `Authorization: Bearer <token>` or a `token` query parameter.
+    pub token: String,
+}
+
+impl Default for ControlApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: ControlApiBind::default(),
+            port: 9876,
+            token: String::new(),
+        }
+    }
+}
+
+pub fn generate_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(TOKEN_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// A command, as sent by API clients. Mirrors the `playback_*` commands,
+/// except for those that reach this machine's file system
+/// (`playback_open_local`, `playback_local_select`), change saved settings
+/// (`playback_cache_settings`, `playback_cache_preset`,
+/// `playback_sleep_policy`) or tune the app's own UI
+/// (`playback_time_update_interval`, the stats subscriptions).
+#[derive(Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+enum ControlCommand {
+    Play,
+    Pause,
+    Clear,
+    Seek {
+        time: f64,
+    },
+    AbsoluteSeek {
+        time: f64,
+    },
+    Volume {
+        volume: u8,
+    },
+    Speed {
+        speed: f64,
+    },
+    Load {
+        url: String,
+        #[serde(default)]
+        http: Option<HttpOptions>,
+        #[serde(default)]
+        options: Option<LoadOptions>,
+    },
+    ChangeSubtitle {
+        subtitle: String,
+    },
+    ChangeAudio {
+        audio: String,
+    },
+    LoadSubtitle {
+        url: String,
+        #[serde(default)]
+        title: Option<String>,
+        #[serde(default)]
+        lang: Option<String>,
+        #[serde(default)]
+        http: Option<HttpOptions>,
+    },
+    /// `null` cancels the timer.
+    SleepTimer {
+        mode: Option<SleepTimerMode>,
+    },
+}
+
+/// Commands come from the network, so arguments the UI never sends are
+/// refused here rather than left to mpv.
+impl TryFrom<ControlCommand> for PlaybackEvent {
+    type Error = String;
+
+    fn try_from(command: ControlCommand) -> Result<Self, String> {
+        Ok(match command {
+            ControlCommand::Play => PlaybackEvent::Play,
+            ControlCommand::Pause => PlaybackEvent::Pause,
+            ControlCommand::Clear => PlaybackEvent::Clear,
+            ControlCommand::Seek { time } => PlaybackEvent::Seek(check_time(time)?),
+            ControlCommand::AbsoluteSeek { time } if time < 0.0 => {
+                return Err(format!("Invalid position {}", time))
+            }
+            ControlCommand::AbsoluteSeek { time } => PlaybackEvent::AbsoluteSeek(check_time(time)?),
+            ControlCommand::Volume { volume } if volume > MAX_VOLUME => {
+                return Err(format!("Volume must be at most {}", MAX_VOLUME))
+            }
+            ControlCommand::Volume { volume } => PlaybackEvent::Volume(volume),
+            ControlCommand::Speed { speed } if !(MIN_SPEED..=MAX_SPEED).contains(&speed) => {
+                return Err(format!(
+                    "Speed must be between {} and {}",
+                    MIN_SPEED, MAX_SPEED
+                ))
+            }
+            ControlCommand::Speed { speed } => PlaybackEvent::Speed(speed),
+            ControlCommand::Load { url, http, options } => PlaybackEvent::Load {
+                url: check_url(url)?,
+                options: options.unwrap_or_default(),
+                http,
+            },
+            ControlCommand::ChangeSubtitle { subtitle } => {
+                PlaybackEvent::ChangeSubtitle(check_track(subtitle)?)
+            }
+            ControlCommand::ChangeAudio { audio } => {
+                PlaybackEvent::ChangeAudio(check_track(audio)?)
+            }
+            ControlCommand::LoadSubtitle {
+                url,
+                title,
+                lang,
+                http,
+            } => PlaybackEvent::LoadSubtitle {
+                url: check_url(url)?,
+                title,
+                lang,
+                http,
+            },
+            ControlCommand::SleepTimer { mode } => PlaybackEvent::SleepTimer(mode),
+        })
+    }
+}
+
+fn check_time(time: f64) -> Result<f64, String> {
+    if time.is_finite() {
+        Ok(time)
+    } else {
+        Err(format!("Invalid time {}", time))
+    }
+}
+
+/// Track ids as mpv takes them for `aid` and `sid`.
+fn check_track(track: String) -> Result<String, String> {
+    if track == "no" || track == "auto" || track.parse::<u32>().is_ok() {
+        Ok(track)
+    } else {
+        Err(format!(
+            "Invalid track '{}', expected a track id, \"no\" or \"auto\"",
+            track
+        ))
+    }
+}
+
+/// Only network streams; mpv would otherwise open files on this machine.
+fn check_url(url: String) -> Result<String, String> {
+    let scheme = url
+        .split_once("://")
+        .map(|(scheme, _)| scheme.to_lowercase());
+    match scheme.as_deref() {
+        Some("http" | "https") => Ok(url),
+        _ => Err(format!("Unsupported URL '{}', expected http or https", url)),
+    }
+}
+
+/// Playback event as streamed to WebSocket clients.
+#[derive(Serialize, Clone)]
+struct StreamedEvent {
+    event: &'static str,
+    payload: Value,
+}
+
+#[derive(Clone)]
+struct ApiState {
+    render_tx: Sender<PlaybackEvent>,
+    events_tx: broadcast::Sender<StreamedEvent>,
+    token: Arc<str>,
+}
+
+impl ApiState {
+    /// Commands go through the same channel as the UI's, so they behave
+    /// identically.
+    fn run(&self, event: PlaybackEvent) -> Result<(), String> {
+        self.render_tx
+            .send(event)
+            .map_err(|e| format!("Failed to send render event: {}", e))
+    }
+
+    fn router(self) -> Router {
+        Router::new()
+            .route("/api/playback/{command}", post(run_command))
+            .route("/api/events", get(stream_events))
+            .layer(middleware::from_fn_with_state(self.clone(), authorize))
+            .with_state(self)
+    }
+}
+
+/// The listening server: signal `shutdown` and wait for `task` to stop it.
+struct Server {
+    shutdown: oneshot::Sender<()>,
+    task: tauri::async_runtime::JoinHandle<()>,
+}
+
+/// Control API handle, managed as app state.
+#[derive(Clone)]
+pub struct ControlApi {
+    render_tx: Sender<PlaybackEvent>,
+    events_tx: broadcast::Sender<StreamedEvent>,
+    server: Arc<Mutex<Option<Server>>>,
+}
+
+impl ControlApi {
+    /// Stop the running server and start it again with `settings`.
+    pub async fn apply(&self, settings: &ControlApiSettings) -> Result<(), String> {
+        let mut server = self.server.lock().await;
+
+        if let Some(Server { shutdown, task }) = server.take() {
+            shutdown.send(()).ok();
+            // Wait for the port to be released before binding it again
+            if tokio::time::timeout(SHUTDOWN_TIMEOUT, task).await.is_err() {
+                log::warn!("Control API did not stop in time");
+            }
+            log::info!("Control API stopped");
+        }
+
+        if !settings.enabled {
+            return Ok(());
+        }
+        if settings.token.is_empty() {
+            return Err("The control API needs a token".to_string());
+        }
+
+        let ip = match settings.bind {
+            ControlApiBind::Localhost => Ipv4Addr::LOCALHOST,
+            ControlApiBind::Lan => Ipv4Addr::UNSPECIFIED,
+        };
+        let address = SocketAddr::from((ip, settings.port));
+        let listener = tokio::net::TcpListener::bind(address)
+            .await
+            .map_err(|e| format!("Failed to listen on {}: {}", address, e))?;
+
+        let state = ApiState {
+            render_tx: self.render_tx.clone(),
+            events_tx: self.events_tx.clone(),
+            token: settings.token.as_str().into(),
+        };
+        let router = state.router();
+
+        let (shutdown, shutdown_rx) = oneshot::channel();
+        let task = tauri::async_runtime::spawn(async move {
+            let result = axum::serve(listener, router)
+                .with_graceful_shutdown(async {
+                    shutdown_rx.await.ok();
+                })
+                .await;
+
+            if let Err(e) = result {
+                log::error!("Control API failed: {}", e);
+            }
+        });
+        *server = Some(Server { shutdown, task });
+
+        log::info!("Control API listening on {}", address);
+        Ok(())
+    }
+}
+
+pub fn init(app: &AppHandle, render_tx: Sender<PlaybackEvent>) -> ControlApi {
+    let (events_tx, _) = broadcast::channel(256);
+    forward_events(app, &events_tx);
+
+    let api = ControlApi {
+        render_tx,
+        events_tx,
+        server: Arc::new(Mutex::new(None)),
+    };
+
+    let settings = GeneralSettings::get(app)
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+        .control_api;
+    let starter = api.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = starter.apply(&settings).await {
+            log::error!("Failed to start the control API: {}", e);
+        }
+    });
+
+    api
+}
+
+fn forward_events(app: &AppHandle, events_tx: &broadcast::Sender<StreamedEvent>) {
+    fn forward<E>(app: &AppHandle, events_tx: &broadcast::Sender<StreamedEvent>)
+    where
+        E: tauri_specta::Event + Serialize + DeserializeOwned,
+    {
+        let events_tx = events_tx.clone();
+        E::listen_any(app, move |event| {
+            // Nobody may be connected, in which case the event is dropped
+            if events_tx.receiver_count() == 0 {
+                return;
+            }
+            if let Ok(payload) = serde_json::to_value(&event.payload) {
+                events_tx
+                    .send(StreamedEvent {
+                        event: E::NAME,
+                        payload,
+                    })
+                    .ok();
+            }
+        });
+    }
+
+    forward::<PlayBackTimeChange>(app, events_tx);
+    forward::<PlayBackStateChange>(app, events_tx);
+    forward::<FileLoadedChange>(app, events_tx);
+    forward::<EOFEventChange>(app, events_tx);
+    forward::<ErrorEventChange>(app, events_tx);
+    forward::<VolumeEventChange>(app, events_tx);
+    forward::<SpeedEventChange>(app, events_tx);
+    forward::<AudioTrackChange>(app, events_tx);
+    forward::<SubtitleTrackChange>(app, events_tx);
+    forward::<NowPlayingChange>(app, events_tx);
+    forward::<SleepTimerChange>(app, events_tx);
+}
+
+/// Compare without returning early, so response times don't leak the token.
+fn token_matches(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+async fn authorize(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    // Browsers cannot set headers on WebSocket connections
+    let query = request.uri().query().and_then(|query| {
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("token="))
+    });
+
+    match bearer.or(query) {
+        Some(token) if token_matches(token, &state.token) => next.run(request).await,
+        _ => StatusCode::UNAUTHORIZED.into_response(),
+    }
+}
+
+async fn run_command(
+    State(state): State<ApiState>,
+    Path(command): Path<String>,
+    body: Bytes,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let mut arguments = if body.is_empty() {
+        json!({})
+    } else {
+        serde_json::from_slice::<Value>(&body)
+            .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid JSON body: {}", e)))?
+    };
+    let Some(object) = arguments.as_object_mut() else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "The body must be a JSON object".to_string(),
+        ));
+    };
+    object.insert("command".to_string(), Value::String(command));
+
+    let event = serde_json::from_value::<ControlCommand>(arguments)
+        .map_err(|e| e.to_string())
+        .and_then(PlaybackEvent::try_from)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    state
+        .run(event)
+        .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, e))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn stream_events(State(state): State<ApiState>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(move |socket| serve_socket(socket, state))
+}
+
+async fn serve_socket(socket: WebSocket, state: ApiState) {
+    let (mut sink, mut stream) = socket.split();
+    let mut events_rx = state.events_tx.subscribe();
+
+    loop {
+        let reply = tokio::select! {
+            event = events_rx.recv() => match event {
+                Ok(event) => json!(event),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    json!({ "error": format!("{} events were dropped", skipped) })
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            message = stream.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    let event = serde_json::from_str::<ControlCommand>(text.as_str())
+                        .map_err(|e| e.to_string())
+                        .and_then(PlaybackEvent::try_from);
+                    match event {
+                        Ok(event) => match state.run(event) {
+                            Ok(()) => continue,
+                            Err(e) => json!({ "error": e }),
+                        },
+                        Err(e) => json!({ "error": format!("Invalid command: {}", e) }),
+                    }
+                }
+                Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                Some(Ok(_)) => continue,
+            },
+        };
+
+        if sink
+            .send(Message::Text(reply.to_string().into()))
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::{channel, Receiver};
+
+    use tauri_plugin_http::reqwest;
+
+    use super::*;
+
+    const TOKEN: &str = "secret-token";
+
+    struct TestServer {
+        url: String,
+        render_rx: Receiver<PlaybackEvent>,
+        client: reqwest::Client,
+    }
+
+    impl TestServer {
+        async fn start() -> Self {
+            let (render_tx, render_rx) = channel();
+            let state = ApiState {
+                render_tx,
+                events_tx: broadcast::channel(16).0,
+                token: TOKEN.into(),
+            };
+
+            let listener =
+                tokio::net::TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+                    .await
+                    .unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            tokio::spawn(async move { axum::serve(listener, state.router()).await.unwrap() });
+
+            Self {
+                url,
+                render_rx,
+                client: reqwest::Client::new(),
+            }
+        }
+
+        async fn post(&self, command: &str, body: Value) -> u16 {
+            self.client
+                .post(format!("{}/api/playback/{}", self.url, command))
+                .bearer_auth(TOKEN)
+                .body(body.to_string())
+                .send()
+                .await
+                .unwrap()
+                .status()
+                .as_u16()
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_need_the_token() {
+        let server = TestServer::start().await;
+        let url = format!("{}/api/playback/play", server.url);
+
+        let missing = server.client.post(&url).send().await.unwrap();
+        assert_eq!(missing.status().as_u16(), 401);
+
+        let wrong = server
+            .client
+            .post(&url)
+            .bearer_auth("wrong-token!")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(wrong.status().as_u16(), 401);
+        assert!(server.render_rx.try_recv().is_err());
+
+        let query = server
+            .client
+            .post(format!("{}?token={}", url, TOKEN))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(query.status().as_u16(), 204);
+        assert!(matches!(
+            server.render_rx.try_recv(),
+            Ok(PlaybackEvent::Play)
+        ));
+    }
+
+    #[tokio::test]
+    async fn commands_reach_the_render_thread() {
+        let server = TestServer::start().await;
+
+        assert_eq!(server.post("seek", json!({ "time": -10 })).await, 204);
+        let event = server.render_rx.try_recv();
+        assert!(
+            matches!(event, Ok(PlaybackEvent::Seek(time)) if time == -10.0),
+            "{:?}",
+            event
+        );
+
+        assert_eq!(server.post("volume", json!({ "volume": 80 })).await, 204);
+        let event = server.render_rx.try_recv();
+        assert!(
+            matches!(event, Ok(PlaybackEvent::Volume(80))),
+            "{:?}",
+            event
+        );
+
+        assert_eq!(
+            server
+                .post("change-subtitle", json!({ "subtitle": "no" }))
+                .await,
+            204
+        );
+        let event = server.render_rx.try_recv();
+        assert!(
+            matches!(&event, Ok(PlaybackEvent::ChangeSubtitle(sid)) if sid == "no"),
+            "{:?}",
+            event
+        );
+
+        assert_eq!(server.post("unknown", json!({})).await, 400);
+    }
+
+    #[tokio::test]
+    async fn invalid_arguments_are_refused() {
+        let server = TestServer::start().await;
+
+        let invalid = [
+            ("volume", json!({ "volume": 200 })),
+            ("speed", json!({ "speed": 0 })),
+            ("absolute-seek", json!({ "time": -1 })),
+            ("change-subtitle", json!({ "subtitle": "x" })),
+            ("change-audio", json!({ "audio": "1,2" })),
+            ("load", json!({ "url": "/etc/passwd" })),
+            ("load-subtitle", json!({ "url": "file:///etc/passwd" })),
+        ];
+        for (command, body) in invalid {
+            assert_eq!(
+                server.post(command, body.clone()).await,
+                400,
+                "{} {}",
+                command,
+                body
+            );
+        }
+
+        assert!(server.render_rx.try_recv().is_err());
+    }
+}
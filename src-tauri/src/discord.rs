@@ -0,0 +1,725 @@
+//! Discord Rich Presence over the local Discord IPC socket, showing what is
+//! playing in the user's Discord profile.
+
+use std::{
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::AppHandle;
+use tauri_specta::Event;
+
+use crate::mpv::{NowPlayingChange, PlayBackStateChange, PlayBackTimeChange, RequestClearEvent};
+use crate::store::GeneralSettings;
+
+/// Discord allows five activity updates per 20 seconds.
+const MIN_UPDATE_INTERVAL: Duration = Duration::from_secs(4);
+
+/// Wait this long before trying to reach Discord again after a failure.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// How long to wait for Discord to answer before giving up on the connection.
+const IPC_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Position jumps larger than this between two time updates count as a seek.
+const SEEK_THRESHOLD: f64 = 2.0;
+
+const OP_HANDSHAKE: u32 = 0;
+const OP_FRAME: u32 = 1;
+const OP_CLOSE: u32 = 2;
+
+/// "Watching" activity type.
+const ACTIVITY_WATCHING: u32 = 3;
+
+/// The app's own Discord application, baked in at build time. Builds without
+/// it need an application id in the settings.
+const DEFAULT_CLIENT_ID: &str = match option_env!("SQUARE_DISCORD_CLIENT_ID") {
+    Some(client_id) => client_id,
+    None => "",
+};
+
+/// Rich Presence settings, stored in `GeneralSettings`.
+#[derive(Serialize, Deserialize, specta::Type, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscordSettings {
+    pub enabled: bool,
+    /// Show that something is playing without its title or episode.
+    pub privacy_mode: bool,
+    /// Discord application id the presence is published under. Defaults to
+    /// the app's own application, if the build has one.
+    pub client_id: String,
+}
+
+impl Default for DiscordSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            privacy_mode: false,
+            client_id: DEFAULT_CLIENT_ID.to_string(),
+        }
+    }
+}
+
+impl DiscordSettings {
+    /// The configured application id, or the app's own if none is set.
+    fn client_id(&self) -> &str {
+        if self.client_id.is_empty() {
+            DEFAULT_CLIENT_ID
+        } else {
+            &self.client_id
+        }
+    }
+
+    /// Refuse enabling the presence without an application to publish it under.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.enabled && self.client_id().is_empty() {
+            return Err(
+                "Discord Rich Presence needs a Discord application id in this build".to_string(),
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Player state change picked up from the render thread's events.
+enum PresenceUpdate {
+    NowPlaying(NowPlayingChange),
+    Paused(bool),
+    Time { position: f64, duration: f64 },
+    Clear,
+    Settings(DiscordSettings),
+}
+
+/// Handle to the presence thread, managed as app state.
+pub struct DiscordPresence {
+    tx: Sender<PresenceUpdate>,
+}
+
+impl DiscordPresence {
+    pub fn set_settings(&self, settings: DiscordSettings) {
+        self.tx.send(PresenceUpdate::Settings(settings)).ok();
+    }
+}
+
+pub fn init(app: &AppHandle) -> DiscordPresence {
+    let settings = GeneralSettings::get(app)
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+        .discord;
+    if let Err(e) = settings.validate() {
+        log::warn!("{}", e);
+    }
+
+    let (tx, rx) = channel();
+    listen(app, tx.clone());
+
+    std::thread::Builder::new()
+        .name("discord-presence".to_string())
+        .spawn(move || Presence::new(settings).run(rx))
+        .map_err(|e| log::error!("Failed to start Discord presence thread: {}", e))
+        .ok();
+
+    DiscordPresence { tx }
+}
+
+fn listen(app: &AppHandle, update_tx: Sender<PresenceUpdate>) {
+    let tx = update_tx.clone();
+    NowPlayingChange::listen_any(app, move |event| {
+        tx.send(PresenceUpdate::NowPlaying(event.payload)).ok();
+    });
+
+    let tx = update_tx.clone();
+    PlayBackStateChange::listen_any(app, move |event| {
+        tx.send(PresenceUpdate::Paused(event.payload.pause)).ok();
+    });
+
+    let tx = update_tx.clone();
+    PlayBackTimeChange::listen_any(app, move |event| {
+        tx.send(PresenceUpdate::Time {
+            position: event.payload.position,
+            duration: event.payload.duration,
+        })
+        .ok();
+    });
+
+    RequestClearEvent::listen_any(app, move |_| {
+        update_tx.send(PresenceUpdate::Clear).ok();
+    });
+}
+
+/// Presence state owned by the presence thread. Updates are coalesced so
+/// time events don't run into Discord's rate limit.
+struct Presence {
+    settings: DiscordSettings,
+    now_playing: Option<NowPlayingChange>,
+    paused: bool,
+    position: f64,
+    duration: f64,
+    /// Last time update, used to tell seeks from normal progress.
+    anchor: Option<(Instant, f64)>,
+    ipc: Option<DiscordIpc>,
+    dirty: bool,
+    next_update: Instant,
+    next_connect: Instant,
+}
+
+impl Presence {
+    fn new(settings: DiscordSettings) -> Self {
+        Self {
+            settings,
+            now_playing: None,
+            paused: false,
+            position: 0.0,
+            duration: 0.0,
+            anchor: None,
+            ipc: None,
+            dirty: false,
+            next_update: Instant::now(),
+            next_connect: Instant::now(),
+        }
+    }
+
+    fn run(mut self, rx: Receiver<PresenceUpdate>) {
+        loop {
+            let update = if self.dirty {
+                match rx.recv_timeout(self.next_update.saturating_duration_since(Instant::now())) {
+                    Ok(update) => Some(update),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            } else {
+                match rx.recv() {
+                    Ok(update) => Some(update),
+                    Err(_) => break,
+                }
+            };
+
+            if let Some(update) = update {
+                self.apply(update);
+            }
+
+            if self.dirty && Instant::now() >= self.next_update {
+                self.publish();
+            }
+        }
+
+        if let Some(mut ipc) = self.ipc.take() {
+            ipc.set_activity(None).ok();
+        }
+    }
+
+    fn apply(&mut self, update: PresenceUpdate) {
+        match update {
+            PresenceUpdate::NowPlaying(now_playing) => {
+                self.now_playing = now_playing.loaded.then_some(now_playing);
+                self.paused = false;
+                self.position = 0.0;
+                self.duration = 0.0;
+                self.anchor = None;
+                self.dirty = true;
+            }
+            PresenceUpdate::Paused(paused) => {
+                self.dirty |= self.paused != paused;
+                self.paused = paused;
+            }
+            PresenceUpdate::Time { position, duration } => {
+                let now = Instant::now();
+                let seeked = match self.anchor {
+                    Some((at, previous)) if !self.paused => {
+                        let expected = previous + now.duration_since(at).as_secs_f64();
+                        (position - expected).abs() > SEEK_THRESHOLD
+                    }
+                    Some((_, previous)) => (position - previous).abs() > SEEK_THRESHOLD,
+                    None => true,
+                };
+
+                self.dirty |= seeked || (duration - self.duration).abs() > SEEK_THRESHOLD;
+                self.anchor = Some((now, position));
+                self.position = position;
+                self.duration = duration;
+            }
+            PresenceUpdate::Clear => {
+                self.now_playing = None;
+                self.anchor = None;
+                self.dirty = true;
+            }
+            PresenceUpdate::Settings(settings) => {
+                if settings.client_id() != self.settings.client_id() {
+                    // The handshake binds the connection to one application
+                    self.disconnect();
+                }
+                self.settings = settings;
+                self.dirty = true;
+            }
+        }
+    }
+
+    fn publish(&mut self) {
+        self.dirty = false;
+        self.next_update = Instant::now() + MIN_UPDATE_INTERVAL;
+
+        if !self.settings.enabled || self.settings.client_id().is_empty() {
+            self.disconnect();
+            return;
+        }
+
+        let activity = self
+            .now_playing
+            .as_ref()
+            .map(|now_playing| self.activity(now_playing));
+        if activity.is_none() && self.ipc.is_none() {
+            return;
+        }
+
+        let Some(ipc) = self.connect() else {
+            // Retry once Discord may be reachable again
+            self.dirty = true;
+            self.next_update = self.next_connect;
+            return;
+        };
+
+        if let Err(e) = ipc.set_activity(activity) {
+            log::warn!("Failed to update Discord presence: {}", e);
+            self.ipc = None;
+            self.dirty = true;
+            self.next_update = Instant::now() + RECONNECT_INTERVAL;
+        }
+    }
+
+    fn activity(&self, now_playing: &NowPlayingChange) -> Value {
+        let mut activity = json!({ "type": ACTIVITY_WATCHING });
+
+        if self.settings.privacy_mode {
+            activity["details"] = json!("Watching something");
+        } else {
+            if let Some(title) = &now_playing.title {
+                activity["details"] = json!(title);
+            }
+            if let Some(episode) = &now_playing.episode {
+                activity["state"] = json!(episode);
+            }
+        }
+
+        if self.paused {
+            activity["state"] = json!("Paused");
+        } else if self.duration > 0.0 {
+            // Discord counts elapsed and remaining time itself from these
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            activity["timestamps"] = json!({
+                "start": (now - self.position) as u64,
+                "end": (now + self.duration - self.position) as u64,
+            });
+        }
+
+        activity
+    }
+
+    fn connect(&mut self) -> Option<&mut DiscordIpc> {
+        if self.ipc.is_none() && Instant::now() >= self.next_connect {
+            match DiscordIpc::connect(self.settings.client_id()) {
+                Ok(ipc) => {
+                    log::info!("Connected to Discord for Rich Presence");
+                    self.ipc = Some(ipc);
+                }
+                Err(e) => {
+                    log::debug!("Discord is not reachable: {}", e);
+                    self.next_connect = Instant::now() + RECONNECT_INTERVAL;
+                }
+            }
+        }
+
+        self.ipc.as_mut()
+    }
+
+    fn disconnect(&mut self) {
+        if let Some(mut ipc) = self.ipc.take() {
+            if let Err(e) = ipc.set_activity(None) {
+                log::debug!("Failed to clear Discord presence: {}", e);
+            }
+        }
+    }
+}
+
+trait Transport: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Transport for T {}
+
+/// Client for Discord's IPC protocol: frames of a little endian opcode and
+/// length followed by a JSON payload.
+struct DiscordIpc {
+    stream: Box<dyn Transport>,
+    nonce: u64,
+}
+
+impl DiscordIpc {
+    /// Connect to the first Discord instance that answers.
+    fn connect(client_id: &str) -> io::Result<Self> {
+        let mut last_error =
+            io::Error::new(io::ErrorKind::NotFound, "Discord IPC socket not found");
+
+        for path in socket_paths() {
+            match Self::connect_at(&path, client_id) {
+                Ok(ipc) => return Ok(ipc),
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Connect to the socket at `path`, e.g. a fake IPC server.
+    fn connect_at(path: &Path, client_id: &str) -> io::Result<Self> {
+        #[cfg(unix)]
+        let stream: Box<dyn Transport> = {
+            let stream = std::os::unix::net::UnixStream::connect(path)?;
+            stream.set_read_timeout(Some(IPC_TIMEOUT))?;
+            stream.set_write_timeout(Some(IPC_TIMEOUT))?;
+            Box::new(stream)
+        };
+        #[cfg(windows)]
+        let stream: Box<dyn Transport> = Box::new(TimedPipe::open(path)?);
+
+        let mut ipc = Self { stream, nonce: 0 };
+        ipc.send(OP_HANDSHAKE, &json!({ "v": 1, "client_id": client_id }))?;
+        ipc.receive()?;
+        Ok(ipc)
+    }
+
+    /// Publish `activity`, or clear the presence with `None`.
+    fn set_activity(&mut self, activity: Option<Value>) -> io::Result<()> {
+        self.nonce += 1;
+        self.send(
+            OP_FRAME,
+            &json!({
+                "cmd": "SET_ACTIVITY",
+                "args": { "pid": std::process::id(), "activity": activity },
+                "nonce": self.nonce.to_string(),
+            }),
+        )?;
+
+        let reply = self.receive()?;
+        if reply["evt"] == "ERROR" {
+            return Err(io::Error::other(format!(
+                "Discord rejected the activity: {}",
+                reply["data"]["message"]
+            )));
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, opcode: u32, payload: &Value) -> io::Result<()> {
+        let payload = payload.to_string();
+        let mut frame = Vec::with_capacity(8 + payload.len());
+        frame.extend_from_slice(&opcode.to_le_bytes());
+        frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        frame.extend_from_slice(payload.as_bytes());
+
+        self.stream.write_all(&frame)?;
+        self.stream.flush()
+    }
+
+    fn receive(&mut self) -> io::Result<Value> {
+        let mut header = [0u8; 8];
+        self.stream.read_exact(&mut header)?;
+        let opcode = u32::from_le_bytes(header[..4].try_into().unwrap());
+        let length = u32::from_le_bytes(header[4..].try_into().unwrap());
+
+        let mut payload = vec![0u8; length as usize];
+        self.stream.read_exact(&mut payload)?;
+        let payload: Value = serde_json::from_slice(&payload)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        if opcode == OP_CLOSE {
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionAborted,
+                format!("Discord closed the connection: {}", payload["message"]),
+            ));
+        }
+        Ok(payload)
+    }
+}
+
+/// Named pipe with a read timeout. Pipes opened as files have none, so reads
+/// happen on a separate thread that hands the data over.
+///
+/// A reader stuck on an unresponsive Discord only exits once the pipe closes.
+#[cfg(windows)]
+struct TimedPipe {
+    pipe: std::fs::File,
+    chunks: Receiver<io::Result<Vec<u8>>>,
+    pending: Vec<u8>,
+}
+
+#[cfg(windows)]
+impl TimedPipe {
+    fn open(path: &Path) -> io::Result<Self> {
+        let pipe = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)?;
+        let mut reader = pipe.try_clone()?;
+
+        let (tx, chunks) = channel();
+        std::thread::Builder::new()
+            .name("discord-ipc-reader".to_string())
+            .spawn(move || {
+                let mut buf = [0u8; 4096];
+                loop {
+                    let read = reader.read(&mut buf);
+                    let done = !matches!(read, Ok(n) if n > 0);
+                    if tx.send(read.map(|n| buf[..n].to_vec())).is_err() || done {
+                        break;
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            pipe,
+            chunks,
+            pending: Vec::new(),
+        })
+    }
+}
+
+#[cfg(windows)]
+impl Read for TimedPipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pending.is_empty() {
+            self.pending = match self.chunks.recv_timeout(IPC_TIMEOUT) {
+                Ok(chunk) => chunk?,
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "Discord did not answer",
+                    ))
+                }
+                Err(RecvTimeoutError::Disconnected) => return Ok(0),
+            };
+        }
+
+        let n = buf.len().min(self.pending.len());
+        buf[..n].copy_from_slice(&self.pending[..n]);
+        self.pending.drain(..n);
+        Ok(n)
+    }
+}
+
+#[cfg(windows)]
+impl Write for TimedPipe {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pipe.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.pipe.flush()
+    }
+}
+
+/// Candidate IPC socket paths, `discord-ipc-0` to `discord-ipc-9` in each
+/// location Discord, its Flatpak and its Snap use.
+fn socket_paths() -> Vec<PathBuf> {
+    #[cfg(windows)]
+    {
+        (0..10)
+            .map(|i| PathBuf::from(format!(r"\\.\pipe\discord-ipc-{}", i)))
+            .collect()
+    }
+
+    #[cfg(unix)]
+    {
+        let base = ["XDG_RUNTIME_DIR", "TMPDIR", "TMP", "TEMP"]
+            .iter()
+            .find_map(|var| std::env::var_os(var))
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("/tmp"));
+        let dirs = [
+            base.clone(),
+            base.join("app/com.discordapp.Discord"),
+            base.join("snap.discord"),
+        ];
+
+        dirs.iter()
+            .flat_map(|dir| (0..10).map(move |i| dir.join(format!("discord-ipc-{}", i))))
+            .collect()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    use super::*;
+
+    /// Fake Discord client that answers every frame and hands the received
+    /// payloads to the test.
+    struct FakeDiscord {
+        path: PathBuf,
+        frames: Receiver<(u32, Value)>,
+    }
+
+    impl FakeDiscord {
+        fn start(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "square-discord-{}-{}",
+                std::process::id(),
+                name
+            ));
+            std::fs::remove_file(&path).ok();
+            let listener = UnixListener::bind(&path).unwrap();
+
+            let (tx, frames) = channel();
+            std::thread::spawn(move || {
+                let (mut stream, _) = listener.accept().unwrap();
+                while let Ok((opcode, payload)) = read_frame(&mut stream) {
+                    let reply = if opcode == OP_HANDSHAKE {
+                        json!({ "cmd": "DISPATCH", "evt": "READY" })
+                    } else {
+                        json!({ "cmd": payload["cmd"], "evt": null, "nonce": payload["nonce"] })
+                    };
+                    tx.send((opcode, payload)).ok();
+                    write_frame(&mut stream, OP_FRAME, &reply);
+                }
+            });
+
+            Self { path, frames }
+        }
+
+        fn next_frame(&self) -> (u32, Value) {
+            self.frames.recv_timeout(Duration::from_secs(5)).unwrap()
+        }
+    }
+
+    impl Drop for FakeDiscord {
+        fn drop(&mut self) {
+            std::fs::remove_file(&self.path).ok();
+        }
+    }
+
+    fn read_frame(stream: &mut UnixStream) -> io::Result<(u32, Value)> {
+        let mut header = [0u8; 8];
+        stream.read_exact(&mut header)?;
+        let opcode = u32::from_le_bytes(header[..4].try_into().unwrap());
+        let length = u32::from_le_bytes(header[4..].try_into().unwrap());
+
+        let mut payload = vec![0u8; length as usize];
+        stream.read_exact(&mut payload)?;
+        Ok((opcode, serde_json::from_slice(&payload).unwrap()))
+    }
+
+    fn write_frame(stream: &mut UnixStream, opcode: u32, payload: &Value) {
+        let payload = payload.to_string();
+        stream.write_all(&opcode.to_le_bytes()).unwrap();
+        stream
+            .write_all(&(payload.len() as u32).to_le_bytes())
+            .unwrap();
+        stream.write_all(payload.as_bytes()).unwrap();
+    }
+
+    fn settings() -> DiscordSettings {
+        DiscordSettings {
+            enabled: true,
+            privacy_mode: false,
+            client_id: "1234".to_string(),
+        }
+    }
+
+    #[test]
+    fn handshake_and_set_activity() {
+        let discord = FakeDiscord::start("activity");
+        let mut ipc = DiscordIpc::connect_at(&discord.path, "1234").unwrap();
+
+        let (opcode, handshake) = discord.next_frame();
+        assert_eq!(opcode, OP_HANDSHAKE);
+        assert_eq!(handshake, json!({ "v": 1, "client_id": "1234" }));
+
+        ipc.set_activity(Some(json!({ "details": "Movie" })))
+            .unwrap();
+        let (opcode, frame) = discord.next_frame();
+        assert_eq!(opcode, OP_FRAME);
+        assert_eq!(frame["cmd"], "SET_ACTIVITY");
+        assert_eq!(frame["nonce"], "1");
+        assert_eq!(frame["args"]["pid"], std::process::id());
+        assert_eq!(frame["args"]["activity"]["details"], "Movie");
+    }
+
+    #[test]
+    fn clear_removes_the_presence() {
+        let discord = FakeDiscord::start("clear");
+        let mut presence = Presence::new(settings());
+        presence.ipc = Some(DiscordIpc::connect_at(&discord.path, "1234").unwrap());
+        discord.next_frame();
+
+        presence.apply(PresenceUpdate::NowPlaying(NowPlayingChange {
+            title: Some("Movie".to_string()),
+            episode: Some("S01E02".to_string()),
+            loaded: true,
+            ..NowPlayingChange::default()
+        }));
+        presence.publish();
+        let (_, frame) = discord.next_frame();
+        let activity = &frame["args"]["activity"];
+        assert_eq!(activity["type"], ACTIVITY_WATCHING);
+        assert_eq!(activity["details"], "Movie");
+        assert_eq!(activity["state"], "S01E02");
+
+        presence.apply(PresenceUpdate::Clear);
+        assert!(presence.dirty);
+        presence.publish();
+        let (_, frame) = discord.next_frame();
+        assert_eq!(frame["cmd"], "SET_ACTIVITY");
+        assert!(frame["args"]["activity"].is_null());
+    }
+
+    #[test]
+    fn privacy_mode_hides_the_title() {
+        let presence = Presence::new(DiscordSettings {
+            privacy_mode: true,
+            ..settings()
+        });
+        let activity = presence.activity(&NowPlayingChange {
+            title: Some("Movie".to_string()),
+            episode: Some("S01E02".to_string()),
+            loaded: true,
+            ..NowPlayingChange::default()
+        });
+
+        assert_eq!(activity["details"], "Watching something");
+        assert!(activity.get("state").is_none());
+    }
+
+    #[test]
+    fn untitled_items_are_shown_until_cleared() {
+        let mut presence = Presence::new(settings());
+
+        presence.apply(PresenceUpdate::NowPlaying(NowPlayingChange {
+            loaded: true,
+            ..NowPlayingChange::default()
+        }));
+        assert!(presence.now_playing.is_some());
+
+        presence.apply(PresenceUpdate::NowPlaying(NowPlayingChange::default()));
+        assert!(presence.now_playing.is_none());
+    }
+
+    #[test]
+    fn enabling_needs_an_application_id() {
+        let settings = DiscordSettings {
+            client_id: String::new(),
+            ..settings()
+        };
+        assert_eq!(settings.validate().is_err(), DEFAULT_CLIENT_ID.is_empty());
+
+        let disabled = DiscordSettings {
+            enabled: false,
+            ..settings
+        };
+        assert!(disabled.validate().is_ok());
+    }
+}
@@ -0,0 +1,928 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_http::reqwest;
+use tauri_plugin_store::StoreExt;
+use tauri_specta::Event;
+use tokio::io::AsyncWriteExt;
+
+use crate::mpv::{redact_url, HttpOptions};
+use crate::store::GeneralSettings;
+
+const DOWNLOADS_STORE: &str = "downloads";
+const QUEUE_KEY: &str = "queue";
+
+/// Minimum time between two `DownloadProgressChange` events per download.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Extension used while a file is still being downloaded.
+const PARTIAL_EXTENSION: &str = "part";
+
+/// Download limits, stored in `GeneralSettings`.
+#[derive(Serialize, Deserialize, specta::Type, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadSettings {
+    pub max_parallel: u32,
+    /// Total bandwidth cap across all downloads in KiB/s, `None` for unlimited.
+    pub bandwidth_limit_kib: Option<u32>,
+    /// Free space to leave on the disk, in MiB.
+    pub min_free_space_mib: u32,
+}
+
+impl Default for DownloadSettings {
+    fn default() -> Self {
+        Self {
+            max_parallel: 2,
+            bandwidth_limit_kib: None,
+            min_free_space_mib: 1024,
+        }
+    }
+}
+
+/// External subtitle to store next to the downloaded media.
+#[derive(Serialize, Deserialize, specta::Type, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SubtitleDownload {
+    pub url: String,
+    /// Language suffix used in the file name, e.g. `en` for `movie.en.srt`.
+    pub lang: Option<String>,
+    /// File extension without the dot, e.g. `srt`.
+    pub extension: String,
+}
+
+#[derive(Serialize, Deserialize, specta::Type, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadRequest {
+    pub item_id: String,
+    pub title: String,
+    pub url: String,
+    /// File name of the media, including its extension.
+    pub file_name: String,
+    #[serde(default)]
+    pub http: Option<HttpOptions>,
+    #[serde(default)]
+    pub subtitles: Vec<SubtitleDownload>,
+}
+
+#[derive(Serialize, Deserialize, specta::Type, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", tag = "state", content = "message")]
+pub enum DownloadState {
+    Queued,
+    Downloading,
+    Paused,
+    Completed,
+    Failed(String),
+}
+
+impl DownloadState {
+    /// Whether a download in this state may move to `next`. Completed
+    /// downloads stay as they are until removed.
+    fn can_become(&self, next: &DownloadState) -> bool {
+        use DownloadState::*;
+
+        matches!(
+            (self, next),
+            (Queued | Downloading, Paused)
+                | (Paused | Failed(_), Queued)
+                // The task may finish just as it is paused or resumed
+                | (Downloading | Paused | Queued, Completed)
+                | (Downloading, Failed(_))
+        )
+    }
+}
+
+/// A persisted entry of the download queue. Byte counts are `f64` so they
+/// export as plain numbers to the frontend.
+#[derive(Serialize, Deserialize, specta::Type, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadEntry {
+    pub request: DownloadRequest,
+    pub state: DownloadState,
+    pub downloaded_bytes: f64,
+    pub total_bytes: Option<f64>,
+    /// `ETag` or `Last-Modified` of the response the partial file came from,
+    /// sent as `If-Range` on resume so a changed file is downloaded afresh.
+    #[serde(default)]
+    pub validator: Option<String>,
+}
+
+#[derive(Debug, specta::Type, tauri_specta::Event, Serialize, Deserialize, Clone)]
+pub struct DownloadProgressChange {
+    pub item_id: String,
+    pub state: DownloadState,
+    pub downloaded_bytes: f64,
+    pub total_bytes: Option<f64>,
+    pub bytes_per_second: f64,
+}
+
+/// Files of a completed download.
+pub struct LocalCopy {
+    pub media: PathBuf,
+    pub subtitles: Vec<PathBuf>,
+}
+
+/// Shared bandwidth budget for all running downloads, refilled every second.
+/// Bytes over the budget are carried into the following windows.
+struct RateLimiter {
+    window_start: Instant,
+    bytes_in_window: u64,
+}
+
+impl RateLimiter {
+    /// How long the caller has to wait before it may transfer `bytes` more.
+    fn reserve(&mut self, bytes: u64, limit: Option<u32>) -> Option<Duration> {
+        self.reserve_at(Instant::now(), bytes, limit)
+    }
+
+    fn reserve_at(&mut self, now: Instant, bytes: u64, limit: Option<u32>) -> Option<Duration> {
+        let limit = (limit? as u64 * 1024).max(1);
+
+        // Every elapsed window drains one budget's worth of bytes
+        let elapsed = now.saturating_duration_since(self.window_start).as_secs();
+        if elapsed > 0 {
+            self.window_start += Duration::from_secs(elapsed);
+            self.bytes_in_window = self
+                .bytes_in_window
+                .saturating_sub(limit.saturating_mul(elapsed));
+        }
+
+        self.bytes_in_window += bytes;
+        if self.bytes_in_window <= limit {
+            return None;
+        }
+
+        // Wait until enough windows have passed to drain the overflow
+        let windows = (self.bytes_in_window - limit).div_ceil(limit);
+        let resume_at = self.window_start + Duration::from_secs(windows);
+        Some(resume_at.saturating_duration_since(now))
+    }
+}
+
+/// A download task, with the means to stop it and wait for it to exit.
+struct RunningDownload {
+    cancel: Arc<AtomicBool>,
+    finished: tokio::sync::watch::Receiver<bool>,
+}
+
+struct Inner {
+    app: AppHandle,
+    client: reqwest::Client,
+    dir: PathBuf,
+    queue: Mutex<Vec<DownloadEntry>>,
+    /// Running downloads, keyed by item id.
+    running: Mutex<HashMap<String, RunningDownload>>,
+    limiter: Mutex<RateLimiter>,
+}
+
+/// Persistent download queue with resumable HTTP range downloads.
+///
+/// Media is stored in `<app local data>/downloads/<item id>/`, with external
+/// subtitles next to it so mpv can load them alongside the local file.
+#[derive(Clone)]
+pub struct DownloadManager {
+    inner: Arc<Inner>,
+}
+
+impl DownloadManager {
+    pub fn new(app: AppHandle, client: reqwest::Client, dir: PathBuf) -> Self {
+        let mut queue: Vec<DownloadEntry> = app
+            .store(DOWNLOADS_STORE)
+            .ok()
+            .and_then(|store| store.get(QUEUE_KEY))
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+
+        // Downloads interrupted by an exit pick up where they left off
+        for entry in queue.iter_mut() {
+            if entry.state == DownloadState::Downloading {
+                entry.state = DownloadState::Queued;
+            }
+        }
+
+        Self {
+            inner: Arc::new(Inner {
+                app,
+                client,
+                dir,
+                queue: Mutex::new(queue),
+                running: Mutex::new(HashMap::new()),
+                limiter: Mutex::new(RateLimiter {
+                    window_start: Instant::now(),
+                    bytes_in_window: 0,
+                }),
+            }),
+        }
+    }
+
+    fn settings(&self) -> DownloadSettings {
+        GeneralSettings::get(&self.inner.app)
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+            .downloads
+    }
+
+    pub fn list(&self) -> Vec<DownloadEntry> {
+        self.inner.queue.lock().unwrap().clone()
+    }
+
+    pub fn enqueue(&self, request: DownloadRequest) -> Result<DownloadEntry, String> {
+        item_dir(&self.inner.dir, &request.item_id)?;
+
+        let entry = {
+            let mut queue = self.inner.queue.lock().unwrap();
+            if queue.iter().any(|e| e.request.item_id == request.item_id) {
+                return Err(format!(
+                    "Item {} is already downloaded or queued",
+                    request.item_id
+                ));
+            }
+
+            let entry = DownloadEntry {
+                request,
+                state: DownloadState::Queued,
+                downloaded_bytes: 0.0,
+                total_bytes: None,
+                validator: None,
+            };
+            queue.push(entry.clone());
+            entry
+        };
+
+        log::info!("Queued download of {}", redact_url(&entry.request.url));
+        self.persist();
+        self.schedule();
+        Ok(entry)
+    }
+
+    pub fn pause(&self, item_id: &str) -> Result<(), String> {
+        self.set_state(item_id, DownloadState::Paused)?;
+
+        if let Some(running) = self.inner.running.lock().unwrap().get(item_id) {
+            running.cancel.store(true, Ordering::Release);
+        }
+
+        Ok(())
+    }
+
+    pub fn resume(&self, item_id: &str) -> Result<(), String> {
+        self.set_state(item_id, DownloadState::Queued)?;
+        self.schedule();
+        Ok(())
+    }
+
+    pub async fn remove(&self, item_id: &str, delete_files: bool) -> Result<(), String> {
+        // Checked before anything is touched, an unknown id may still name a
+        // directory, or the downloads root itself
+        let dir = queued_item_dir(&self.inner.queue.lock().unwrap(), &self.inner.dir, item_id)?;
+
+        let finished = self
+            .inner
+            .running
+            .lock()
+            .unwrap()
+            .get(item_id)
+            .map(|running| {
+                running.cancel.store(true, Ordering::Release);
+                running.finished.clone()
+            });
+
+        // The task may still be writing; its files can only go once it exited
+        if let Some(mut finished) = finished {
+            finished.wait_for(|finished| *finished).await.ok();
+        }
+
+        self.inner
+            .queue
+            .lock()
+            .unwrap()
+            .retain(|entry| entry.request.item_id != item_id);
+        self.persist();
+
+        if delete_files {
+            if dir.exists() {
+                tokio::fs::remove_dir_all(&dir)
+                    .await
+                    .map_err(|e| format!("Failed to delete download files: {}", e))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Files of a completed download of `item_id`, if there is one on disk.
+    pub fn local_copy(&self, item_id: &str) -> Option<LocalCopy> {
+        let queue = self.inner.queue.lock().unwrap();
+        let entry = queue
+            .iter()
+            .find(|e| e.request.item_id == item_id && e.state == DownloadState::Completed)?;
+
+        let media = self.media_path(&entry.request).ok()?;
+        if !media.exists() {
+            return None;
+        }
+
+        let subtitles = entry
+            .request
+            .subtitles
+            .iter()
+            .map(|subtitle| subtitle_path(&media, subtitle))
+            .filter(|path| path.exists())
+            .collect();
+
+        Some(LocalCopy { media, subtitles })
+    }
+
+    /// Start queued downloads until `max_parallel` are running.
+    pub fn schedule(&self) {
+        let max_parallel = self.settings().max_parallel.max(1) as usize;
+
+        loop {
+            let next = {
+                let mut queue = self.inner.queue.lock().unwrap();
+                let mut running = self.inner.running.lock().unwrap();
+                if running.len() >= max_parallel {
+                    return;
+                }
+
+                // A paused download may still be winding down; wait for it to exit
+                let Some(entry) = queue.iter_mut().find(|e| {
+                    e.state == DownloadState::Queued && !running.contains_key(&e.request.item_id)
+                }) else {
+                    return;
+                };
+
+                entry.state = DownloadState::Downloading;
+                let cancel = Arc::new(AtomicBool::new(false));
+                let (finished_tx, finished) = tokio::sync::watch::channel(false);
+                running.insert(
+                    entry.request.item_id.clone(),
+                    RunningDownload {
+                        cancel: cancel.clone(),
+                        finished,
+                    },
+                );
+                (entry.clone(), cancel, finished_tx)
+            };
+
+            let manager = self.clone();
+            tokio::spawn(async move {
+                let (entry, cancel, finished_tx) = next;
+                let item_id = entry.request.item_id.clone();
+                let result = manager.download(entry, cancel.clone()).await;
+
+                manager.inner.running.lock().unwrap().remove(&item_id);
+                finished_tx.send(true).ok();
+                match result {
+                    Ok(()) => {
+                        let _ = manager.set_state(&item_id, DownloadState::Completed);
+                    }
+                    // Paused or removed; whoever cancelled already set the state
+                    Err(_) if cancel.load(Ordering::Acquire) => {}
+                    Err(e) => {
+                        log::error!("Download of {} failed: {}", item_id, e);
+                        let _ = manager.set_state(&item_id, DownloadState::Failed(e));
+                    }
+                }
+                manager.schedule();
+            });
+        }
+    }
+
+    async fn download(&self, entry: DownloadEntry, cancel: Arc<AtomicBool>) -> Result<(), String> {
+        let request = entry.request;
+        // Read once; changed limits apply to the next download
+        let settings = self.settings();
+
+        let dir = self.item_dir(&request.item_id)?;
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .map_err(|e| format!("Failed to create download directory: {}", e))?;
+
+        let media_path = self.media_path(&request)?;
+        let mut partial_name = media_path.file_name().unwrap_or_default().to_os_string();
+        partial_name.push(format!(".{}", PARTIAL_EXTENSION));
+        let partial_path = media_path.with_file_name(partial_name);
+
+        let mut offset = tokio::fs::metadata(&partial_path)
+            .await
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+        let mut validator = entry.validator;
+
+        let mut response = loop {
+            // Without a validator there is no telling whether the partial file
+            // still matches the server's, so start over
+            if validator.is_none() {
+                offset = 0;
+            }
+
+            let mut builder = self.request(&request.url, request.http.as_ref());
+            if let Some(validator) = validator.as_ref().filter(|_| offset > 0) {
+                builder = builder
+                    .header("Range", format!("bytes={}-", offset))
+                    .header("If-Range", validator);
+            }
+
+            let response = builder
+                .send()
+                .await
+                .map_err(|e| format!("Request failed: {}", e))?;
+
+            match response.status().as_u16() {
+                206 => {
+                    log::info!(
+                        "Resuming download of {} at byte {}",
+                        request.item_id,
+                        offset
+                    );
+                    break response;
+                }
+                // The partial file may already hold the whole body; `Content-Range`
+                // tells the actual size
+                416 if offset > 0 => {
+                    if unsatisfied_range_total(&response) == Some(offset) {
+                        return self.finish(&request, &partial_path, &media_path).await;
+                    }
+
+                    log::warn!(
+                        "Partial download of {} does not match the server's file, restarting",
+                        request.item_id
+                    );
+                    offset = 0;
+                    validator = None;
+                }
+                status if (200..300).contains(&status) => {
+                    offset = 0;
+                    validator = response_validator(&response);
+                    self.set_validator(&request.item_id, validator.clone());
+                    break response;
+                }
+                status => return Err(format!("Server responded with {}", status)),
+            }
+        };
+
+        let total = response.content_length().map(|length| length + offset);
+        self.check_disk_space(
+            &dir,
+            total.map(|total| total - offset),
+            settings.min_free_space_mib,
+        )?;
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(offset > 0)
+            .truncate(offset == 0)
+            .open(&partial_path)
+            .await
+            .map_err(|e| format!("Failed to open {}: {}", partial_path.display(), e))?;
+
+        let mut downloaded = offset;
+        let mut last_progress = Instant::now();
+        let mut bytes_since_progress = 0u64;
+
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| format!("Download interrupted: {}", e))?
+        {
+            if cancel.load(Ordering::Acquire) {
+                file.flush().await.ok();
+                return Err("cancelled".to_string());
+            }
+
+            let wait = self
+                .inner
+                .limiter
+                .lock()
+                .unwrap()
+                .reserve(chunk.len() as u64, settings.bandwidth_limit_kib);
+            if let Some(wait) = wait {
+                tokio::time::sleep(wait).await;
+            }
+
+            file.write_all(&chunk)
+                .await
+                .map_err(|e| format!("Failed to write {}: {}", partial_path.display(), e))?;
+
+            downloaded += chunk.len() as u64;
+            bytes_since_progress += chunk.len() as u64;
+
+            let elapsed = last_progress.elapsed();
+            if elapsed >= PROGRESS_INTERVAL {
+                self.update_progress(
+                    &request.item_id,
+                    downloaded,
+                    total,
+                    bytes_since_progress as f64 / elapsed.as_secs_f64(),
+                );
+                last_progress = Instant::now();
+                bytes_since_progress = 0;
+            }
+        }
+
+        file.flush()
+            .await
+            .map_err(|e| format!("Failed to write {}: {}", partial_path.display(), e))?;
+        self.update_progress(&request.item_id, downloaded, total, 0.0);
+
+        self.finish(&request, &partial_path, &media_path).await
+    }
+
+    /// Move the finished media into place and fetch its external subtitles.
+    async fn finish(
+        &self,
+        request: &DownloadRequest,
+        partial_path: &Path,
+        media_path: &Path,
+    ) -> Result<(), String> {
+        tokio::fs::rename(partial_path, media_path)
+            .await
+            .map_err(|e| format!("Failed to move finished download: {}", e))?;
+
+        for subtitle in &request.subtitles {
+            let path = subtitle_path(media_path, subtitle);
+            // Subtitles are optional, a failure must not fail the whole download
+            if let Err(e) = self
+                .download_subtitle(&subtitle.url, request.http.as_ref(), &path)
+                .await
+            {
+                log::warn!(
+                    "Failed to download subtitle {}: {}",
+                    redact_url(&subtitle.url),
+                    e
+                );
+            }
+        }
+
+        log::info!("Download of {} completed", request.item_id);
+        Ok(())
+    }
+
+    async fn download_subtitle(
+        &self,
+        url: &str,
+        http: Option<&HttpOptions>,
+        path: &Path,
+    ) -> Result<(), String> {
+        let response = self
+            .request(url, http)
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(format!("Server responded with {}", response.status()));
+        }
+
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| format!("Failed to read body: {}", e))?;
+
+        tokio::fs::write(path, body)
+            .await
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    fn request(&self, url: &str, http: Option<&HttpOptions>) -> reqwest::RequestBuilder {
+        let mut builder = self.inner.client.get(url);
+
+        if let Some(http) = http {
+            for (name, value) in &http.headers {
+                builder = builder.header(name, value);
+            }
+            if let Some(user_agent) = &http.user_agent {
+                builder = builder.header("User-Agent", user_agent);
+            }
+            if let Some(cookies) = &http.cookies {
+                builder = builder.header("Cookie", cookies);
+            }
+        }
+
+        builder
+    }
+
+    fn check_disk_space(
+        &self,
+        dir: &Path,
+        remaining: Option<u64>,
+        min_free_space_mib: u32,
+    ) -> Result<(), String> {
+        let available = match fs2::available_space(dir) {
+            Ok(available) => available,
+            Err(e) => {
+                log::warn!("Could not determine free disk space: {}", e);
+                return Ok(());
+            }
+        };
+
+        let reserve = min_free_space_mib as u64 * 1024 * 1024;
+        let required = remaining.unwrap_or(0) + reserve;
+        if available < required {
+            return Err(format!(
+                "Not enough disk space: {} MiB available, {} MiB required",
+                available / 1024 / 1024,
+                required / 1024 / 1024
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn item_dir(&self, item_id: &str) -> Result<PathBuf, String> {
+        item_dir(&self.inner.dir, item_id)
+    }
+
+    fn media_path(&self, request: &DownloadRequest) -> Result<PathBuf, String> {
+        Ok(self
+            .item_dir(&request.item_id)?
+            .join(sanitize_file_name(&request.file_name)))
+    }
+
+    fn set_state(&self, item_id: &str, state: DownloadState) -> Result<(), String> {
+        let entry = {
+            let mut queue = self.inner.queue.lock().unwrap();
+            let entry = queue
+                .iter_mut()
+                .find(|e| e.request.item_id == item_id)
+                .ok_or_else(|| format!("No download for item {}", item_id))?;
+
+            if !entry.state.can_become(&state) {
+                return Err(format!(
+                    "Download of item {} is {:?} and cannot become {:?}",
+                    item_id, entry.state, state
+                ));
+            }
+
+            entry.state = state;
+            entry.clone()
+        };
+
+        self.persist();
+        self.emit_progress(&entry, 0.0);
+        Ok(())
+    }
+
+    fn set_validator(&self, item_id: &str, validator: Option<String>) {
+        if let Some(entry) = self
+            .inner
+            .queue
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .find(|e| e.request.item_id == item_id)
+        {
+            entry.validator = validator;
+        }
+
+        self.persist();
+    }
+
+    fn update_progress(&self, item_id: &str, downloaded: u64, total: Option<u64>, rate: f64) {
+        let entry = {
+            let mut queue = self.inner.queue.lock().unwrap();
+            let Some(entry) = queue.iter_mut().find(|e| e.request.item_id == item_id) else {
+                return;
+            };
+
+            entry.downloaded_bytes = downloaded as f64;
+            entry.total_bytes = total.map(|total| total as f64);
+            entry.clone()
+        };
+
+        // Not persisted: on resume the progress is recovered from the partial file
+        self.emit_progress(&entry, rate);
+    }
+
+    fn emit_progress(&self, entry: &DownloadEntry, bytes_per_second: f64) {
+        DownloadProgressChange {
+            item_id: entry.request.item_id.clone(),
+            state: entry.state.clone(),
+            downloaded_bytes: entry.downloaded_bytes,
+            total_bytes: entry.total_bytes,
+            bytes_per_second,
+        }
+        .emit(&self.inner.app)
+        .ok();
+    }
+
+    fn persist(&self) {
+        let queue = self.inner.queue.lock().unwrap().clone();
+
+        match self.inner.app.store(DOWNLOADS_STORE) {
+            Ok(store) => {
+                store.set(QUEUE_KEY, json!(queue));
+                if let Err(e) = store.save() {
+                    log::error!("Failed to save download queue: {}", e);
+                }
+            }
+            Err(e) => log::error!("Failed to open download store: {}", e),
+        }
+    }
+}
+
+/// Validator for `If-Range`: a strong `ETag`, or else `Last-Modified`. Weak
+/// `ETag`s are not allowed there.
+fn response_validator(response: &reqwest::Response) -> Option<String> {
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+
+    header(reqwest::header::ETAG)
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| header(reqwest::header::LAST_MODIFIED))
+}
+
+/// Full size of the resource from the `Content-Range: bytes */<size>` of a
+/// `416` response.
+fn unsatisfied_range_total(response: &reqwest::Response) -> Option<u64> {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .strip_prefix("bytes */")?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Keep ids and server supplied names from escaping the downloads directory.
+fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim_start_matches('.')
+        .to_string()
+}
+
+/// `movie.mkv` + `en`/`srt` becomes `movie.en.srt`, the layout mpv and
+/// other players use to match subtitles to media.
+fn subtitle_path(media_path: &Path, subtitle: &SubtitleDownload) -> PathBuf {
+    let stem = media_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("subtitle");
+
+    let file_name = match &subtitle.lang {
+        Some(lang) => format!("{}.{}.{}", stem, lang, subtitle.extension),
+        None => format!("{}.{}", stem, subtitle.extension),
+    };
+
+    media_path.with_file_name(sanitize_file_name(&file_name))
+}
+
+/// Directory of `item_id`'s files. Ids that sanitize to nothing, like `..`,
+/// would resolve to the downloads directory itself and are refused.
+fn item_dir(root: &Path, item_id: &str) -> Result<PathBuf, String> {
+    let name = sanitize_file_name(item_id);
+    if name.is_empty() {
+        return Err(format!("Invalid item id '{}'", item_id));
+    }
+
+    Ok(root.join(name))
+}
+
+/// Directory of a download in `queue`, refusing ids that are not queued.
+fn queued_item_dir(queue: &[DownloadEntry], root: &Path, item_id: &str) -> Result<PathBuf, String> {
+    if !queue.iter().any(|e| e.request.item_id == item_id) {
+        return Err(format!("No download for item {}", item_id));
+    }
+
+    item_dir(root, item_id)
+}
+
+/// Resolve the downloads directory and start any downloads left in the queue.
+pub fn init(app: &AppHandle, client: reqwest::Client) -> Result<DownloadManager, String> {
+    let dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))?
+        .join("downloads");
+
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create downloads directory: {}", e))?;
+
+    let manager = DownloadManager::new(app.clone(), client, dir);
+    manager.schedule();
+    Ok(manager)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limiter_carries_overflow_into_next_windows() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter {
+            window_start: start,
+            bytes_in_window: 0,
+        };
+        let limit = Some(1);
+
+        assert_eq!(limiter.reserve_at(start, 1024, limit), None);
+        // 2.5 KiB over a 1 KiB/s budget needs three windows to drain
+        assert_eq!(
+            limiter.reserve_at(start, 2560, limit),
+            Some(Duration::from_secs(3))
+        );
+
+        // One window later only a budget's worth has drained
+        let later = start + Duration::from_millis(1500);
+        assert_eq!(
+            limiter.reserve_at(later, 0, limit),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(limiter.bytes_in_window, 2560);
+
+        let drained = start + Duration::from_secs(3);
+        assert_eq!(limiter.reserve_at(drained, 512, limit), None);
+    }
+
+    fn entry(item_id: &str, state: DownloadState) -> DownloadEntry {
+        DownloadEntry {
+            request: DownloadRequest {
+                item_id: item_id.to_string(),
+                title: "Movie".to_string(),
+                url: "https://example.com/Items/1/Download".to_string(),
+                file_name: "movie.mkv".to_string(),
+                http: None,
+                subtitles: Vec::new(),
+            },
+            state,
+            downloaded_bytes: 0.0,
+            total_bytes: None,
+            validator: None,
+        }
+    }
+
+    #[test]
+    fn item_dirs_stay_inside_the_downloads_directory() {
+        let root = Path::new("/downloads");
+
+        assert_eq!(item_dir(root, "abc").unwrap(), root.join("abc"));
+        assert_eq!(item_dir(root, "../abc").unwrap(), root.join("_abc"));
+        for item_id in ["", ".", "..", "..."] {
+            assert!(item_dir(root, item_id).is_err(), "{:?}", item_id);
+        }
+    }
+
+    #[test]
+    fn remove_refuses_unknown_and_dot_ids() {
+        let root = Path::new("/downloads");
+        let queue = [
+            entry("abc", DownloadState::Completed),
+            entry("..", DownloadState::Queued),
+        ];
+
+        assert_eq!(
+            queued_item_dir(&queue, root, "abc").unwrap(),
+            root.join("abc")
+        );
+        // `remove("..", true)` must never get to `remove_dir_all` on the root
+        assert!(queued_item_dir(&queue, root, "..").is_err());
+        assert!(queued_item_dir(&queue, root, "def").is_err());
+    }
+
+    #[test]
+    fn pause_and_resume_leave_finished_downloads_alone() {
+        use DownloadState::*;
+
+        // Pausing
+        assert!(Queued.can_become(&Paused));
+        assert!(Downloading.can_become(&Paused));
+        assert!(!Completed.can_become(&Paused));
+        assert!(!Failed("error".to_string()).can_become(&Paused));
+
+        // Resuming
+        assert!(Paused.can_become(&Queued));
+        assert!(Failed("error".to_string()).can_become(&Queued));
+        assert!(!Completed.can_become(&Queued));
+        assert!(!Downloading.can_become(&Queued));
+
+        // Finishing
+        assert!(Downloading.can_become(&Completed));
+        assert!(Paused.can_become(&Completed));
+        assert!(!Paused.can_become(&Failed("error".to_string())));
+    }
+}
@@ -0,0 +1,318 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+use crate::mpv::redact_url;
+
+const HISTORY_STORE: &str = "history";
+
+/// How often the in-progress entry is written to disk during playback.
+const SAVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Positions past this fraction of the duration count as watched, so the
+/// entry is dropped instead of resuming a few seconds before the credits end.
+const FINISHED_THRESHOLD: f64 = 0.95;
+
+/// Positions before this are not worth resuming from.
+const MIN_RESUME_POSITION: f64 = 10.0;
+
+/// Entries kept in the store; the least recently updated are dropped first.
+const MAX_ENTRIES: usize = 500;
+
+/// Last known playback state of one item.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub item_id: Option<String>,
+    /// Stream URL with credentials removed.
+    pub url: String,
+    pub position: f64,
+    pub duration: f64,
+    pub aid: Option<String>,
+    pub sid: Option<String>,
+    /// Unix timestamp (seconds) of the last update.
+    pub updated_at: i64,
+}
+
+impl HistoryEntry {
+    fn key(&self) -> String {
+        history_key(self.item_id.as_deref(), &self.url)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.duration > 0.0 && self.position >= self.duration * FINISHED_THRESHOLD
+    }
+
+    fn is_resumable(&self) -> bool {
+        self.position >= MIN_RESUME_POSITION && !self.is_finished()
+    }
+}
+
+fn history_key(item_id: Option<&str>, url: &str) -> String {
+    match item_id {
+        Some(item_id) => format!("item:{}", item_id),
+        None => format!("url:{}", redact_url(url)),
+    }
+}
+
+/// Find a resumable entry through `get`, preferring the item id over the URL.
+fn find_resumable(
+    get: impl Fn(&str) -> Option<JsonValue>,
+    item_id: Option<&str>,
+    url: &str,
+) -> Option<HistoryEntry> {
+    let keys = [
+        item_id.map(|item_id| history_key(Some(item_id), url)),
+        Some(history_key(None, url)),
+    ];
+
+    keys.into_iter()
+        .flatten()
+        .find_map(|key| get(&key))
+        .and_then(|value| serde_json::from_value::<HistoryEntry>(value).ok())
+        .filter(HistoryEntry::is_resumable)
+}
+
+/// Keys to delete so that at most `max` entries remain, least recently
+/// updated first. Entries that no longer parse are always dropped.
+fn stale_keys(entries: Vec<(String, JsonValue)>, max: usize) -> Vec<String> {
+    let mut entries = entries
+        .into_iter()
+        .map(|(key, value)| {
+            let updated_at = serde_json::from_value::<HistoryEntry>(value)
+                .map(|entry| entry.updated_at)
+                .unwrap_or(i64::MIN);
+            (key, updated_at)
+        })
+        .collect::<Vec<_>>();
+
+    let excess = entries.len().saturating_sub(max);
+    let unparseable = entries
+        .iter()
+        .filter(|(_, updated_at)| *updated_at == i64::MIN)
+        .count();
+
+    entries.sort_by_key(|(_, updated_at)| *updated_at);
+    entries
+        .into_iter()
+        .take(excess.max(unparseable))
+        .map(|(key, _)| key)
+        .collect()
+}
+
+/// Local resume-position store, so resuming works without the server.
+///
+/// Entries are keyed by Jellyfin item id when known and by the redacted
+/// stream URL otherwise. Only the entry for the file currently playing is kept
+/// in memory; it is written out periodically and on clear, load and exit.
+pub struct PlaybackHistory {
+    app: AppHandle,
+    current: Option<HistoryEntry>,
+    /// Set once the current file has loaded. Until then property changes
+    /// still queued from the previous file are ignored.
+    recording: bool,
+    dirty: bool,
+    last_save: Instant,
+}
+
+impl PlaybackHistory {
+    pub fn new(app: AppHandle) -> Self {
+        Self {
+            app,
+            current: None,
+            recording: false,
+            dirty: false,
+            last_save: Instant::now(),
+        }
+    }
+
+    /// Look up a resumable entry, preferring the item id over the URL.
+    pub fn lookup(&self, item_id: Option<&str>, url: &str) -> Option<HistoryEntry> {
+        let store = self.app.store(HISTORY_STORE).ok()?;
+        find_resumable(|key| store.get(key), item_id, url)
+    }
+
+    /// Prepare to track a file that is about to load, flushing the previous
+    /// one. Nothing is recorded until `file_loaded`.
+    pub fn begin(&mut self, item_id: Option<String>, url: &str) {
+        self.flush();
+        self.recording = false;
+
+        self.current = Some(HistoryEntry {
+            item_id,
+            url: redact_url(url),
+            position: 0.0,
+            duration: 0.0,
+            aid: None,
+            sid: None,
+            updated_at: chrono::Utc::now().timestamp(),
+        });
+    }
+
+    /// The file passed to `begin` has loaded; start recording its state.
+    pub fn file_loaded(&mut self) {
+        self.recording = self.current.is_some();
+    }
+
+    pub fn update_position(&mut self, position: f64) {
+        self.update(|entry| entry.position = position);
+    }
+
+    pub fn update_duration(&mut self, duration: f64) {
+        self.update(|entry| entry.duration = duration);
+    }
+
+    pub fn update_aid(&mut self, aid: &str) {
+        self.update(|entry| entry.aid = Some(aid.to_string()));
+    }
+
+    pub fn update_sid(&mut self, sid: &str) {
+        self.update(|entry| entry.sid = Some(sid.to_string()));
+    }
+
+    fn update(&mut self, update: impl FnOnce(&mut HistoryEntry)) {
+        if !self.recording {
+            return;
+        }
+
+        if let Some(entry) = self.current.as_mut() {
+            update(entry);
+            entry.updated_at = chrono::Utc::now().timestamp();
+            self.dirty = true;
+        }
+    }
+
+    /// Persist the current entry if `SAVE_INTERVAL` has passed since the last write.
+    pub fn maybe_save(&mut self) {
+        if self.dirty && self.last_save.elapsed() >= SAVE_INTERVAL {
+            self.save();
+        }
+    }
+
+    /// Persist the current entry immediately.
+    pub fn flush(&mut self) {
+        if self.dirty {
+            self.save();
+        }
+    }
+
+    fn save(&mut self) {
+        let Some(entry) = self.current.as_ref() else {
+            return;
+        };
+
+        let store = match self.app.store(HISTORY_STORE) {
+            Ok(store) => store,
+            Err(e) => {
+                log::error!("Failed to open playback history store: {}", e);
+                return;
+            }
+        };
+
+        // Anything not worth resuming replaces what was stored before, e.g.
+        // after seeking back to the start or watching to the end
+        if entry.is_resumable() {
+            store.set(entry.key(), json!(entry));
+        } else {
+            store.delete(entry.key());
+        }
+
+        for key in stale_keys(store.entries(), MAX_ENTRIES) {
+            store.delete(key);
+        }
+
+        if let Err(e) = store.save() {
+            log::error!("Failed to save playback history: {}", e);
+        }
+
+        self.dirty = false;
+        self.last_save = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn entry(item_id: Option<&str>, position: f64, updated_at: i64) -> HistoryEntry {
+        HistoryEntry {
+            item_id: item_id.map(str::to_string),
+            url: "https://example.com/Videos/1/stream?api_key=secret".to_string(),
+            position,
+            duration: 1000.0,
+            aid: None,
+            sid: None,
+            updated_at,
+        }
+    }
+
+    fn store(entries: &[HistoryEntry]) -> HashMap<String, JsonValue> {
+        entries
+            .iter()
+            .map(|entry| (entry.key(), json!(entry)))
+            .collect()
+    }
+
+    fn lookup(store: &HashMap<String, JsonValue>, item_id: Option<&str>) -> Option<HistoryEntry> {
+        find_resumable(
+            |key| store.get(key).cloned(),
+            item_id,
+            "https://example.com/Videos/1/stream?api_key=other",
+        )
+    }
+
+    #[test]
+    fn only_resumes_from_the_middle() {
+        let started = store(&[entry(Some("started"), 5.0, 0)]);
+        assert!(lookup(&started, Some("started")).is_none());
+
+        let finished = store(&[entry(Some("finished"), 990.0, 0)]);
+        assert!(lookup(&finished, Some("finished")).is_none());
+
+        let watching = store(&[entry(Some("watching"), 300.0, 0)]);
+        assert_eq!(lookup(&watching, Some("watching")).unwrap().position, 300.0);
+    }
+
+    #[test]
+    fn falls_back_to_the_redacted_url() {
+        let store = store(&[entry(None, 300.0, 0)]);
+        assert_eq!(lookup(&store, Some("unknown")).unwrap().position, 300.0);
+        assert_eq!(lookup(&store, None).unwrap().position, 300.0);
+    }
+
+    #[test]
+    fn seeking_back_to_the_start_forgets_the_position() {
+        let mut store = store(&[entry(Some("item"), 300.0, 0)]);
+
+        let rewound = entry(Some("item"), 5.0, 1);
+        assert!(!rewound.is_resumable());
+        store.remove(&rewound.key());
+
+        assert!(lookup(&store, Some("item")).is_none());
+    }
+
+    #[test]
+    fn prunes_the_least_recently_updated() {
+        let mut entries = (0..5)
+            .map(|i| {
+                let entry = entry(Some(&i.to_string()), 300.0, i);
+                (entry.key(), json!(entry))
+            })
+            .collect::<Vec<_>>();
+        entries.reverse();
+
+        let mut stale = stale_keys(entries.clone(), 3);
+        stale.sort();
+        assert_eq!(stale, ["item:0", "item:1"]);
+
+        assert!(stale_keys(entries.clone(), 5).is_empty());
+
+        entries.push(("item:broken".to_string(), json!("garbage")));
+        assert_eq!(stale_keys(entries, 10), ["item:broken"]);
+    }
+}
@@ -0,0 +1,414 @@
+//! Typed client for the Jellyfin REST API, shared by everything in the
+//! backend that talks to the signed in server.
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tauri_plugin_http::reqwest::{self, Method, StatusCode};
+
+use super::models::{
+    BaseItem, ItemsQuery, ItemsResult, MediaSegment, MediaSegmentsResult, PlaybackInfoRequest,
+    PlaybackInfoResponse, PublicSystemInfo, SessionInfo, User,
+};
+use super::{JellyfinSession, SharedSession};
+
+/// Oldest server release the backend is known to work with.
+pub const MIN_SERVER_VERSION: (u32, u32, u32) = (10, 8, 0);
+
+/// Longest part of an error response body kept in `JellyfinError::Server`.
+const MAX_ERROR_BODY: usize = 200;
+
+#[derive(Debug, specta::Type, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", tag = "kind", content = "message")]
+pub enum JellyfinError {
+    NotSignedIn,
+    /// The access token was rejected; the user has to sign in again.
+    Unauthorized,
+    /// The user is not allowed to do this.
+    Forbidden(String),
+    NotFound(String),
+    /// Any other error status, with the start of the response body.
+    Server(String),
+    /// The server could not be reached.
+    Network(String),
+    /// The response did not match the expected model.
+    Decode(String),
+    /// The server is older than `MIN_SERVER_VERSION`.
+    UnsupportedVersion(String),
+}
+
+impl std::fmt::Display for JellyfinError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JellyfinError::NotSignedIn => write!(f, "not signed in to a Jellyfin server"),
+            JellyfinError::Unauthorized => write!(f, "the server rejected the access token"),
+            JellyfinError::Forbidden(path) => write!(f, "not allowed to access {}", path),
+            JellyfinError::NotFound(path) => write!(f, "{} not found on the server", path),
+            JellyfinError::Server(e) => write!(f, "server error: {}", e),
+            JellyfinError::Network(e) => write!(f, "request failed: {}", e),
+            JellyfinError::Decode(e) => write!(f, "unexpected response: {}", e),
+            JellyfinError::UnsupportedVersion(version) => {
+                let (major, minor, patch) = MIN_SERVER_VERSION;
+                write!(
+                    f,
+                    "Jellyfin {} is not supported, {}.{}.{} or newer is required",
+                    version, major, minor, patch
+                )
+            }
+        }
+    }
+}
+
+impl From<JellyfinError> for String {
+    fn from(e: JellyfinError) -> Self {
+        e.to_string()
+    }
+}
+
+impl From<reqwest::Error> for JellyfinError {
+    fn from(e: reqwest::Error) -> Self {
+        JellyfinError::Network(e.to_string())
+    }
+}
+
+/// Basic details of a server, for the sign in screen.
+#[derive(Debug, specta::Type, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct JellyfinServerInfo {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+}
+
+impl From<PublicSystemInfo> for JellyfinServerInfo {
+    fn from(info: PublicSystemInfo) -> Self {
+        Self {
+            id: info.id,
+            name: info.server_name,
+            version: info.version,
+        }
+    }
+}
+
+/// `10.10.3` as `(10, 10, 3)`. Pre-release suffixes like `-rc1` are ignored.
+fn parse_version(version: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = version
+        .split(['-', '+'])
+        .next()?
+        .split('.')
+        .map(|part| part.parse::<u32>().ok());
+
+    let major = parts.next()??;
+    let minor = parts.next().flatten().unwrap_or(0);
+    let patch = parts.next().flatten().unwrap_or(0);
+    Some((major, minor, patch))
+}
+
+/// Check a server release against `MIN_SERVER_VERSION`.
+pub fn check_version(version: &str) -> Result<(), JellyfinError> {
+    match parse_version(version) {
+        Some(parsed) if parsed >= MIN_SERVER_VERSION => Ok(()),
+        _ => Err(JellyfinError::UnsupportedVersion(version.to_string())),
+    }
+}
+
+/// Client for the signed in server. Cheap to clone; every clone shares the
+/// same connection pool and session.
+#[derive(Clone)]
+pub struct JellyfinClient {
+    http: reqwest::Client,
+    session: SharedSession,
+}
+
+impl JellyfinClient {
+    pub fn new(http: reqwest::Client, session: SharedSession) -> Self {
+        Self { http, session }
+    }
+
+    pub fn session(&self) -> Result<JellyfinSession, JellyfinError> {
+        self.session
+            .read()
+            .unwrap()
+            .clone()
+            .ok_or(JellyfinError::NotSignedIn)
+    }
+
+    pub fn is_signed_in(&self) -> bool {
+        self.session.read().unwrap().is_some()
+    }
+
+    /// Send an authenticated request and map error statuses to `JellyfinError`.
+    pub async fn request<B: Serialize + ?Sized>(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, &str)],
+        body: Option<&B>,
+    ) -> Result<reqwest::Response, JellyfinError> {
+        let session = self.session()?;
+
+        let mut request = self
+            .http
+            .request(method, session.url(path))
+            .header("Authorization", session.authorization_header())
+            .query(query);
+        if let Some(body) = body {
+            let body = serde_json::to_string(body)
+                .map_err(|e| JellyfinError::Decode(format!("invalid request body: {}", e)))?;
+            request = request
+                .header("Content-Type", "application/json")
+                .body(body);
+        }
+
+        check_status(path, request.send().await?).await
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, JellyfinError> {
+        self.get_with_query(path, &[]).await
+    }
+
+    pub async fn get_with_query<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T, JellyfinError> {
+        let response = self.request::<()>(Method::GET, path, query, None).await?;
+        decode(response).await
+    }
+
+    pub async fn post<B: Serialize + ?Sized>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<(), JellyfinError> {
+        self.request(Method::POST, path, &[], Some(body)).await?;
+        Ok(())
+    }
+
+    pub async fn post_for<B: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T, JellyfinError> {
+        let response = self.request(Method::POST, path, &[], Some(body)).await?;
+        decode(response).await
+    }
+
+    /// Public details of the server at `server_url`, which need no session.
+    /// Fails for servers older than `MIN_SERVER_VERSION`.
+    pub async fn server_info(&self, server_url: &str) -> Result<PublicSystemInfo, JellyfinError> {
+        let path = "/System/Info/Public";
+        let url = format!("{}{}", server_url.trim_end_matches('/'), path);
+
+        let response = check_status(path, self.http.get(url).send().await?).await?;
+        let info: PublicSystemInfo = decode(response).await?;
+        check_version(&info.version)?;
+        Ok(info)
+    }
+
+    pub async fn current_user(&self) -> Result<User, JellyfinError> {
+        self.get("/Users/Me").await
+    }
+
+    pub async fn item(&self, item_id: &str) -> Result<BaseItem, JellyfinError> {
+        let session = self.session()?;
+        self.get(&format!("/Users/{}/Items/{}", session.user_id, item_id))
+            .await
+    }
+
+    pub async fn items(&self, query: &ItemsQuery) -> Result<ItemsResult, JellyfinError> {
+        let session = self.session()?;
+        let query = serde_json::to_value(query)
+            .map_err(|e| JellyfinError::Decode(format!("invalid query: {}", e)))?;
+
+        let mut pairs = vec![("userId".to_string(), session.user_id)];
+        if let Some(query) = query.as_object() {
+            for (key, value) in query {
+                let value = match value {
+                    serde_json::Value::String(value) => value.clone(),
+                    value => value.to_string(),
+                };
+                pairs.push((key.clone(), value));
+            }
+        }
+
+        let pairs: Vec<(&str, &str)> = pairs
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        self.get_with_query("/Items", &pairs).await
+    }
+
+    pub async fn playback_info(
+        &self,
+        item_id: &str,
+        request: &PlaybackInfoRequest,
+    ) -> Result<PlaybackInfoResponse, JellyfinError> {
+        let mut request = request.clone();
+        if request.user_id.is_none() {
+            request.user_id = Some(self.session()?.user_id);
+        }
+
+        self.post_for(&format!("/Items/{}/PlaybackInfo", item_id), &request)
+            .await
+    }
+
+    /// Sessions visible to the user; non-admins only see their own.
+    pub async fn sessions(&self) -> Result<Vec<SessionInfo>, JellyfinError> {
+        self.get("/Sessions").await
+    }
+
+    /// Intro, outro and other segments of an item. Servers before 10.10
+    /// have no segments and report none.
+    pub async fn media_segments(&self, item_id: &str) -> Result<Vec<MediaSegment>, JellyfinError> {
+        match self
+            .get::<MediaSegmentsResult>(&format!("/MediaSegments/{}", item_id))
+            .await
+        {
+            Ok(result) => Ok(result.items),
+            Err(JellyfinError::NotFound(_)) => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+async fn check_status(
+    path: &str,
+    response: reqwest::Response,
+) -> Result<reqwest::Response, JellyfinError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    match status {
+        StatusCode::UNAUTHORIZED => Err(JellyfinError::Unauthorized),
+        StatusCode::FORBIDDEN => Err(JellyfinError::Forbidden(path.to_string())),
+        StatusCode::NOT_FOUND => Err(JellyfinError::NotFound(path.to_string())),
+        _ => {
+            let body = response.text().await.unwrap_or_default();
+            let body: String = body.trim().chars().take(MAX_ERROR_BODY).collect();
+            Err(JellyfinError::Server(if body.is_empty() {
+                status.to_string()
+            } else {
+                format!("{}: {}", status, body)
+            }))
+        }
+    }
+}
+
+async fn decode<T: DeserializeOwned>(response: reqwest::Response) -> Result<T, JellyfinError> {
+    let body = response.bytes().await?;
+    serde_json::from_slice(&body).map_err(|e| JellyfinError::Decode(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jellyfin::mock::{MockServer, ACCESS_TOKEN, USER_ID};
+
+    #[test]
+    fn parses_versions() {
+        assert_eq!(parse_version("10.10.3"), Some((10, 10, 3)));
+        assert_eq!(parse_version("10.10.3-rc1"), Some((10, 10, 3)));
+        assert_eq!(parse_version("10.9"), Some((10, 9, 0)));
+        assert_eq!(parse_version("garbage"), None);
+        assert_eq!(parse_version(""), None);
+
+        assert!(check_version("10.10.3-rc1").is_ok());
+        assert!(check_version("10.8.0").is_ok());
+        assert!(matches!(
+            check_version("10.7.7"),
+            Err(JellyfinError::UnsupportedVersion(version)) if version == "10.7.7"
+        ));
+        assert!(matches!(
+            check_version("garbage"),
+            Err(JellyfinError::UnsupportedVersion(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn sends_the_session_authorization() {
+        let server = MockServer::start().await;
+        server.respond("/Items", 200, r#"{ "Items": [], "TotalRecordCount": 0 }"#);
+
+        let query = ItemsQuery::default();
+        server.client().items(&query).await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, Method::GET);
+        assert_eq!(requests[0].path, "/Items");
+        assert!(requests[0]
+            .query
+            .as_deref()
+            .unwrap_or_default()
+            .contains(&format!("userId={}", USER_ID)));
+
+        let authorization = requests[0].authorization.as_deref().unwrap();
+        assert!(authorization.starts_with("MediaBrowser "));
+        assert!(authorization.contains(&format!("Token=\"{}\"", ACCESS_TOKEN)));
+        assert!(authorization.contains("DeviceId=\"device-1\""));
+    }
+
+    #[tokio::test]
+    async fn maps_error_statuses() {
+        let server = MockServer::start().await;
+        let client = server.client();
+        server.respond("/Users/Me", 401, "");
+        server.respond("/Users/Me", 403, "");
+        server.respond("/Users/Me", 404, "");
+        server.respond("/Users/Me", 503, "  down for maintenance  ");
+        server.respond("/Users/Me", 500, "");
+
+        assert!(matches!(
+            client.current_user().await,
+            Err(JellyfinError::Unauthorized)
+        ));
+        assert!(matches!(
+            client.current_user().await,
+            Err(JellyfinError::Forbidden(path)) if path == "/Users/Me"
+        ));
+        assert!(matches!(
+            client.current_user().await,
+            Err(JellyfinError::NotFound(path)) if path == "/Users/Me"
+        ));
+        assert!(matches!(
+            client.current_user().await,
+            Err(JellyfinError::Server(message))
+                if message == "503 Service Unavailable: down for maintenance"
+        ));
+        assert!(matches!(
+            client.current_user().await,
+            Err(JellyfinError::Server(message)) if message == "500 Internal Server Error"
+        ));
+    }
+
+    #[tokio::test]
+    async fn refuses_old_servers() {
+        let server = MockServer::start().await;
+        let info = |version: &str| {
+            format!(
+                r#"{{ "Id": "server-1", "ServerName": "Home", "Version": "{}" }}"#,
+                version
+            )
+        };
+        server.respond("/System/Info/Public", 200, info("10.7.7"));
+        server.respond("/System/Info/Public", 200, info("10.10.3-rc1"));
+
+        let client = server.client();
+        assert!(matches!(
+            client.server_info(&server.url).await,
+            Err(JellyfinError::UnsupportedVersion(version)) if version == "10.7.7"
+        ));
+        let info = client.server_info(&server.url).await.unwrap();
+        assert_eq!(info.version, "10.10.3-rc1");
+    }
+
+    #[tokio::test]
+    async fn missing_media_segments_are_empty() {
+        let server = MockServer::start().await;
+        server.respond("/MediaSegments/item-1", 404, "");
+
+        let segments = server.client().media_segments("item-1").await.unwrap();
+        assert!(segments.is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+//! Finds Jellyfin servers on the local network with the UDP discovery
+//! protocol: a broadcast on port 7359 that every server answers with its
+//! address, id and name.
+
+use std::{
+    collections::HashSet,
+    net::{Ipv4Addr, SocketAddrV4},
+    time::Duration,
+};
+
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
+use tokio::{net::UdpSocket, time::Instant};
+
+const DISCOVERY_PORT: u16 = 7359;
+const DISCOVERY_MESSAGE: &[u8] = b"Who is JellyfinServer?";
+
+/// A server that answered the discovery broadcast.
+#[derive(Debug, specta::Type, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredServer {
+    pub id: String,
+    pub name: String,
+    /// Base URL the server advertises, e.g. `http://192.168.1.10:8096`.
+    pub address: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DiscoveryReply {
+    id: String,
+    name: String,
+    address: String,
+}
+
+/// Local IPv4 addresses to send from, each with its broadcast address.
+fn broadcast_targets() -> Vec<(Ipv4Addr, Ipv4Addr)> {
+    let interfaces = match if_addrs::get_if_addrs() {
+        Ok(interfaces) => interfaces,
+        Err(e) => {
+            log::warn!("Failed to list network interfaces: {}", e);
+            Vec::new()
+        }
+    };
+
+    let mut targets: Vec<_> = interfaces
+        .into_iter()
+        .filter(|interface| !interface.is_loopback())
+        .filter_map(|interface| match interface.addr {
+            if_addrs::IfAddr::V4(addr) => {
+                let broadcast = addr.broadcast.unwrap_or_else(|| {
+                    Ipv4Addr::from(u32::from(addr.ip) | !u32::from(addr.netmask))
+                });
+                Some((addr.ip, broadcast))
+            }
+            if_addrs::IfAddr::V6(_) => None,
+        })
+        .collect();
+
+    // Let the OS pick the interface when none could be listed
+    if targets.is_empty() {
+        targets.push((Ipv4Addr::UNSPECIFIED, Ipv4Addr::BROADCAST));
+    }
+    targets
+}
+
+/// Broadcast from `local` and collect replies until `deadline`.
+async fn discover_from(
+    local: Ipv4Addr,
+    broadcast: Ipv4Addr,
+    deadline: Instant,
+) -> Result<Vec<DiscoveryReply>, String> {
+    let socket = UdpSocket::bind(SocketAddrV4::new(local, 0))
+        .await
+        .map_err(|e| format!("Failed to bind {}: {}", local, e))?;
+    socket
+        .set_broadcast(true)
+        .map_err(|e| format!("Failed to enable broadcast on {}: {}", local, e))?;
+
+    for target in [broadcast, Ipv4Addr::BROADCAST] {
+        if let Err(e) = socket
+            .send_to(DISCOVERY_MESSAGE, SocketAddrV4::new(target, DISCOVERY_PORT))
+            .await
+        {
+            log::debug!(
+                "Discovery broadcast from {} to {} failed: {}",
+                local,
+                target,
+                e
+            );
+        }
+    }
+
+    let mut replies = Vec::new();
+    let mut buffer = [0u8; 4096];
+
+    loop {
+        let received = tokio::time::timeout_at(deadline, socket.recv_from(&mut buffer)).await;
+        let (len, from) = match received {
+            Err(_) => break,
+            Ok(Ok(received)) => received,
+            Ok(Err(e)) => {
+                log::debug!("Discovery receive on {} failed: {}", local, e);
+                break;
+            }
+        };
+
+        match serde_json::from_slice::<DiscoveryReply>(&buffer[..len]) {
+            Ok(reply) => replies.push(reply),
+            Err(e) => log::debug!("Ignoring malformed discovery reply from {}: {}", from, e),
+        }
+    }
+
+    Ok(replies)
+}
+
+/// Broadcast the discovery message on every local interface and return the
+/// servers that answered within `timeout`, once each, sorted by name.
+pub async fn discover(timeout: Duration) -> Vec<DiscoveredServer> {
+    let deadline = Instant::now() + timeout;
+    let targets = broadcast_targets();
+
+    let results = join_all(
+        targets
+            .iter()
+            .map(|(local, broadcast)| discover_from(*local, *broadcast, deadline)),
+    )
+    .await;
+
+    let mut seen = HashSet::new();
+    let mut servers = Vec::new();
+
+    for result in results {
+        let replies = match result {
+            Ok(replies) => replies,
+            Err(e) => {
+                log::debug!("Server discovery skipped an interface: {}", e);
+                continue;
+            }
+        };
+
+        // Servers on several networks answer every broadcast
+        for reply in replies {
+            if seen.insert(reply.id.clone()) {
+                servers.push(DiscoveredServer {
+                    id: reply.id,
+                    name: reply.name,
+                    address: reply.address,
+                });
+            }
+        }
+    }
+
+    servers.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    log::info!("Discovered {} Jellyfin servers", servers.len());
+    servers
+}
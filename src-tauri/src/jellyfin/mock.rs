@@ -0,0 +1,145 @@
+//! Mock Jellyfin server for tests. Records every request and answers with
+//! queued responses, or an empty `204` once the queue for a path runs out.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    net::{Ipv4Addr, SocketAddr},
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, Method, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    Router,
+};
+use tauri_plugin_http::reqwest;
+
+use super::client::JellyfinClient;
+use super::JellyfinSession;
+
+pub const ACCESS_TOKEN: &str = "test-token";
+pub const USER_ID: &str = "user-1";
+
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: Method,
+    pub path: String,
+    pub query: Option<String>,
+    pub authorization: Option<String>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).unwrap()
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    requests: Vec<RecordedRequest>,
+    responses: HashMap<String, VecDeque<(StatusCode, String)>>,
+}
+
+pub struct MockServer {
+    pub url: String,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockServer {
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let router = Router::new().fallback(handle).with_state(state.clone());
+
+        let listener = tokio::net::TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+            .await
+            .unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        Self { url, state }
+    }
+
+    /// Answer the next request to `path` with `status` and `body`.
+    pub fn respond(&self, path: &str, status: u16, body: impl Into<String>) {
+        self.state
+            .lock()
+            .unwrap()
+            .responses
+            .entry(path.to_string())
+            .or_default()
+            .push_back((StatusCode::from_u16(status).unwrap(), body.into()));
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Wait until at least `count` requests arrived, failing after 10 seconds.
+    pub async fn wait_for_requests(&self, count: usize) -> Vec<RecordedRequest> {
+        let wait = async {
+            loop {
+                let requests = self.requests();
+                if requests.len() >= count {
+                    return requests;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+
+        tokio::time::timeout(Duration::from_secs(10), wait)
+            .await
+            .unwrap_or_else(|_| panic!("expected {} requests, got {:?}", count, self.requests()))
+    }
+
+    pub fn session(&self) -> JellyfinSession {
+        JellyfinSession {
+            server_url: self.url.clone(),
+            access_token: ACCESS_TOKEN.to_string(),
+            user_id: USER_ID.to_string(),
+            device_id: "device-1".to_string(),
+            device_name: "Test".to_string(),
+        }
+    }
+
+    /// A client signed in to this server.
+    pub fn client(&self) -> JellyfinClient {
+        JellyfinClient::new(
+            reqwest::Client::new(),
+            Arc::new(RwLock::new(Some(self.session()))),
+        )
+    }
+}
+
+async fn handle(
+    State(state): State<Arc<Mutex<MockState>>>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    let mut state = state.lock().unwrap();
+    state.requests.push(RecordedRequest {
+        method,
+        path: uri.path().to_string(),
+        query: uri.query().map(str::to_string),
+        authorization: headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string),
+        body,
+    });
+
+    match state
+        .responses
+        .get_mut(uri.path())
+        .and_then(VecDeque::pop_front)
+    {
+        Some((status, body)) => {
+            (status, [(header::CONTENT_TYPE, "application/json")], body).into_response()
+        }
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
@@ -0,0 +1,61 @@
+use std::sync::{Arc, RwLock};
+
+use serde::{Deserialize, Serialize};
+
+pub mod client;
+pub mod discovery;
+#[cfg(test)]
+mod mock;
+pub mod models;
+pub mod remote;
+pub mod reporter;
+pub mod socket;
+pub mod syncplay;
+
+/// Client name sent to the server in the `Authorization` header.
+const CLIENT_NAME: &str = "square";
+
+/// Credentials of the Jellyfin server the frontend is signed in to, so the
+/// backend can talk to it on its own.
+#[derive(specta::Type, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct JellyfinSession {
+    pub server_url: String,
+    pub access_token: String,
+    pub user_id: String,
+    pub device_id: String,
+    pub device_name: String,
+}
+
+/// The active session, `None` while signed out.
+pub type SharedSession = Arc<RwLock<Option<JellyfinSession>>>;
+
+impl JellyfinSession {
+    /// Value for the `Authorization` header Jellyfin expects from clients.
+    pub fn authorization_header(&self) -> String {
+        format!(
+            "MediaBrowser Client=\"{}\", Device=\"{}\", DeviceId=\"{}\", Version=\"{}\", Token=\"{}\"",
+            CLIENT_NAME,
+            self.device_name,
+            self.device_id,
+            env!("CARGO_PKG_VERSION"),
+            self.access_token
+        )
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.server_url.trim_end_matches('/'), path)
+    }
+}
+
+// Keep the access token out of logs
+impl std::fmt::Debug for JellyfinSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JellyfinSession")
+            .field("server_url", &self.server_url)
+            .field("user_id", &self.user_id)
+            .field("device_id", &self.device_id)
+            .field("device_name", &self.device_name)
+            .finish()
+    }
+}
@@ -0,0 +1,281 @@
+//! Jellyfin API models, as the server sends them. Only the fields the backend
+//! uses are declared; everything else in a response is ignored.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// `/System/Info/Public`, readable without signing in.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct PublicSystemInfo {
+    pub id: String,
+    pub server_name: String,
+    pub version: String,
+    #[serde(default)]
+    pub product_name: Option<String>,
+    #[serde(default)]
+    pub local_address: Option<String>,
+    #[serde(default)]
+    pub startup_wizard_completed: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct User {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub server_id: Option<String>,
+    #[serde(default)]
+    pub has_password: bool,
+    #[serde(default)]
+    pub policy: Option<UserPolicy>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct UserPolicy {
+    #[serde(default)]
+    pub is_administrator: bool,
+    #[serde(default)]
+    pub enable_content_downloading: bool,
+}
+
+/// Per-user state of an item.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct UserItemData {
+    pub playback_position_ticks: i64,
+    pub play_count: u32,
+    pub played: bool,
+    pub is_favorite: bool,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct BaseItem {
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// `Movie`, `Episode`, `Series`, `Audio` and so on.
+    #[serde(rename = "Type", default)]
+    pub item_type: Option<String>,
+    #[serde(default)]
+    pub media_type: Option<String>,
+    #[serde(default)]
+    pub series_id: Option<String>,
+    #[serde(default)]
+    pub series_name: Option<String>,
+    #[serde(default)]
+    pub season_id: Option<String>,
+    /// Episode number.
+    #[serde(default)]
+    pub index_number: Option<u32>,
+    /// Season number.
+    #[serde(default)]
+    pub parent_index_number: Option<u32>,
+    #[serde(default)]
+    pub run_time_ticks: Option<i64>,
+    #[serde(default)]
+    pub user_data: Option<UserItemData>,
+    #[serde(default)]
+    pub media_sources: Vec<MediaSource>,
+    /// Image type, e.g. `Primary`, to image tag.
+    #[serde(default)]
+    pub image_tags: HashMap<String, String>,
+}
+
+/// Page of items from `/Items`.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct ItemsResult {
+    pub items: Vec<BaseItem>,
+    #[serde(default)]
+    pub total_record_count: u32,
+    #[serde(default)]
+    pub start_index: u32,
+}
+
+/// Filters for `/Items`; unset fields are left out of the query.
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemsQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    /// Comma separated, e.g. `Movie,Episode`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_item_types: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recursive: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search_term: Option<String>,
+    /// Comma separated extra fields, e.g. `MediaSources`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort_by: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_index: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct MediaSource {
+    pub id: String,
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub container: Option<String>,
+    #[serde(default)]
+    pub protocol: Option<String>,
+    #[serde(default)]
+    pub run_time_ticks: Option<i64>,
+    #[serde(default)]
+    pub bitrate: Option<i64>,
+    #[serde(default)]
+    pub supports_direct_play: bool,
+    #[serde(default)]
+    pub supports_direct_stream: bool,
+    #[serde(default)]
+    pub supports_transcoding: bool,
+    /// Relative to the server URL.
+    #[serde(default)]
+    pub transcoding_url: Option<String>,
+    #[serde(default)]
+    pub media_streams: Vec<MediaStream>,
+    #[serde(default)]
+    pub default_audio_stream_index: Option<u32>,
+    #[serde(default)]
+    pub default_subtitle_stream_index: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct MediaStream {
+    pub index: u32,
+    /// `Video`, `Audio`, `Subtitle` or `Attachment`.
+    #[serde(rename = "Type")]
+    pub stream_type: String,
+    #[serde(default)]
+    pub codec: Option<String>,
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub display_title: Option<String>,
+    #[serde(default)]
+    pub is_default: bool,
+    #[serde(default)]
+    pub is_forced: bool,
+    #[serde(default)]
+    pub is_external: bool,
+    /// Subtitle download URL, relative to the server URL.
+    #[serde(default)]
+    pub delivery_url: Option<String>,
+}
+
+/// Body of `/Items/{id}/PlaybackInfo`; unset fields use the server defaults.
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct PlaybackInfoRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_source_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_time_ticks: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio_stream_index: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtitle_stream_index: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_streaming_bitrate: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enable_direct_play: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enable_direct_stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enable_transcoding: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct PlaybackInfoResponse {
+    #[serde(default)]
+    pub media_sources: Vec<MediaSource>,
+    #[serde(default)]
+    pub play_session_id: Option<String>,
+    /// Why the item cannot be played, e.g. `NotAllowed`.
+    #[serde(default)]
+    pub error_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct PlayerState {
+    pub position_ticks: Option<i64>,
+    pub is_paused: bool,
+    pub is_muted: bool,
+    pub can_seek: bool,
+    pub volume_level: Option<u32>,
+}
+
+/// A client connected to the server, from `/Sessions`.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct SessionInfo {
+    pub id: String,
+    #[serde(default)]
+    pub user_id: Option<String>,
+    #[serde(default)]
+    pub user_name: Option<String>,
+    #[serde(default)]
+    pub client: Option<String>,
+    #[serde(default)]
+    pub device_id: Option<String>,
+    #[serde(default)]
+    pub device_name: Option<String>,
+    #[serde(default)]
+    pub application_version: Option<String>,
+    #[serde(default)]
+    pub supports_remote_control: bool,
+    #[serde(default)]
+    pub now_playing_item: Option<BaseItem>,
+    #[serde(default)]
+    pub play_state: Option<PlayerState>,
+    #[serde(default)]
+    pub last_activity_date: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub enum MediaSegmentType {
+    Intro,
+    Outro,
+    Recap,
+    Preview,
+    Commercial,
+    #[serde(other)]
+    Unknown,
+}
+
+/// A marked part of an item, e.g. its intro. Needs Jellyfin 10.10.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct MediaSegment {
+    pub id: String,
+    pub item_id: String,
+    #[serde(rename = "Type")]
+    pub segment_type: MediaSegmentType,
+    pub start_ticks: i64,
+    pub end_ticks: i64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub(super) struct MediaSegmentsResult {
+    pub items: Vec<MediaSegment>,
+}
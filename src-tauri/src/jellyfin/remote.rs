@@ -0,0 +1,361 @@
+//! Makes square a controllable Jellyfin session, so other clients can "Play
+//! On" it and drive playback. Commands arrive over the session socket and
+//! are routed into `PlaybackEvent`s and the window commands.
+
+use std::sync::mpsc::Sender;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager};
+use tauri_specta::Event;
+use tokio::sync::{
+    broadcast,
+    mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    oneshot,
+};
+
+use super::client::JellyfinClient;
+use super::socket::{SessionSocket, SocketEvent};
+use crate::mpv::{
+    MediaControlAction, MediaControlEvent, PlayBackStateChange, PlaybackEvent, VolumeEventChange,
+};
+use crate::properties::{MpvFormat, MpvValue};
+
+const TICKS_PER_SECOND: f64 = 10_000_000.0;
+
+/// Step for the remote volume up and down buttons, in percent.
+const VOLUME_STEP: u8 = 5;
+
+/// Seconds skipped by the remote rewind and fast forward buttons.
+const REWIND_SECS: f64 = 10.0;
+const FAST_FORWARD_SECS: f64 = 30.0;
+
+/// General commands advertised to the server; playstate commands are
+/// covered by `SupportsMediaControl`.
+const SUPPORTED_COMMANDS: &[&str] = &[
+    "VolumeUp",
+    "VolumeDown",
+    "Mute",
+    "Unmute",
+    "ToggleMute",
+    "SetVolume",
+    "SetAudioStreamIndex",
+    "SetSubtitleStreamIndex",
+    "ToggleFullscreen",
+    "DisplayMessage",
+];
+
+/// Remote control requests the frontend has to carry out, as they need the
+/// library and navigation state only it has.
+#[derive(Debug, specta::Type, tauri_specta::Event, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum RemoteControlEvent {
+    /// Load `item_ids` and start at `start_index`.
+    Play {
+        item_ids: Vec<String>,
+        start_index: u32,
+        start_position: f64,
+        media_source_id: Option<String>,
+        audio_stream_index: Option<u32>,
+        subtitle_stream_index: Option<i32>,
+    },
+    /// Playback was stopped; leave the player.
+    Stop,
+    DisplayMessage {
+        header: String,
+        text: String,
+        timeout_ms: Option<u32>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct PlayRequest {
+    item_ids: Vec<String>,
+    /// `PlayNow`, `PlayNext`, `PlayLast`, `PlayInstantMix` or `PlayShuffle`.
+    #[serde(default)]
+    play_command: Option<String>,
+    #[serde(default)]
+    start_index: Option<u32>,
+    #[serde(default)]
+    start_position_ticks: Option<i64>,
+    #[serde(default)]
+    media_source_id: Option<String>,
+    #[serde(default)]
+    audio_stream_index: Option<u32>,
+    #[serde(default)]
+    subtitle_stream_index: Option<i32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct PlaystateRequest {
+    command: String,
+    #[serde(default)]
+    seek_position_ticks: Option<i64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct GeneralCommand {
+    name: String,
+    #[serde(default)]
+    arguments: std::collections::HashMap<String, String>,
+}
+
+/// Player state change picked up from the render thread's events.
+enum PlayerUpdate {
+    Paused(bool),
+    Volume(u8),
+}
+
+pub fn init(
+    app: &AppHandle,
+    render_tx: Sender<PlaybackEvent>,
+    client: JellyfinClient,
+    socket: &SessionSocket,
+) {
+    let (player_tx, player_rx) = unbounded_channel();
+    listen(app, player_tx);
+
+    let remote = RemoteControl {
+        app: app.clone(),
+        render_tx,
+        client,
+        paused: true,
+        volume: 100,
+    };
+    tauri::async_runtime::spawn(remote.run(socket.subscribe(), player_rx));
+}
+
+fn listen(app: &AppHandle, player_tx: UnboundedSender<PlayerUpdate>) {
+    let tx = player_tx.clone();
+    PlayBackStateChange::listen_any(app, move |event| {
+        tx.send(PlayerUpdate::Paused(event.payload.pause)).ok();
+    });
+
+    VolumeEventChange::listen_any(app, move |event| {
+        player_tx
+            .send(PlayerUpdate::Volume(event.payload.percentage))
+            .ok();
+    });
+}
+
+struct RemoteControl {
+    app: AppHandle,
+    render_tx: Sender<PlaybackEvent>,
+    client: JellyfinClient,
+    paused: bool,
+    volume: u8,
+}
+
+impl RemoteControl {
+    async fn run(
+        mut self,
+        mut socket_rx: broadcast::Receiver<SocketEvent>,
+        mut player_rx: UnboundedReceiver<PlayerUpdate>,
+    ) {
+        loop {
+            tokio::select! {
+                event = socket_rx.recv() => match event {
+                    // The server forgets capabilities when the socket drops
+                    Ok(SocketEvent::Connected) => {
+                        if let Err(e) = self.report_capabilities().await {
+                            log::warn!("Failed to register remote control capabilities: {}", e);
+                        }
+                    }
+                    Ok(SocketEvent::Message { message_type, data }) => {
+                        self.handle_message(&message_type, data);
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        log::warn!("Remote control missed {} session socket messages", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                update = player_rx.recv() => match update {
+                    Some(PlayerUpdate::Paused(paused)) => self.paused = paused,
+                    Some(PlayerUpdate::Volume(volume)) => self.volume = volume,
+                    None => break,
+                },
+            }
+        }
+
+        log::info!("Remote control task stopped");
+    }
+
+    async fn report_capabilities(&self) -> Result<(), String> {
+        let body = json!({
+            "PlayableMediaTypes": ["Video", "Audio"],
+            "SupportedCommands": SUPPORTED_COMMANDS,
+            "SupportsMediaControl": true,
+            "SupportsPersistentIdentifier": true,
+        });
+
+        self.client
+            .post("/Sessions/Capabilities/Full", &body)
+            .await?;
+
+        log::info!("Registered as a remote controllable Jellyfin session");
+        Ok(())
+    }
+
+    fn send(&self, event: PlaybackEvent) {
+        if self.render_tx.send(event).is_err() {
+            log::warn!("Render thread is not running, dropping remote command");
+        }
+    }
+
+    fn emit(&self, event: RemoteControlEvent) {
+        event.emit(&self.app).ok();
+    }
+
+    fn handle_message(&mut self, message_type: &str, data: Value) {
+        let result = match message_type {
+            "Play" => serde_json::from_value(data).map(|request| self.play(request)),
+            "Playstate" => serde_json::from_value(data).map(|request| self.playstate(request)),
+            "GeneralCommand" => {
+                serde_json::from_value(data).map(|command| self.general_command(command))
+            }
+            _ => return,
+        };
+
+        if let Err(e) = result {
+            log::warn!("Ignoring malformed {} remote command: {}", message_type, e);
+        }
+    }
+
+    fn play(&self, request: PlayRequest) {
+        // There is no queue to add to, and replacing playback instead would
+        // cut off what is playing
+        if let Some(command) = request.play_command.as_deref().filter(|c| *c != "PlayNow") {
+            log::info!("Ignoring unsupported remote play command {}", command);
+            return;
+        }
+
+        log::info!("Remote play request for {} items", request.item_ids.len());
+        self.show_window();
+
+        self.emit(RemoteControlEvent::Play {
+            item_ids: request.item_ids,
+            start_index: request.start_index.unwrap_or(0),
+            start_position: request.start_position_ticks.unwrap_or(0) as f64 / TICKS_PER_SECOND,
+            media_source_id: request.media_source_id,
+            audio_stream_index: request.audio_stream_index,
+            subtitle_stream_index: request.subtitle_stream_index,
+        });
+    }
+
+    fn playstate(&self, request: PlaystateRequest) {
+        log::debug!("Remote playstate command {}", request.command);
+
+        match request.command.as_str() {
+            "Pause" => self.send(PlaybackEvent::Pause),
+            "Unpause" => self.send(PlaybackEvent::Play),
+            "PlayPause" if self.paused => self.send(PlaybackEvent::Play),
+            "PlayPause" => self.send(PlaybackEvent::Pause),
+            "Stop" => {
+                self.send(PlaybackEvent::Clear);
+                self.emit(RemoteControlEvent::Stop);
+            }
+            "Seek" => {
+                let ticks = request.seek_position_ticks.unwrap_or(0);
+                self.send(PlaybackEvent::AbsoluteSeek(ticks as f64 / TICKS_PER_SECOND));
+            }
+            "Rewind" => self.send(PlaybackEvent::Seek(-REWIND_SECS)),
+            "FastForward" => self.send(PlaybackEvent::Seek(FAST_FORWARD_SECS)),
+            "NextTrack" => self.media_control(MediaControlAction::Next),
+            "PreviousTrack" => self.media_control(MediaControlAction::Previous),
+            command => log::debug!("Ignoring remote playstate command {}", command),
+        }
+    }
+
+    fn general_command(&mut self, command: GeneralCommand) {
+        log::debug!("Remote general command {}", command.name);
+        let argument = |name: &str| command.arguments.get(name).map(String::as_str);
+
+        match command.name.as_str() {
+            "SetVolume" => {
+                if let Some(volume) = argument("Volume").and_then(|v| v.parse::<u8>().ok()) {
+                    self.set_volume(volume);
+                }
+            }
+            "VolumeUp" => self.set_volume(self.volume.saturating_add(VOLUME_STEP).min(100)),
+            "VolumeDown" => self.set_volume(self.volume.saturating_sub(VOLUME_STEP)),
+            "Mute" => self.set_mute(Some(true)),
+            "Unmute" => self.set_mute(Some(false)),
+            "ToggleMute" => self.set_mute(None),
+            "SetAudioStreamIndex" => {
+                if let Some(index) = argument("Index").and_then(|v| v.parse().ok()) {
+                    self.send(PlaybackEvent::AudioStreamIndex(index));
+                }
+            }
+            "SetSubtitleStreamIndex" => {
+                if let Some(index) = argument("Index").and_then(|v| v.parse().ok()) {
+                    self.send(PlaybackEvent::SubtitleStreamIndex(index));
+                }
+            }
+            "ToggleFullscreen" => {
+                if let Err(e) = crate::toggle_fullscreen(self.app.clone()) {
+                    log::warn!("Remote fullscreen toggle failed: {}", e);
+                }
+            }
+            "DisplayMessage" => self.emit(RemoteControlEvent::DisplayMessage {
+                header: argument("Header").unwrap_or_default().to_string(),
+                text: argument("Text").unwrap_or_default().to_string(),
+                timeout_ms: argument("TimeoutMs").and_then(|v| v.parse().ok()),
+            }),
+            name => log::debug!("Ignoring remote general command {}", name),
+        }
+    }
+
+    fn set_volume(&mut self, volume: u8) {
+        self.volume = volume;
+        self.send(PlaybackEvent::Volume(volume));
+    }
+
+    /// Set mute, or toggle it with `None`.
+    fn set_mute(&self, mute: Option<bool>) {
+        let render_tx = self.render_tx.clone();
+
+        tauri::async_runtime::spawn(async move {
+            let mute = match mute {
+                Some(mute) => mute,
+                None => {
+                    let (reply, current) = oneshot::channel();
+                    render_tx
+                        .send(PlaybackEvent::GetProperty {
+                            name: "mute".to_string(),
+                            format: MpvFormat::Flag,
+                            reply,
+                        })
+                        .ok();
+                    match current.await {
+                        Ok(Ok(MpvValue::Flag(muted))) => !muted,
+                        _ => return,
+                    }
+                }
+            };
+
+            // Nobody waits for the reply
+            let (reply, _) = oneshot::channel();
+            render_tx
+                .send(PlaybackEvent::SetProperty {
+                    name: "mute".to_string(),
+                    value: MpvValue::Flag(mute),
+                    reply,
+                })
+                .ok();
+        });
+    }
+
+    fn media_control(&self, action: MediaControlAction) {
+        MediaControlEvent { action }.emit(&self.app).ok();
+    }
+
+    fn show_window(&self) {
+        if let Some(window) = self.app.get_window("main") {
+            window.show().ok();
+            window.set_focus().ok();
+        }
+    }
+}
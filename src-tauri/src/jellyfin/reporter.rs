@@ -0,0 +1,419 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+
+use super::client::JellyfinClient;
+
+/// How often a progress report is sent while playing.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Delay before the first retry; doubled after every failed attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_ATTEMPTS: u32 = 5;
+
+const TICKS_PER_SECOND: f64 = 10_000_000.0;
+
+/// Jellyfin specific details of a load, supplied by the frontend alongside the
+/// item id so the backend can report playback to the server.
+#[derive(Debug, specta::Type, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaybackReportInfo {
+    pub media_source_id: Option<String>,
+    pub play_session_id: Option<String>,
+    /// `DirectPlay`, `DirectStream` or `Transcode`.
+    pub play_method: String,
+    /// Jellyfin stream index of each audio track, in mpv track order.
+    #[serde(default)]
+    pub audio_stream_indexes: Vec<u32>,
+    /// Jellyfin stream index of each subtitle track, in mpv track order.
+    #[serde(default)]
+    pub subtitle_stream_indexes: Vec<u32>,
+}
+
+/// Body of the `/Sessions/Playing*` requests.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct PlaybackReport {
+    pub item_id: String,
+    pub media_source_id: Option<String>,
+    pub play_session_id: Option<String>,
+    pub play_method: String,
+    pub position_ticks: i64,
+    pub is_paused: bool,
+    pub can_seek: bool,
+    pub audio_stream_index: Option<u32>,
+    /// `-1` when subtitles are off.
+    pub subtitle_stream_index: Option<i64>,
+}
+
+#[derive(Debug)]
+pub enum ReportEvent {
+    Start(PlaybackReport),
+    Progress(PlaybackReport),
+    Stopped(PlaybackReport),
+}
+
+impl ReportEvent {
+    fn endpoint(&self) -> &'static str {
+        match self {
+            ReportEvent::Start(_) => "/Sessions/Playing",
+            ReportEvent::Progress(_) => "/Sessions/Playing/Progress",
+            ReportEvent::Stopped(_) => "/Sessions/Playing/Stopped",
+        }
+    }
+
+    fn report(&self) -> &PlaybackReport {
+        match self {
+            ReportEvent::Start(report)
+            | ReportEvent::Progress(report)
+            | ReportEvent::Stopped(report) => report,
+        }
+    }
+}
+
+/// Render thread side of the reporter. Tracks the playback state of the
+/// current Jellyfin item and turns it into `ReportEvent`s.
+///
+/// Lives on the render thread so reporting keeps working while the main
+/// webview is hidden or throttled.
+pub struct ProgressTracker {
+    report_tx: UnboundedSender<ReportEvent>,
+    item: Option<(String, PlaybackReportInfo)>,
+    started: bool,
+    position: f64,
+    paused: bool,
+    aid: Option<u32>,
+    sid: Option<u32>,
+    last_progress: Instant,
+}
+
+impl ProgressTracker {
+    pub fn new(report_tx: UnboundedSender<ReportEvent>) -> Self {
+        Self {
+            report_tx,
+            item: None,
+            started: false,
+            position: 0.0,
+            paused: false,
+            aid: None,
+            sid: None,
+            last_progress: Instant::now(),
+        }
+    }
+
+    /// Start tracking a new load. Any previous item is reported as stopped.
+    pub fn begin(&mut self, item_id: Option<String>, info: Option<PlaybackReportInfo>) {
+        self.stop();
+
+        self.item = item_id.zip(info);
+        self.position = 0.0;
+        self.paused = false;
+        self.aid = None;
+        self.sid = None;
+    }
+
+    /// The file finished loading; tell the server playback has started.
+    pub fn file_loaded(&mut self) {
+        if self.item.is_none() || self.started {
+            return;
+        }
+
+        self.started = true;
+        self.send(ReportEvent::Start);
+    }
+
+    pub fn update_position(&mut self, position: f64) {
+        self.position = position;
+
+        if self.started && self.last_progress.elapsed() >= PROGRESS_INTERVAL {
+            self.send(ReportEvent::Progress);
+        }
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        if self.paused == paused {
+            return;
+        }
+
+        self.paused = paused;
+        if self.started {
+            self.send(ReportEvent::Progress);
+        }
+    }
+
+    pub fn set_aid(&mut self, aid: &str) {
+        self.aid = aid.parse().ok();
+    }
+
+    pub fn set_sid(&mut self, sid: &str) {
+        self.sid = sid.parse().ok();
+    }
+
+    /// mpv track id of the audio stream with Jellyfin index `stream_index`.
+    pub fn audio_track(&self, stream_index: u32) -> Option<u32> {
+        let (_, info) = self.item.as_ref()?;
+        track_id(stream_index, &info.audio_stream_indexes)
+    }
+
+    /// mpv track id of the subtitle stream with Jellyfin index `stream_index`.
+    pub fn subtitle_track(&self, stream_index: u32) -> Option<u32> {
+        let (_, info) = self.item.as_ref()?;
+        track_id(stream_index, &info.subtitle_stream_indexes)
+    }
+
+    /// Report the current item as stopped, on clear, end of file or a new load.
+    pub fn stop(&mut self) {
+        if self.started {
+            self.send(ReportEvent::Stopped);
+        }
+
+        self.started = false;
+        self.item = None;
+    }
+
+    fn send(&mut self, event: fn(PlaybackReport) -> ReportEvent) {
+        let Some(report) = self.report() else {
+            return;
+        };
+
+        if self.report_tx.send(event(report)).is_err() {
+            log::warn!("Playback reporter is not running");
+        }
+        self.last_progress = Instant::now();
+    }
+
+    fn report(&self) -> Option<PlaybackReport> {
+        let (item_id, info) = self.item.as_ref()?;
+
+        // mpv track ids start at 1 and index into the frontend supplied mapping
+        let stream_index = |id: Option<u32>, indexes: &[u32]| {
+            id.and_then(|id| indexes.get(id.checked_sub(1)? as usize).copied())
+        };
+
+        Some(PlaybackReport {
+            item_id: item_id.clone(),
+            media_source_id: info.media_source_id.clone(),
+            play_session_id: info.play_session_id.clone(),
+            play_method: info.play_method.clone(),
+            position_ticks: (self.position * TICKS_PER_SECOND) as i64,
+            is_paused: self.paused,
+            can_seek: true,
+            audio_stream_index: stream_index(self.aid, &info.audio_stream_indexes),
+            subtitle_stream_index: Some(
+                stream_index(self.sid, &info.subtitle_stream_indexes)
+                    .map(i64::from)
+                    .unwrap_or(-1),
+            ),
+        })
+    }
+}
+
+/// Inverse of the stream index mapping: mpv track ids start at 1.
+fn track_id(stream_index: u32, indexes: &[u32]) -> Option<u32> {
+    let position = indexes.iter().position(|index| *index == stream_index)?;
+    Some(position as u32 + 1)
+}
+
+/// Add `event` to the reports waiting to be sent. Only the latest progress
+/// matters, so one that directly follows another replaces it.
+fn enqueue(queue: &mut VecDeque<ReportEvent>, event: ReportEvent) {
+    if let (ReportEvent::Progress(_), Some(last @ ReportEvent::Progress(_))) =
+        (&event, queue.back_mut())
+    {
+        *last = event;
+        return;
+    }
+    queue.push_back(event);
+}
+
+/// Send report events to the signed in server in order, retrying failures
+/// with exponential backoff. Runs until the tracker side is dropped and every
+/// queued report was sent.
+///
+/// Events keep arriving while a report is retried, and a failed progress
+/// report is dropped as soon as a newer event is waiting, so a flaky server
+/// delays the reports but never lets them pile up.
+pub async fn run_reporter(client: JellyfinClient, mut report_rx: UnboundedReceiver<ReportEvent>) {
+    let mut queue = VecDeque::new();
+    let mut open = true;
+
+    loop {
+        if queue.is_empty() && open {
+            match report_rx.recv().await {
+                Some(event) => enqueue(&mut queue, event),
+                None => open = false,
+            }
+        }
+        while let Ok(event) = report_rx.try_recv() {
+            enqueue(&mut queue, event);
+        }
+
+        let Some(event) = queue.pop_front() else {
+            break;
+        };
+
+        if !client.is_signed_in() {
+            log::debug!("Skipping playback report, not signed in to a Jellyfin server");
+            continue;
+        }
+
+        let mut backoff = INITIAL_BACKOFF;
+
+        for attempt in 1..=MAX_ATTEMPTS {
+            match client.post(event.endpoint(), event.report()).await {
+                Ok(()) => break,
+                Err(e) => {
+                    log::warn!(
+                        "Playback report to {} failed (attempt {}/{}): {}",
+                        event.endpoint(),
+                        attempt,
+                        MAX_ATTEMPTS,
+                        e
+                    );
+                }
+            }
+
+            if attempt == MAX_ATTEMPTS {
+                break;
+            }
+
+            // A newer report supersedes this progress, no point retrying it
+            let superseded = |queue: &VecDeque<ReportEvent>| {
+                matches!(event, ReportEvent::Progress(_)) && !queue.is_empty()
+            };
+
+            // Keep queueing while waiting for the retry
+            let retry_at = tokio::time::Instant::now() + backoff;
+            while open && !superseded(&queue) {
+                tokio::select! {
+                    received = report_rx.recv() => match received {
+                        Some(event) => enqueue(&mut queue, event),
+                        None => open = false,
+                    },
+                    _ = tokio::time::sleep_until(retry_at) => break,
+                }
+            }
+            if superseded(&queue) {
+                break;
+            }
+            tokio::time::sleep_until(retry_at).await;
+            backoff *= 2;
+        }
+    }
+
+    log::info!("Playback reporter stopped");
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::*;
+    use crate::jellyfin::mock::MockServer;
+
+    fn report_info() -> PlaybackReportInfo {
+        PlaybackReportInfo {
+            media_source_id: Some("source-1".to_string()),
+            play_session_id: Some("session-1".to_string()),
+            play_method: "DirectPlay".to_string(),
+            audio_stream_indexes: vec![1, 2],
+            subtitle_stream_indexes: vec![3],
+        }
+    }
+
+    fn progress(position: f64) -> ReportEvent {
+        let mut tracker = ProgressTracker::new(unbounded_channel().0);
+        tracker.begin(Some("item-1".to_string()), Some(report_info()));
+        tracker.position = position;
+        ReportEvent::Progress(tracker.report().unwrap())
+    }
+
+    #[tokio::test]
+    async fn reports_start_progress_and_stop() {
+        let server = MockServer::start().await;
+        let (report_tx, report_rx) = unbounded_channel();
+        let reporter = tokio::spawn(run_reporter(server.client(), report_rx));
+
+        let mut tracker = ProgressTracker::new(report_tx);
+        tracker.begin(Some("item-1".to_string()), Some(report_info()));
+        tracker.set_aid("2");
+        tracker.file_loaded();
+        tracker.update_position(12.5);
+        tracker.set_paused(true);
+        tracker.stop();
+        drop(tracker);
+        reporter.await.unwrap();
+
+        let requests = server.requests();
+        let paths: Vec<_> = requests.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "/Sessions/Playing",
+                "/Sessions/Playing/Progress",
+                "/Sessions/Playing/Stopped"
+            ]
+        );
+
+        let progress = requests[1].json();
+        assert_eq!(progress["ItemId"], "item-1");
+        assert_eq!(progress["PlaySessionId"], "session-1");
+        assert_eq!(progress["PositionTicks"], 125_000_000);
+        assert_eq!(progress["IsPaused"], true);
+        assert_eq!(progress["AudioStreamIndex"], 2);
+        assert_eq!(progress["SubtitleStreamIndex"], -1);
+    }
+
+    #[tokio::test]
+    async fn retries_failed_reports_in_order() {
+        let server = MockServer::start().await;
+        server.respond("/Sessions/Playing", 503, "");
+        let (report_tx, report_rx) = unbounded_channel();
+        let reporter = tokio::spawn(run_reporter(server.client(), report_rx));
+
+        let mut tracker = ProgressTracker::new(report_tx);
+        tracker.begin(Some("item-1".to_string()), Some(report_info()));
+        tracker.file_loaded();
+        tracker.stop();
+        drop(tracker);
+        reporter.await.unwrap();
+
+        let paths: Vec<_> = server.requests().into_iter().map(|r| r.path).collect();
+        assert_eq!(
+            paths,
+            [
+                "/Sessions/Playing",
+                "/Sessions/Playing",
+                "/Sessions/Playing/Stopped"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn newer_progress_replaces_a_failed_one_without_waiting() {
+        let server = MockServer::start().await;
+        server.respond("/Sessions/Playing/Progress", 500, "");
+        let (report_tx, report_rx) = unbounded_channel();
+        let reporter = tokio::spawn(run_reporter(server.client(), report_rx));
+
+        let started = Instant::now();
+        report_tx.send(progress(1.0)).unwrap();
+        server.wait_for_requests(1).await;
+        report_tx.send(progress(2.0)).unwrap();
+        report_tx.send(progress(3.0)).unwrap();
+        drop(report_tx);
+        reporter.await.unwrap();
+
+        assert!(started.elapsed() < INITIAL_BACKOFF);
+        let positions: Vec<_> = server
+            .requests()
+            .iter()
+            .map(|r| r.json()["PositionTicks"].as_i64().unwrap())
+            .collect();
+        assert_eq!(positions, [10_000_000, 30_000_000]);
+    }
+}
@@ -0,0 +1,177 @@
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::sync::{broadcast, watch};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+use super::JellyfinSession;
+
+/// Delay before the first reconnect; doubled after every failed attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Keep-alive interval used until the server asks for its own.
+const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(30);
+
+/// Something that happened on the session websocket.
+#[derive(Debug, Clone)]
+pub enum SocketEvent {
+    /// A connection was (re)established; subscribers re-announce themselves.
+    Connected,
+    Message {
+        message_type: String,
+        data: Value,
+    },
+}
+
+/// The signed in session's `/socket` connection. Jellyfin pushes remote
+/// control and SyncPlay commands over it; subscribers pick the messages they
+/// care about.
+pub struct SessionSocket {
+    session_tx: watch::Sender<Option<JellyfinSession>>,
+    events_tx: broadcast::Sender<SocketEvent>,
+}
+
+impl SessionSocket {
+    /// Start the connection task; it connects once a session is set.
+    pub fn start() -> Self {
+        let (session_tx, session_rx) = watch::channel(None);
+        let (events_tx, _) = broadcast::channel(64);
+
+        tauri::async_runtime::spawn(run(session_rx, events_tx.clone()));
+        Self {
+            session_tx,
+            events_tx,
+        }
+    }
+
+    /// Connect with the new session, or disconnect on sign out.
+    pub fn set_session(&self, session: Option<JellyfinSession>) {
+        self.session_tx.send_replace(session);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<SocketEvent> {
+        self.events_tx.subscribe()
+    }
+}
+
+fn socket_url(session: &JellyfinSession) -> String {
+    let url = session.url("/socket");
+    let url = if let Some(rest) = url.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else if let Some(rest) = url.strip_prefix("http://") {
+        format!("ws://{}", rest)
+    } else {
+        url
+    };
+
+    format!(
+        "{}?api_key={}&deviceId={}",
+        url, session.access_token, session.device_id
+    )
+}
+
+/// Keep a connection open for the current session, reconnecting with
+/// exponential backoff, until the socket handle is dropped.
+async fn run(
+    mut session_rx: watch::Receiver<Option<JellyfinSession>>,
+    events_tx: broadcast::Sender<SocketEvent>,
+) {
+    let mut backoff = INITIAL_BACKOFF;
+
+    loop {
+        let session = session_rx.borrow_and_update().clone();
+        let Some(session) = session else {
+            if session_rx.changed().await.is_err() {
+                break;
+            }
+            continue;
+        };
+
+        match connect_async(socket_url(&session)).await {
+            Ok((stream, _)) => {
+                log::info!("Connected to the Jellyfin session socket");
+                backoff = INITIAL_BACKOFF;
+                events_tx.send(SocketEvent::Connected).ok();
+
+                match serve(stream, &mut session_rx, &events_tx).await {
+                    Ok(()) => continue,
+                    Err(e) => log::warn!("Jellyfin session socket closed: {}", e),
+                }
+            }
+            Err(e) => log::warn!("Failed to connect to the Jellyfin session socket: {}", e),
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(backoff) => {}
+            changed = session_rx.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+            }
+        }
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+
+    log::info!("Jellyfin session socket stopped");
+}
+
+/// Pump one connection. Returns `Ok` when the session changed and the caller
+/// should reconnect right away.
+async fn serve<S>(
+    stream: tokio_tungstenite::WebSocketStream<S>,
+    session_rx: &mut watch::Receiver<Option<JellyfinSession>>,
+    events_tx: &broadcast::Sender<SocketEvent>,
+) -> Result<(), String>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    let (mut sink, mut stream) = stream.split();
+    let mut keep_alive = tokio::time::interval(DEFAULT_KEEP_ALIVE);
+
+    loop {
+        tokio::select! {
+            changed = session_rx.changed() => {
+                sink.close().await.ok();
+                return changed.map_err(|_| "app is shutting down".to_string());
+            }
+            _ = keep_alive.tick() => {
+                let message = json!({ "MessageType": "KeepAlive" }).to_string();
+                sink.send(Message::text(message))
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            message = stream.next() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => {
+                        return Err("closed by the server".to_string())
+                    }
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(e.to_string()),
+                };
+
+                let Ok(mut message) = serde_json::from_str::<Value>(text.as_str()) else {
+                    log::debug!("Ignoring malformed session socket message");
+                    continue;
+                };
+                let message_type = message["MessageType"].as_str().unwrap_or_default().to_string();
+                let data = message["Data"].take();
+
+                match message_type.as_str() {
+                    // Data is the server's timeout in seconds; ping at half of it
+                    "ForceKeepAlive" => {
+                        let timeout = data.as_f64().unwrap_or(60.0).max(2.0);
+                        keep_alive = tokio::time::interval(Duration::from_secs_f64(timeout / 2.0));
+                    }
+                    "KeepAlive" => {}
+                    _ => {
+                        log::debug!("Session socket message: {}", message_type);
+                        events_tx.send(SocketEvent::Message { message_type, data }).ok();
+                    }
+                }
+            }
+        }
+    }
+}
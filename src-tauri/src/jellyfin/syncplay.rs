@@ -0,0 +1,987 @@
+//! Jellyfin SyncPlay client: keeps playback in step with a group of clients
+//! watching together. Group commands arrive over the session socket and are
+//! scheduled against the server clock; drift is corrected with small speed
+//! changes, or a seek when it gets too large.
+
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::AppHandle;
+use tauri_specta::Event;
+use tokio::sync::{
+    broadcast,
+    mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+};
+use tokio::time::Instant;
+
+use super::client::JellyfinClient;
+use super::socket::{SessionSocket, SocketEvent};
+use crate::mpv::{
+    FileLoadedChange, NowPlayingChange, PauseForCacheChange, PlayBackStateChange,
+    PlayBackTimeChange, PlaybackEvent, SpeedEventChange,
+};
+
+const TICKS_PER_SECOND: f64 = 10_000_000.0;
+
+/// Drift below this is left alone.
+const MIN_DRIFT: f64 = 0.1;
+/// Drift above this is corrected with a seek instead of a speed change.
+const MAX_SPEED_DRIFT: f64 = 3.0;
+/// A speed correction aims to catch up over this many seconds.
+const SPEED_CORRECTION_WINDOW: f64 = 5.0;
+const MAX_SPEED_CORRECTION: f64 = 0.1;
+/// Reported speeds this close to the one we set are our own correction.
+const SPEED_TOLERANCE: f64 = 0.001;
+/// Give a seek correction time to land before measuring drift again.
+const SEEK_COOLDOWN: Duration = Duration::from_secs(2);
+
+/// How often the server clock offset is measured while in a group.
+const TIME_SYNC_INTERVAL: Duration = Duration::from_secs(60);
+
+/// SyncPlay group as listed by the server.
+#[derive(Debug, specta::Type, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPlayGroup {
+    pub group_id: String,
+    pub group_name: String,
+    /// `Idle`, `Waiting`, `Paused` or `Playing`.
+    pub state: String,
+    pub participants: Vec<String>,
+}
+
+/// Group changes pushed by the server, for the SyncPlay UI. The frontend
+/// loads the item named by `playQueue`; playback control is handled here.
+#[derive(Debug, specta::Type, tauri_specta::Event, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum SyncPlayEvent {
+    GroupJoined {
+        group: SyncPlayGroup,
+    },
+    GroupLeft,
+    UserJoined {
+        user: String,
+    },
+    UserLeft {
+        user: String,
+    },
+    StateUpdate {
+        state: String,
+        reason: String,
+    },
+    PlayQueue {
+        item_ids: Vec<String>,
+        playing_index: Option<u32>,
+        start_position: f64,
+        is_playing: bool,
+    },
+    /// The group does not exist or the library is not accessible.
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct GroupInfo {
+    group_id: String,
+    group_name: String,
+    state: String,
+    #[serde(default)]
+    participants: Vec<String>,
+}
+
+impl From<GroupInfo> for SyncPlayGroup {
+    fn from(info: GroupInfo) -> Self {
+        Self {
+            group_id: info.group_id,
+            group_name: info.group_name,
+            state: info.state,
+            participants: info.participants,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct GroupUpdate {
+    #[serde(rename = "Type")]
+    kind: String,
+    #[serde(default)]
+    data: Value,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct StateUpdate {
+    state: String,
+    reason: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct PlayQueueUpdate {
+    playlist: Vec<QueueItem>,
+    playing_item_index: i32,
+    start_position_ticks: i64,
+    is_playing: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct QueueItem {
+    item_id: String,
+    playlist_item_id: String,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+struct GroupCommand {
+    playlist_item_id: String,
+    when: DateTime<Utc>,
+    #[serde(default)]
+    position_ticks: Option<i64>,
+    command: String,
+}
+
+impl GroupCommand {
+    fn position(&self) -> f64 {
+        self.position_ticks.unwrap_or(0) as f64 / TICKS_PER_SECOND
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct UtcTime {
+    request_reception_time: DateTime<Utc>,
+    response_transmission_time: DateTime<Utc>,
+}
+
+/// SyncPlay endpoints of the signed in server.
+#[derive(Clone)]
+struct SyncPlayApi {
+    client: JellyfinClient,
+}
+
+impl SyncPlayApi {
+    async fn post(&self, path: &str, body: Value) -> Result<(), String> {
+        Ok(self.client.post(path, &body).await?)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, String> {
+        Ok(self.client.get(path).await?)
+    }
+
+    /// Fire and forget a request from the sync task, which must not wait on
+    /// the network while commands are due.
+    fn post_in_background(&self, path: &'static str, body: Value) {
+        let api = self.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = api.post(path, body).await {
+                log::warn!("SyncPlay request to {} failed: {}", path, e);
+            }
+        });
+    }
+}
+
+/// Handle for the SyncPlay commands, managed as app state.
+pub struct SyncPlay {
+    api: SyncPlayApi,
+}
+
+impl SyncPlay {
+    pub async fn list_groups(&self) -> Result<Vec<SyncPlayGroup>, String> {
+        let groups: Vec<GroupInfo> = self.api.get("/SyncPlay/List").await?;
+        Ok(groups.into_iter().map(SyncPlayGroup::from).collect())
+    }
+
+    pub async fn create_group(&self, name: &str) -> Result<(), String> {
+        self.api
+            .post("/SyncPlay/New", json!({ "GroupName": name }))
+            .await
+    }
+
+    pub async fn join_group(&self, group_id: &str) -> Result<(), String> {
+        self.api
+            .post("/SyncPlay/Join", json!({ "GroupId": group_id }))
+            .await
+    }
+
+    pub async fn leave_group(&self) -> Result<(), String> {
+        self.api.post("/SyncPlay/Leave", json!({})).await
+    }
+
+    pub async fn pause(&self) -> Result<(), String> {
+        self.api.post("/SyncPlay/Pause", json!({})).await
+    }
+
+    pub async fn unpause(&self) -> Result<(), String> {
+        self.api.post("/SyncPlay/Unpause", json!({})).await
+    }
+
+    pub async fn seek(&self, position: f64) -> Result<(), String> {
+        let ticks = (position * TICKS_PER_SECOND) as i64;
+        self.api
+            .post("/SyncPlay/Seek", json!({ "PositionTicks": ticks }))
+            .await
+    }
+}
+
+/// Player state change picked up from the render thread's events.
+enum PlayerUpdate {
+    Time(f64),
+    Paused(bool),
+    Buffering(bool),
+    Speed(f64),
+    /// A file finished loading, or another one started to.
+    Loaded(bool),
+}
+
+pub fn init(
+    app: &AppHandle,
+    render_tx: Sender<PlaybackEvent>,
+    client: JellyfinClient,
+    socket: &SessionSocket,
+) -> SyncPlay {
+    let api = SyncPlayApi { client };
+    let (player_tx, player_rx) = unbounded_channel();
+    listen(app, player_tx);
+
+    let (events_tx, mut events_rx) = unbounded_channel();
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = events_rx.recv().await {
+            event.emit(&handle).ok();
+        }
+    });
+
+    let state = GroupState::new(events_tx, render_tx, api.clone());
+    tauri::async_runtime::spawn(state.run(socket.subscribe(), player_rx));
+
+    SyncPlay { api }
+}
+
+fn listen(app: &AppHandle, player_tx: UnboundedSender<PlayerUpdate>) {
+    let tx = player_tx.clone();
+    PlayBackTimeChange::listen_any(app, move |event| {
+        tx.send(PlayerUpdate::Time(event.payload.position)).ok();
+    });
+
+    let tx = player_tx.clone();
+    PlayBackStateChange::listen_any(app, move |event| {
+        tx.send(PlayerUpdate::Paused(event.payload.pause)).ok();
+    });
+
+    let tx = player_tx.clone();
+    NowPlayingChange::listen_any(app, move |_| {
+        tx.send(PlayerUpdate::Loaded(false)).ok();
+    });
+
+    let tx = player_tx.clone();
+    FileLoadedChange::listen_any(app, move |_| {
+        tx.send(PlayerUpdate::Loaded(true)).ok();
+    });
+
+    let tx = player_tx.clone();
+    SpeedEventChange::listen_any(app, move |event| {
+        tx.send(PlayerUpdate::Speed(event.payload.speed)).ok();
+    });
+
+    PauseForCacheChange::listen_any(app, move |event| {
+        player_tx
+            .send(PlayerUpdate::Buffering(event.payload.pause))
+            .ok();
+    });
+}
+
+/// Measure the server clock offset (server minus local) NTP style and report
+/// the round trip time, which the server uses to schedule commands.
+async fn sync_time(api: &SyncPlayApi) -> Option<TimeDelta> {
+    let sent = Utc::now();
+    let time: UtcTime = match api.get("/GetUtcTime").await {
+        Ok(time) => time,
+        Err(e) => {
+            log::warn!("SyncPlay time sync failed: {}", e);
+            return None;
+        }
+    };
+    let received = Utc::now();
+
+    let offset =
+        ((time.request_reception_time - sent) + (time.response_transmission_time - received)) / 2;
+    let round_trip =
+        (received - sent) - (time.response_transmission_time - time.request_reception_time);
+    log::debug!(
+        "SyncPlay clock offset {} ms, round trip {} ms",
+        offset.num_milliseconds(),
+        round_trip.num_milliseconds()
+    );
+
+    let ping = json!({ "Ping": round_trip.num_milliseconds().max(0) });
+    if let Err(e) = api.post("/SyncPlay/Ping", ping).await {
+        log::warn!("SyncPlay request to /SyncPlay/Ping failed: {}", e);
+    }
+    Some(offset)
+}
+
+/// Sync task state for the group this client is in.
+struct GroupState {
+    events_tx: UnboundedSender<SyncPlayEvent>,
+    render_tx: Sender<PlaybackEvent>,
+    api: SyncPlayApi,
+    group_id: Option<String>,
+    playlist_item_id: Option<String>,
+    /// Server clock minus local clock.
+    clock_offset: TimeDelta,
+    next_time_sync: Instant,
+    /// Command waiting for its `When`, in local time.
+    pending: Option<(Instant, GroupCommand)>,
+    /// Server time and position the group started playing from.
+    playing_since: Option<(DateTime<Utc>, f64)>,
+    /// Position a group seek is waiting for before reporting ready.
+    seek_target: Option<f64>,
+    /// Whether mpv has a file to seek in. Commands usually arrive with the
+    /// play queue, before the frontend loaded the item.
+    file_loaded: bool,
+    /// Seek held back until the file has loaded.
+    deferred_seek: Option<f64>,
+    position: f64,
+    paused: bool,
+    buffering: bool,
+    /// Speed the user chose; drift corrections are applied relative to it.
+    user_speed: f64,
+    /// Factor on top of `user_speed` for drift correction, 1.0 when not
+    /// correcting.
+    correction: f64,
+    last_seek: Option<Instant>,
+}
+
+impl GroupState {
+    fn new(
+        events_tx: UnboundedSender<SyncPlayEvent>,
+        render_tx: Sender<PlaybackEvent>,
+        api: SyncPlayApi,
+    ) -> Self {
+        Self {
+            events_tx,
+            render_tx,
+            api,
+            group_id: None,
+            playlist_item_id: None,
+            clock_offset: TimeDelta::zero(),
+            next_time_sync: Instant::now(),
+            pending: None,
+            playing_since: None,
+            seek_target: None,
+            file_loaded: false,
+            deferred_seek: None,
+            position: 0.0,
+            paused: true,
+            buffering: false,
+            user_speed: 1.0,
+            correction: 1.0,
+            last_seek: None,
+        }
+    }
+
+    async fn run(
+        mut self,
+        mut socket_rx: broadcast::Receiver<SocketEvent>,
+        mut player_rx: UnboundedReceiver<PlayerUpdate>,
+    ) {
+        // Time syncs run on their own so a slow server never delays commands
+        let (offset_tx, mut offset_rx) = unbounded_channel();
+
+        loop {
+            let pending_at = self.pending.as_ref().map(|(at, _)| *at);
+            let idle = Instant::now() + TIME_SYNC_INTERVAL;
+
+            tokio::select! {
+                event = socket_rx.recv() => match event {
+                    Ok(SocketEvent::Message { message_type, data }) => {
+                        self.handle_message(&message_type, data);
+                    }
+                    Ok(SocketEvent::Connected) => {}
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        log::warn!("SyncPlay missed {} session socket messages", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                update = player_rx.recv() => match update {
+                    Some(update) => self.handle_player(update),
+                    None => break,
+                },
+                _ = tokio::time::sleep_until(pending_at.unwrap_or(idle)), if pending_at.is_some() => {
+                    if let Some((_, command)) = self.pending.take() {
+                        self.execute(command);
+                    }
+                }
+                _ = tokio::time::sleep_until(self.next_time_sync), if self.group_id.is_some() => {
+                    self.next_time_sync = Instant::now() + TIME_SYNC_INTERVAL;
+                    let api = self.api.clone();
+                    let offset_tx = offset_tx.clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Some(offset) = sync_time(&api).await {
+                            offset_tx.send(offset).ok();
+                        }
+                    });
+                }
+                Some(offset) = offset_rx.recv() => self.clock_offset = offset,
+            }
+        }
+
+        log::info!("SyncPlay task stopped");
+    }
+
+    fn send(&self, event: PlaybackEvent) {
+        if self.render_tx.send(event).is_err() {
+            log::warn!("Render thread is not running, dropping SyncPlay command");
+        }
+    }
+
+    fn server_now(&self) -> DateTime<Utc> {
+        Utc::now() + self.clock_offset
+    }
+
+    /// Local instant at which the server clock reads `when`.
+    fn local_instant(&self, when: DateTime<Utc>) -> Instant {
+        let delay = (when - self.server_now()).to_std().unwrap_or_default();
+        Instant::now() + delay
+    }
+
+    fn handle_message(&mut self, message_type: &str, data: Value) {
+        match message_type {
+            "SyncPlayCommand" => match serde_json::from_value::<GroupCommand>(data) {
+                Ok(command) => self.schedule(command),
+                Err(e) => log::warn!("Ignoring malformed SyncPlay command: {}", e),
+            },
+            "SyncPlayGroupUpdate" => match serde_json::from_value::<GroupUpdate>(data) {
+                Ok(update) => self.handle_group_update(update),
+                Err(e) => log::warn!("Ignoring malformed SyncPlay group update: {}", e),
+            },
+            _ => {}
+        }
+    }
+
+    fn handle_group_update(&mut self, update: GroupUpdate) {
+        let event = match update.kind.as_str() {
+            "GroupJoined" => {
+                let Ok(group) = serde_json::from_value::<GroupInfo>(update.data) else {
+                    return;
+                };
+                log::info!("Joined SyncPlay group {}", group.group_name);
+                self.group_id = Some(group.group_id.clone());
+                self.next_time_sync = Instant::now();
+                SyncPlayEvent::GroupJoined {
+                    group: group.into(),
+                }
+            }
+            "GroupLeft" | "NotInGroup" => {
+                if self.group_id.take().is_none() {
+                    return;
+                }
+                log::info!("Left SyncPlay group");
+                self.pending = None;
+                self.playing_since = None;
+                self.seek_target = None;
+                self.deferred_seek = None;
+                self.reset_speed();
+                SyncPlayEvent::GroupLeft
+            }
+            "UserJoined" => SyncPlayEvent::UserJoined {
+                user: update.data.as_str().unwrap_or_default().to_string(),
+            },
+            "UserLeft" => SyncPlayEvent::UserLeft {
+                user: update.data.as_str().unwrap_or_default().to_string(),
+            },
+            "StateUpdate" => {
+                let Ok(state) = serde_json::from_value::<StateUpdate>(update.data) else {
+                    return;
+                };
+                SyncPlayEvent::StateUpdate {
+                    state: state.state,
+                    reason: state.reason,
+                }
+            }
+            "PlayQueue" => {
+                let Ok(queue) = serde_json::from_value::<PlayQueueUpdate>(update.data) else {
+                    return;
+                };
+                let playing_index = u32::try_from(queue.playing_item_index).ok();
+                self.playlist_item_id = playing_index
+                    .and_then(|index| queue.playlist.get(index as usize))
+                    .map(|item| item.playlist_item_id.clone());
+
+                SyncPlayEvent::PlayQueue {
+                    item_ids: queue
+                        .playlist
+                        .into_iter()
+                        .map(|item| item.item_id)
+                        .collect(),
+                    playing_index,
+                    start_position: queue.start_position_ticks as f64 / TICKS_PER_SECOND,
+                    is_playing: queue.is_playing,
+                }
+            }
+            "GroupDoesNotExist" => SyncPlayEvent::Error {
+                message: "The SyncPlay group does not exist".to_string(),
+            },
+            "LibraryAccessDenied" => SyncPlayEvent::Error {
+                message: "No access to the library of the group's item".to_string(),
+            },
+            kind => {
+                log::debug!("Ignoring SyncPlay group update {}", kind);
+                return;
+            }
+        };
+
+        self.events_tx.send(event).ok();
+    }
+
+    /// Run a command at the server time it names. Seeking ahead of an
+    /// unpause lets the player buffer while waiting.
+    fn schedule(&mut self, command: GroupCommand) {
+        if self.group_id.is_none() {
+            return;
+        }
+        if self
+            .playlist_item_id
+            .as_ref()
+            .is_some_and(|id| *id != command.playlist_item_id)
+        {
+            log::debug!("Ignoring SyncPlay command for another playlist item");
+            return;
+        }
+
+        let at = self.local_instant(command.when);
+        if command.command == "Unpause" && at > Instant::now() {
+            self.seek(command.position());
+        }
+        self.pending = Some((at, command));
+    }
+
+    fn execute(&mut self, command: GroupCommand) {
+        log::debug!("Running SyncPlay command {}", command.command);
+
+        match command.command.as_str() {
+            "Unpause" => {
+                // Catch up on time that passed since the group started playing
+                let elapsed = (self.server_now() - command.when).num_milliseconds() as f64 / 1000.0;
+                let position = command.position() + elapsed.max(0.0);
+                if (position - self.position).abs() > MIN_DRIFT {
+                    self.seek(position);
+                }
+                self.send(PlaybackEvent::Play);
+                self.playing_since = Some((command.when, command.position()));
+                self.seek_target = None;
+            }
+            "Pause" => {
+                self.send(PlaybackEvent::Pause);
+                self.seek(command.position());
+                self.playing_since = None;
+                self.reset_speed();
+            }
+            "Seek" => {
+                self.send(PlaybackEvent::Pause);
+                self.seek(command.position());
+                self.playing_since = None;
+                self.seek_target = Some(command.position());
+                self.reset_speed();
+            }
+            "Stop" => {
+                self.send(PlaybackEvent::Pause);
+                self.playing_since = None;
+                self.reset_speed();
+            }
+            command => log::debug!("Ignoring SyncPlay command {}", command),
+        }
+    }
+
+    fn seek(&mut self, position: f64) {
+        if self.file_loaded {
+            self.send(PlaybackEvent::AbsoluteSeek(position));
+        } else {
+            self.deferred_seek = Some(position);
+        }
+        self.position = position;
+        self.last_seek = Some(Instant::now());
+    }
+
+    /// Play at `correction` times the user's speed.
+    fn set_correction(&mut self, correction: f64) {
+        if (correction - self.correction).abs() >= 0.01
+            || (correction == 1.0 && self.correction != 1.0)
+        {
+            self.correction = correction;
+            self.send(PlaybackEvent::Speed(self.user_speed * correction));
+        }
+    }
+
+    fn reset_speed(&mut self) {
+        self.set_correction(1.0);
+    }
+
+    fn handle_player(&mut self, update: PlayerUpdate) {
+        match update {
+            PlayerUpdate::Time(position) => {
+                self.position = position;
+
+                if let Some(target) = self.seek_target {
+                    if (position - target).abs() <= MAX_SPEED_DRIFT && !self.buffering {
+                        self.seek_target = None;
+                        self.report("/SyncPlay/Ready", false);
+                    }
+                }
+
+                self.correct_drift();
+            }
+            PlayerUpdate::Paused(paused) => self.paused = paused,
+            PlayerUpdate::Buffering(buffering) => {
+                if self.buffering == buffering {
+                    return;
+                }
+                self.buffering = buffering;
+
+                let playing = self.playing_since.is_some();
+                if buffering {
+                    self.report("/SyncPlay/Buffering", playing);
+                } else {
+                    self.report("/SyncPlay/Ready", playing);
+                }
+            }
+            PlayerUpdate::Speed(speed) => {
+                // Our own corrections are reported back too
+                if (speed - self.user_speed * self.correction).abs() > SPEED_TOLERANCE {
+                    self.user_speed = speed;
+                    self.correction = 1.0;
+                }
+            }
+            PlayerUpdate::Loaded(loaded) => {
+                self.file_loaded = loaded;
+                if loaded {
+                    if let Some(position) = self.deferred_seek.take() {
+                        self.seek(position);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Tell the group about our buffering state so it can wait for us.
+    fn report(&self, path: &'static str, is_playing: bool) {
+        let (Some(_), Some(playlist_item_id)) = (&self.group_id, &self.playlist_item_id) else {
+            return;
+        };
+
+        self.api.post_in_background(
+            path,
+            json!({
+                "When": self.server_now().to_rfc3339_opts(SecondsFormat::Millis, true),
+                "PositionTicks": (self.position * TICKS_PER_SECOND) as i64,
+                "IsPlaying": is_playing,
+                "PlaylistItemId": playlist_item_id,
+            }),
+        );
+    }
+
+    fn correct_drift(&mut self) {
+        let Some((when, start)) = self.playing_since else {
+            return;
+        };
+        if self.paused || self.buffering || self.pending.is_some() {
+            return;
+        }
+        if self
+            .last_seek
+            .is_some_and(|last_seek| last_seek.elapsed() < SEEK_COOLDOWN)
+        {
+            return;
+        }
+
+        let expected = start + (self.server_now() - when).num_milliseconds() as f64 / 1000.0;
+        let drift = self.position - expected;
+
+        if drift.abs() >= MAX_SPEED_DRIFT {
+            log::info!("SyncPlay drift of {:.2}s, seeking", drift);
+            self.reset_speed();
+            self.seek(expected);
+        } else if drift.abs() >= MIN_DRIFT {
+            let correction = 1.0
+                - (drift / SPEED_CORRECTION_WINDOW)
+                    .clamp(-MAX_SPEED_CORRECTION, MAX_SPEED_CORRECTION);
+            self.set_correction(correction);
+        } else {
+            self.reset_speed();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, SocketAddr};
+    use std::sync::mpsc::{channel, Receiver};
+
+    use futures_util::SinkExt;
+    use tokio_tungstenite::tungstenite::Message;
+
+    use super::*;
+    use crate::jellyfin::mock::{MockServer, RecordedRequest};
+    use crate::jellyfin::JellyfinSession;
+
+    const PLAYLIST_ITEM_ID: &str = "playlist-item-1";
+
+    /// Websocket server standing in for Jellyfin's `/socket`; frames sent on
+    /// the returned channel are pushed to the client.
+    async fn start_socket_server() -> (String, UnboundedSender<Value>) {
+        let listener = tokio::net::TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+            .await
+            .unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let (frames_tx, mut frames_rx) = unbounded_channel::<Value>();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            while let Some(frame) = frames_rx.recv().await {
+                socket.send(Message::text(frame.to_string())).await.unwrap();
+            }
+        });
+
+        (url, frames_tx)
+    }
+
+    fn message(message_type: &str, data: Value) -> Value {
+        json!({ "MessageType": message_type, "Data": data })
+    }
+
+    fn group_update(kind: &str, data: Value) -> Value {
+        message(
+            "SyncPlayGroupUpdate",
+            json!({ "GroupId": "group-1", "Type": kind, "Data": data }),
+        )
+    }
+
+    fn command(command: &str, when: DateTime<Utc>, position: f64) -> Value {
+        message(
+            "SyncPlayCommand",
+            json!({
+                "GroupId": "group-1",
+                "PlaylistItemId": PLAYLIST_ITEM_ID,
+                "When": when.to_rfc3339_opts(SecondsFormat::Millis, true),
+                "PositionTicks": (position * TICKS_PER_SECOND) as i64,
+                "Command": command,
+            }),
+        )
+    }
+
+    /// Next event sent to the render thread. The sync task shares the test's
+    /// runtime, so this must not block it.
+    async fn next_event(render_rx: &Receiver<PlaybackEvent>) -> PlaybackEvent {
+        let wait = async {
+            loop {
+                if let Ok(event) = render_rx.try_recv() {
+                    return event;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+
+        tokio::time::timeout(Duration::from_secs(10), wait)
+            .await
+            .expect("no event reached the render thread")
+    }
+
+    /// Wait for `count` buffering state reports.
+    async fn wait_for_reports(server: &MockServer, count: usize) -> Vec<RecordedRequest> {
+        let wait = async {
+            loop {
+                let reports: Vec<_> = server
+                    .requests()
+                    .into_iter()
+                    .filter(|r| r.path == "/SyncPlay/Buffering" || r.path == "/SyncPlay/Ready")
+                    .collect();
+                if reports.len() >= count {
+                    return reports;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+
+        tokio::time::timeout(Duration::from_secs(10), wait)
+            .await
+            .unwrap_or_else(|_| panic!("expected {} reports, got {:?}", count, server.requests()))
+    }
+
+    #[tokio::test]
+    async fn group_commands_drive_the_player() {
+        let server = MockServer::start().await;
+        let (socket_url, frames_tx) = start_socket_server().await;
+
+        let socket = SessionSocket::start();
+        let socket_rx = socket.subscribe();
+        socket.set_session(Some(JellyfinSession {
+            server_url: socket_url,
+            ..server.session()
+        }));
+
+        let (render_tx, render_rx) = channel();
+        let (player_tx, player_rx) = unbounded_channel();
+        let (events_tx, mut events_rx) = unbounded_channel();
+        let api = SyncPlayApi {
+            client: server.client(),
+        };
+        tokio::spawn(GroupState::new(events_tx, render_tx, api).run(socket_rx, player_rx));
+
+        frames_tx
+            .send(group_update(
+                "GroupJoined",
+                json!({
+                    "GroupId": "group-1",
+                    "GroupName": "Movie night",
+                    "State": "Paused",
+                    "Participants": ["alice"],
+                }),
+            ))
+            .unwrap();
+        frames_tx
+            .send(group_update(
+                "PlayQueue",
+                json!({
+                    "Playlist": [{ "ItemId": "item-1", "PlaylistItemId": PLAYLIST_ITEM_ID }],
+                    "PlayingItemIndex": 0,
+                    "StartPositionTicks": 0,
+                    "IsPlaying": false,
+                }),
+            ))
+            .unwrap();
+
+        let joined = events_rx.recv().await.unwrap();
+        assert!(
+            matches!(joined, SyncPlayEvent::GroupJoined { .. }),
+            "{:?}",
+            joined
+        );
+        let queue = events_rx.recv().await.unwrap();
+        assert!(
+            matches!(&queue, SyncPlayEvent::PlayQueue { item_ids, .. } if item_ids == &["item-1"]),
+            "{:?}",
+            queue
+        );
+
+        player_tx.send(PlayerUpdate::Loaded(true)).unwrap();
+
+        // Unpausing seeks ahead right away and plays once `When` is reached
+        let when = Utc::now() + TimeDelta::milliseconds(300);
+        frames_tx.send(command("Unpause", when, 100.0)).unwrap();
+        let event = next_event(&render_rx).await;
+        assert!(
+            matches!(event, PlaybackEvent::AbsoluteSeek(position) if position == 100.0),
+            "{:?}",
+            event
+        );
+        let event = next_event(&render_rx).await;
+        assert!(matches!(event, PlaybackEvent::Play), "{:?}", event);
+
+        // Buffering while the group plays is reported with IsPlaying set
+        player_tx.send(PlayerUpdate::Paused(false)).unwrap();
+        player_tx.send(PlayerUpdate::Buffering(true)).unwrap();
+        player_tx.send(PlayerUpdate::Buffering(false)).unwrap();
+        // Reports are sent in the background and may arrive in either order
+        let reports = wait_for_reports(&server, 2).await;
+        let mut paths: Vec<_> = reports.iter().map(|r| r.path.as_str()).collect();
+        paths.sort();
+        assert_eq!(paths, ["/SyncPlay/Buffering", "/SyncPlay/Ready"]);
+        for report in &reports {
+            let body = report.json();
+            assert_eq!(body["IsPlaying"], true);
+            assert_eq!(body["PlaylistItemId"], PLAYLIST_ITEM_ID);
+        }
+
+        frames_tx.send(command("Pause", Utc::now(), 120.0)).unwrap();
+        let event = next_event(&render_rx).await;
+        assert!(matches!(event, PlaybackEvent::Pause), "{:?}", event);
+        let event = next_event(&render_rx).await;
+        assert!(
+            matches!(event, PlaybackEvent::AbsoluteSeek(position) if position == 120.0),
+            "{:?}",
+            event
+        );
+
+        // A group seek reports ready once the player got there
+        frames_tx.send(command("Seek", Utc::now(), 200.0)).unwrap();
+        let event = next_event(&render_rx).await;
+        assert!(matches!(event, PlaybackEvent::Pause), "{:?}", event);
+        let event = next_event(&render_rx).await;
+        assert!(
+            matches!(event, PlaybackEvent::AbsoluteSeek(position) if position == 200.0),
+            "{:?}",
+            event
+        );
+
+        player_tx.send(PlayerUpdate::Time(200.0)).unwrap();
+        let reports = wait_for_reports(&server, 3).await;
+        assert_eq!(reports[2].path, "/SyncPlay/Ready");
+        let body = reports[2].json();
+        assert_eq!(body["IsPlaying"], false);
+        assert_eq!(body["PositionTicks"], 2_000_000_000i64);
+    }
+
+    #[tokio::test]
+    async fn corrections_are_relative_to_the_user_speed() {
+        let server = MockServer::start().await;
+        let api = SyncPlayApi {
+            client: server.client(),
+        };
+        let (render_tx, render_rx) = channel();
+        let mut state = GroupState::new(unbounded_channel().0, render_tx, api);
+
+        state.handle_player(PlayerUpdate::Speed(1.5));
+        state.set_correction(0.95);
+        let event = render_rx.try_recv().unwrap();
+        assert!(
+            matches!(event, PlaybackEvent::Speed(speed) if (speed - 1.425).abs() < 1e-9),
+            "{:?}",
+            event
+        );
+
+        // The correction coming back from the player is not a user change
+        state.handle_player(PlayerUpdate::Speed(1.5 * 0.95));
+        state.reset_speed();
+        let event = render_rx.try_recv().unwrap();
+        assert!(
+            matches!(event, PlaybackEvent::Speed(speed) if speed == 1.5),
+            "{:?}",
+            event
+        );
+    }
+
+    #[tokio::test]
+    async fn seeks_wait_for_the_file_to_load() {
+        let server = MockServer::start().await;
+        let api = SyncPlayApi {
+            client: server.client(),
+        };
+        let (render_tx, render_rx) = channel();
+        let mut state = GroupState::new(unbounded_channel().0, render_tx, api);
+
+        state.execute(GroupCommand {
+            command: "Seek".to_string(),
+            when: Utc::now(),
+            position_ticks: Some(3_000_000_000),
+            playlist_item_id: PLAYLIST_ITEM_ID.to_string(),
+        });
+        let event = render_rx.try_recv().unwrap();
+        assert!(matches!(event, PlaybackEvent::Pause), "{:?}", event);
+        assert!(render_rx.try_recv().is_err());
+
+        state.handle_player(PlayerUpdate::Loaded(true));
+        let event = render_rx.try_recv().unwrap();
+        assert!(
+            matches!(event, PlaybackEvent::AbsoluteSeek(position) if position == 300.0),
+            "{:?}",
+            event
+        );
+    }
+}
@@ -0,0 +1,306 @@
+//! Command line arguments and `square://` deep links, from the initial
+//! launch or forwarded by a second instance.
+//!
+//! Supported forms:
+//! - `square <file, folder or url>` and `square --seek <seconds> <file or url>`
+//! - `square://item/<id>` to show a library item
+//! - `square://play?url=<http url>&t=<seconds>` to play a stream
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, Url};
+use tauri_specta::Event;
+
+use crate::local_media::LocalPlayback;
+use crate::mpv::RequestFileLoad;
+
+const SCHEME: &str = "square";
+
+/// Ask the frontend to navigate somewhere, e.g. from a `square://` link.
+#[derive(Debug, specta::Type, tauri_specta::Event, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum NavigationRequest {
+    Item { item_id: String },
+}
+
+#[derive(Debug, Clone)]
+pub enum LaunchRequest {
+    Open {
+        url: String,
+        start: Option<f64>,
+    },
+    /// A local file or folder, queued with its subtitles.
+    OpenLocal {
+        path: PathBuf,
+        start: Option<f64>,
+    },
+    Navigate(NavigationRequest),
+}
+
+/// Navigation requested at launch, held until the frontend is ready to take it.
+#[derive(Default)]
+pub struct PendingNavigation(Mutex<Option<NavigationRequest>>);
+
+impl PendingNavigation {
+    pub fn take(&self) -> Option<NavigationRequest> {
+        self.0.lock().unwrap().take()
+    }
+}
+
+/// Seconds as `300`, `5:00` or `1:05:00`.
+fn parse_time(value: &str) -> Option<f64> {
+    value
+        .split(':')
+        .try_fold(0.0, |total, part| {
+            let part = part.parse::<f64>().ok()?;
+            // `1:-30` would otherwise mean 30 seconds
+            (part.is_finite() && part >= 0.0).then_some(total * 60.0 + part)
+        })
+        .filter(|time| time.is_finite())
+}
+
+/// Parse process arguments, without the program name. Deep links are left to
+/// `parse_deep_link`, as they also arrive through the deep-link plugin.
+pub fn parse_args(args: &[String], cwd: &Path) -> Option<LaunchRequest> {
+    let mut start = None;
+    let mut target = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        if let Some(value) = arg.strip_prefix("--seek=") {
+            start = parse_time(value);
+        } else if arg == "--seek" {
+            start = args.next().and_then(|value| parse_time(value));
+        } else if arg.starts_with('-') {
+            log::debug!("Ignoring unknown argument {}", arg);
+        } else if target.is_none() {
+            target = Some(arg);
+        }
+    }
+
+    let target = target?;
+    if target.starts_with(&format!("{}:", SCHEME)) {
+        return None;
+    }
+
+    if target.contains("://") {
+        return Some(LaunchRequest::Open {
+            url: target.clone(),
+            start,
+        });
+    }
+
+    let path = cwd.join(target);
+    if !path.exists() {
+        log::warn!("Ignoring {}, no such file or folder", path.display());
+        return None;
+    }
+    Some(LaunchRequest::OpenLocal {
+        path: absolute(path),
+        start,
+    })
+}
+
+fn absolute(path: PathBuf) -> PathBuf {
+    std::fs::canonicalize(&path).unwrap_or(path)
+}
+
+pub fn parse_deep_link(url: &Url) -> Option<LaunchRequest> {
+    if url.scheme() != SCHEME {
+        return None;
+    }
+
+    let segments: Vec<_> = url
+        .path_segments()
+        .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default();
+
+    match (url.host_str()?, segments.as_slice()) {
+        ("item", [item_id]) => Some(LaunchRequest::Navigate(NavigationRequest::Item {
+            item_id: item_id.to_string(),
+        })),
+        ("play", []) => {
+            let query = |name: &str| {
+                url.query_pairs()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.into_owned())
+            };
+
+            // Links come from web pages, so only streams are allowed, never local files
+            let stream = query("url")?;
+            let stream_url = Url::parse(&stream).ok()?;
+            if !matches!(stream_url.scheme(), "http" | "https") {
+                log::warn!("Ignoring deep link to a {} url", stream_url.scheme());
+                return None;
+            }
+
+            Some(LaunchRequest::Open {
+                url: stream,
+                start: query("t").and_then(|t| parse_time(&t)),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Carry out a launch request. At startup the frontend is not listening yet,
+/// so navigation is parked in `PendingNavigation` instead of emitted.
+pub fn dispatch(app: &AppHandle, request: LaunchRequest, startup: bool) {
+    log::info!("Launch request: {:?}", request);
+
+    if !startup {
+        focus_main_window(app);
+    }
+
+    match request {
+        LaunchRequest::Open { url, start } => {
+            let request = RequestFileLoad {
+                url,
+                start_time: start,
+                http: None,
+                options: None,
+            };
+            if let Err(e) = request.emit(app) {
+                log::error!("Failed to emit file load request: {}", e);
+            }
+        }
+        LaunchRequest::OpenLocal { path, start } => {
+            if let Err(e) = app.state::<LocalPlayback>().open(&[path], start) {
+                log::error!("Failed to open local media: {}", e);
+            }
+        }
+        LaunchRequest::Navigate(navigation) if startup => {
+            *app.state::<PendingNavigation>().0.lock().unwrap() = Some(navigation);
+        }
+        LaunchRequest::Navigate(navigation) => {
+            if let Err(e) = navigation.emit(app) {
+                log::error!("Failed to emit navigation request: {}", e);
+            }
+        }
+    }
+}
+
+fn focus_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_window("main") {
+        window.unminimize().ok();
+        window.show().ok();
+        window.set_focus().ok();
+    }
+}
+
+/// Arguments of a second instance, forwarded by the single-instance plugin.
+pub fn handle_args(app: &AppHandle, args: &[String], cwd: &str) {
+    let args = args.get(1..).unwrap_or_default();
+
+    match parse_args(args, Path::new(cwd)) {
+        Some(request) => dispatch(app, request, false),
+        // A plain relaunch brings the running window forward
+        None => focus_main_window(app),
+    }
+}
+
+pub fn handle_deep_links(app: &AppHandle, urls: &[Url], startup: bool) {
+    for url in urls {
+        match parse_deep_link(url) {
+            Some(request) => dispatch(app, request, startup),
+            None => log::warn!("Ignoring unsupported deep link {}", url),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STREAM: &str = "https://media.example.com/movie.mkv";
+
+    fn args(args: &[&str]) -> Option<LaunchRequest> {
+        let args: Vec<_> = args.iter().map(|arg| arg.to_string()).collect();
+        parse_args(&args, Path::new("/"))
+    }
+
+    fn deep_link(url: &str) -> Option<LaunchRequest> {
+        parse_deep_link(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn parses_times() {
+        assert_eq!(parse_time("300"), Some(300.0));
+        assert_eq!(parse_time("5:00"), Some(300.0));
+        assert_eq!(parse_time("1:05:00"), Some(3900.0));
+        assert_eq!(parse_time("1:-30"), None);
+        assert_eq!(parse_time("-5"), None);
+        assert_eq!(parse_time("inf"), None);
+        assert_eq!(parse_time("5:"), None);
+    }
+
+    #[test]
+    fn seek_takes_an_inline_or_separate_value() {
+        let request = args(&["--seek=5:00", STREAM]);
+        assert!(
+            matches!(&request, Some(LaunchRequest::Open { url, start: Some(start) })
+                if url == STREAM && *start == 300.0),
+            "{:?}",
+            request
+        );
+
+        let request = args(&["--seek", "300", STREAM]);
+        assert!(
+            matches!(&request, Some(LaunchRequest::Open { url, start: Some(start) })
+                if url == STREAM && *start == 300.0),
+            "{:?}",
+            request
+        );
+    }
+
+    #[test]
+    fn unknown_flags_are_ignored() {
+        let request = args(&["--fullscreen", "-v", STREAM]);
+        assert!(
+            matches!(&request, Some(LaunchRequest::Open { url, start: None }) if url == STREAM),
+            "{:?}",
+            request
+        );
+
+        assert!(args(&["--fullscreen"]).is_none());
+    }
+
+    #[test]
+    fn deep_links_are_left_to_the_deep_link_handler() {
+        assert!(args(&["square://item/abc"]).is_none());
+    }
+
+    #[test]
+    fn item_links_navigate() {
+        let request = deep_link("square://item/abc123");
+        assert!(
+            matches!(&request, Some(LaunchRequest::Navigate(NavigationRequest::Item { item_id }))
+                if item_id == "abc123"),
+            "{:?}",
+            request
+        );
+
+        assert!(deep_link("square://item").is_none());
+        assert!(deep_link("square://item/abc/extra").is_none());
+    }
+
+    #[test]
+    fn play_links_only_open_streams() {
+        let request =
+            deep_link("square://play?url=https%3A%2F%2Fmedia.example.com%2Fmovie.mkv&t=1:30");
+        assert!(
+            matches!(&request, Some(LaunchRequest::Open { url, start: Some(start) })
+                if url == STREAM && *start == 90.0),
+            "{:?}",
+            request
+        );
+
+        assert!(deep_link("square://play?url=file%3A%2F%2F%2Fetc%2Fpasswd").is_none());
+        assert!(deep_link("square://play?url=smb%3A%2F%2Fhost%2Fshare").is_none());
+        assert!(deep_link("square://play").is_none());
+    }
+}
@@ -2,25 +2,61 @@ use keyring::Entry;
 use rand::Rng;
 use specta::specta;
 use tauri::{Manager, WebviewBuilder, WindowBuilder, Wry};
+use tauri_plugin_deep_link::DeepLinkExt;
 use tauri_plugin_http;
 use tauri_specta::{collect_events, Event};
 use serde::Serialize;
 
+use crate::control_api::{ControlApi, ControlApiSettings};
+use crate::discord::{DiscordPresence, DiscordSettings};
+use crate::downloads::{
+    DownloadEntry, DownloadManager, DownloadProgressChange, DownloadRequest, DownloadSettings,
+};
+use crate::jellyfin::client::{JellyfinClient, JellyfinError, JellyfinServerInfo};
+use crate::jellyfin::discovery::DiscoveredServer;
+use crate::jellyfin::remote::RemoteControlEvent;
+use crate::jellyfin::socket::SessionSocket;
+use crate::jellyfin::syncplay::{SyncPlay, SyncPlayEvent, SyncPlayGroup};
+use crate::launch::{NavigationRequest, PendingNavigation};
+use crate::local_media::{LocalPlayback, LocalQueueChange};
 use crate::mpv::{
     run_render_thread, AudioChangeEvent, AudioTrackChange, BufferingStateChange, CacheTimeChange,
-    EOFEventChange, ErrorEventChange, FileLoadedChange, PauseForCacheChange, PlayBackStateChange,
+    EOFEventChange, ErrorEventChange, FileLoadedChange, HttpOptions, LoadOptions,
+    MediaControlEvent, NowPlayingChange, PauseForCacheChange, PlayBackStateChange,
     PlayBackTimeChange, PlaybackEvent, RequestAudioEvent, RequestClearEvent, RequestFileLoad,
     RequestPlayBackState, RequestSeekEvent, RequestSpeedEvent, RequestSubtitleEvent,
-    RequestVolumeEvent, SpeedEventChange, SubtitleChangeEvent, SubtitleTrackChange, Track,
-    VolumeEventChange,
+    RequestVolumeEvent, SourceFallbackEvent, SpeedEventChange, SubtitleChangeEvent,
+    SubtitleTrackChange, Track, VolumeEventChange,
 };
+use crate::mpv_config::{MpvConfigError, MpvConfigErrorChange, MpvConfigStatus, MpvScript};
+use crate::power::SleepInhibitionChange;
+use crate::properties::{MpvFormat, MpvPropertyChange, MpvValue};
+use crate::shaders::ShaderPreset;
+use crate::sleep_timer::{SleepTimerChange, SleepTimerMode};
+use crate::stats::PlaybackStatsChange;
+use crate::store::{CachePreset, CacheSettings, GeneralSettings, SleepPolicy};
 
 // Credential operations are handled by the frontend JavaScript API
 
+mod control_api;
 mod credentials;
+mod discord;
+pub mod downloads;
+pub mod history;
+pub mod jellyfin;
+mod launch;
+mod local_media;
+#[cfg(target_os = "linux")]
+mod mpris;
 pub mod mpv;
+mod mpv_config;
+mod mpv_ipc;
 mod power;
-mod store;
+mod properties;
+mod shaders;
+mod sleep_timer;
+mod stats;
+pub mod store;
 
 static VAULT_PASSWORD: std::sync::OnceLock<String> = std::sync::OnceLock::new();
 
@@ -29,6 +65,8 @@ static VAULT_PASSWORD: std::sync::OnceLock<String> = std::sync::OnceLock::new();
 struct AppState {
     render_tx: std::sync::mpsc::Sender<PlaybackEvent>,
     pip_window: std::sync::Arc<std::sync::Mutex<Option<tauri::Window>>>,
+    history: std::sync::Arc<std::sync::Mutex<history::PlaybackHistory>>,
+    jellyfin_session: jellyfin::SharedSession,
 }
 
 /// Helper function to send events to render thread with error logging
@@ -135,11 +173,19 @@ fn playback_speed(app: tauri::AppHandle, speed: f64) {
     let _ = send_render_event(&app, PlaybackEvent::Speed(speed));
 }
 
-/// Load a media file URL
+/// Load a media file URL. `http` carries headers, user agent and cookies for
+/// authenticated streams so credentials stay out of the URL. `options` holds
+/// per-file settings such as start time and preselected tracks.
 #[specta]
 #[tauri::command]
-fn playback_load(app: tauri::AppHandle, url: String) {
-    if let Err(e) = send_render_event(&app, PlaybackEvent::Load(url, None)) {
+fn playback_load(
+    app: tauri::AppHandle,
+    url: String,
+    http: Option<HttpOptions>,
+    options: Option<LoadOptions>,
+) {
+    let options = options.unwrap_or_default();
+    if let Err(e) = send_render_event(&app, PlaybackEvent::Load { url, options, http }) {
         log::error!("{}", e);
     }
 }
@@ -169,6 +215,7 @@ fn playback_clear(app: tauri::AppHandle) {
 /// The subtitle will appear in mpv's track-list so the existing UI can select it via sid.
 /// `title` and `lang` are forwarded to mpv's `sub-add` command so the track displays a
 /// human-friendly name (e.g. "English") instead of the raw URL.
+/// `http` is only used for this subtitle; the video's HTTP options are kept.
 #[specta]
 #[tauri::command]
 fn playback_load_subtitle(
@@ -176,12 +223,561 @@ fn playback_load_subtitle(
     url: String,
     title: Option<String>,
     lang: Option<String>,
+    http: Option<HttpOptions>,
 ) {
-    if let Err(e) = send_render_event(&app, PlaybackEvent::LoadSubtitle { url, title, lang }) {
+    if let Err(e) = send_render_event(
+        &app,
+        PlaybackEvent::LoadSubtitle {
+            url,
+            title,
+            lang,
+            http,
+        },
+    ) {
+        log::error!("{}", e);
+    }
+}
+
+/// Play local files or folders. Folders are queued in natural order, and
+/// subtitles next to each video are added once it has loaded.
+#[specta]
+#[tauri::command]
+fn playback_open_local(
+    local: tauri::State<LocalPlayback>,
+    paths: Vec<String>,
+) -> Result<LocalQueueChange, String> {
+    let paths: Vec<std::path::PathBuf> = paths.into_iter().map(Into::into).collect();
+    local.open(&paths, None)
+}
+
+/// Jump to another file of the local queue
+#[specta]
+#[tauri::command]
+fn playback_local_select(
+    local: tauri::State<LocalPlayback>,
+    index: u32,
+) -> Result<LocalQueueChange, String> {
+    local.select(index)
+}
+
+/// Set the minimum interval in milliseconds between two time position updates
+#[specta]
+#[tauri::command]
+fn playback_time_update_interval(app: tauri::AppHandle, interval_ms: u32) {
+    let _ = send_render_event(&app, PlaybackEvent::TimeUpdateInterval(interval_ms));
+}
+
+/// Start receiving `PlaybackStatsChange` events for a stats overlay.
+/// Every call must be paired with `playback_stats_unsubscribe`.
+#[specta]
+#[tauri::command]
+fn playback_stats_subscribe(app: tauri::AppHandle, webview: tauri::Webview) {
+    let event = PlaybackEvent::StatsSubscription {
+        webview: webview.label().to_string(),
+        subscribed: true,
+    };
+    if let Err(e) = send_render_event(&app, event) {
         log::error!("{}", e);
     }
 }
 
+/// Stop receiving `PlaybackStatsChange` events
+#[specta]
+#[tauri::command]
+fn playback_stats_unsubscribe(app: tauri::AppHandle, webview: tauri::Webview) {
+    let event = PlaybackEvent::StatsSubscription {
+        webview: webview.label().to_string(),
+        subscribed: false,
+    };
+    if let Err(e) = send_render_event(&app, event) {
+        log::error!("{}", e);
+    }
+}
+
+/// Persist network cache settings and apply them to the running player
+#[specta]
+#[tauri::command]
+fn playback_cache_settings(app: tauri::AppHandle, settings: CacheSettings) -> Result<(), String> {
+    GeneralSettings::update(&app, |general| general.cache = settings.clone())?;
+    send_render_event(&app, PlaybackEvent::CacheSettings(settings))
+}
+
+/// Replace the cache settings with one of the built-in presets and apply it
+#[specta]
+#[tauri::command]
+fn playback_cache_preset(
+    app: tauri::AppHandle,
+    preset: CachePreset,
+) -> Result<CacheSettings, String> {
+    let settings = CacheSettings::preset(preset);
+    playback_cache_settings(app, settings.clone())?;
+    Ok(settings)
+}
+
+/// Choose what stays awake during playback and apply it immediately
+#[specta]
+#[tauri::command]
+fn playback_sleep_policy(app: tauri::AppHandle, policy: SleepPolicy) -> Result<(), String> {
+    GeneralSettings::update(&app, |general| general.sleep_policy = policy)?;
+    send_render_event(&app, PlaybackEvent::SleepPolicy(policy))
+}
+
+/// Start a sleep timer, replacing any running one
+#[specta]
+#[tauri::command]
+fn playback_sleep_timer_set(app: tauri::AppHandle, mode: SleepTimerMode) -> Result<(), String> {
+    send_render_event(&app, PlaybackEvent::SleepTimer(Some(mode)))
+}
+
+/// Cancel the running sleep timer
+#[specta]
+#[tauri::command]
+fn playback_sleep_timer_cancel(app: tauri::AppHandle) -> Result<(), String> {
+    send_render_event(&app, PlaybackEvent::SleepTimer(None))
+}
+
+/// Persist the Discord Rich Presence settings and apply them immediately.
+/// Enabling fails without a Discord application id.
+#[specta]
+#[tauri::command]
+fn discord_presence_settings(
+    app: tauri::AppHandle,
+    presence: tauri::State<'_, DiscordPresence>,
+    settings: DiscordSettings,
+) -> Result<(), String> {
+    settings.validate()?;
+    GeneralSettings::update(&app, |general| general.discord = settings.clone())?;
+    presence.set_settings(settings);
+    Ok(())
+}
+
+/// Persist the control API settings and restart it. A token is generated
+/// when enabling without one; the saved settings are returned.
+#[specta]
+#[tauri::command]
+async fn control_api_settings(
+    app: tauri::AppHandle,
+    api: tauri::State<'_, ControlApi>,
+    mut settings: ControlApiSettings,
+) -> Result<ControlApiSettings, String> {
+    if settings.enabled && settings.token.is_empty() {
+        settings.token = control_api::generate_token();
+    }
+    GeneralSettings::update(&app, |general| general.control_api = settings.clone())?;
+    api.apply(&settings).await?;
+    Ok(settings)
+}
+
+/// Replace the control API token and restart the API with it
+#[specta]
+#[tauri::command]
+async fn control_api_regenerate_token(
+    app: tauri::AppHandle,
+    api: tauri::State<'_, ControlApi>,
+) -> Result<ControlApiSettings, String> {
+    let mut settings = GeneralSettings::get(&app)?.unwrap_or_default().control_api;
+    settings.token = control_api::generate_token();
+    control_api_settings(app, api, settings).await
+}
+
+/// Navigation requested by the link or arguments square was launched with.
+/// Later requests arrive as `NavigationRequest` events.
+#[specta]
+#[tauri::command]
+fn launch_take_navigation(pending: tauri::State<PendingNavigation>) -> Option<NavigationRequest> {
+    pending.take()
+}
+
+// ===== JELLYFIN COMMANDS =====
+
+/// Share the signed in Jellyfin session with the backend, or clear it on sign out.
+/// Sessions on unsupported servers are refused, which keeps progress
+/// reporting, SyncPlay and remote control off
+#[specta]
+#[tauri::command]
+async fn jellyfin_set_session(
+    app: tauri::AppHandle,
+    client: tauri::State<'_, JellyfinClient>,
+    session: Option<jellyfin::JellyfinSession>,
+) -> Result<(), JellyfinError> {
+    if let Some(new_session) = &session {
+        match client.server_info(&new_session.server_url).await {
+            Ok(_) => {}
+            Err(e @ JellyfinError::UnsupportedVersion(_)) => {
+                log::warn!("Refusing Jellyfin session: {}", e);
+                apply_jellyfin_session(&app, None);
+                return Err(e);
+            }
+            // Offline the session is still needed, e.g. for downloads
+            Err(e) => log::warn!("Could not check the Jellyfin server version: {}", e),
+        }
+    }
+
+    apply_jellyfin_session(&app, session);
+    Ok(())
+}
+
+fn apply_jellyfin_session(app: &tauri::AppHandle, session: Option<jellyfin::JellyfinSession>) {
+    let app_state = app.state::<AppState>();
+    log::info!("Jellyfin session updated: {:?}", session);
+    app.state::<SessionSocket>().set_session(session.clone());
+    *app_state.jellyfin_session.write().unwrap() = session;
+}
+
+/// Find Jellyfin servers on the local network. Waits `timeout_ms`
+/// (default 2000, at most 10000) for replies.
+#[specta]
+#[tauri::command]
+async fn jellyfin_discover_servers(timeout_ms: Option<u32>) -> Vec<DiscoveredServer> {
+    let timeout_ms = timeout_ms.unwrap_or(2000).min(10_000);
+    jellyfin::discovery::discover(std::time::Duration::from_millis(timeout_ms.into())).await
+}
+
+/// Check that `url` points at a supported Jellyfin server and return its
+/// details, before signing in
+#[specta]
+#[tauri::command]
+async fn jellyfin_server_info(
+    client: tauri::State<'_, JellyfinClient>,
+    url: String,
+) -> Result<JellyfinServerInfo, JellyfinError> {
+    client.server_info(&url).await.map(JellyfinServerInfo::from)
+}
+
+// ===== SYNCPLAY COMMANDS =====
+
+/// List the SyncPlay groups on the signed in server
+#[specta]
+#[tauri::command]
+async fn syncplay_list_groups(
+    syncplay: tauri::State<'_, SyncPlay>,
+) -> Result<Vec<SyncPlayGroup>, String> {
+    syncplay.list_groups().await
+}
+
+/// Create a SyncPlay group and join it
+#[specta]
+#[tauri::command]
+async fn syncplay_create_group(
+    syncplay: tauri::State<'_, SyncPlay>,
+    name: String,
+) -> Result<(), String> {
+    syncplay.create_group(&name).await
+}
+
+#[specta]
+#[tauri::command]
+async fn syncplay_join_group(
+    syncplay: tauri::State<'_, SyncPlay>,
+    group_id: String,
+) -> Result<(), String> {
+    syncplay.join_group(&group_id).await
+}
+
+#[specta]
+#[tauri::command]
+async fn syncplay_leave_group(syncplay: tauri::State<'_, SyncPlay>) -> Result<(), String> {
+    syncplay.leave_group().await
+}
+
+/// Ask the group to pause; used instead of `playback_pause` while in a group
+#[specta]
+#[tauri::command]
+async fn syncplay_pause(syncplay: tauri::State<'_, SyncPlay>) -> Result<(), String> {
+    syncplay.pause().await
+}
+
+/// Ask the group to play; used instead of `playback_play` while in a group
+#[specta]
+#[tauri::command]
+async fn syncplay_unpause(syncplay: tauri::State<'_, SyncPlay>) -> Result<(), String> {
+    syncplay.unpause().await
+}
+
+/// Ask the group to seek to `position` seconds
+#[specta]
+#[tauri::command]
+async fn syncplay_seek(syncplay: tauri::State<'_, SyncPlay>, position: f64) -> Result<(), String> {
+    syncplay.seek(position).await
+}
+
+// ===== MPV USER CONFIG COMMANDS =====
+
+/// Whether the user mpv configuration is enabled and loaded, and where it lives
+#[specta]
+#[tauri::command]
+fn mpv_config_status(app: tauri::AppHandle) -> Result<MpvConfigStatus, MpvConfigError> {
+    mpv_config::status(&app)
+}
+
+/// Turn the user mpv configuration on or off; applied on the next launch
+#[specta]
+#[tauri::command]
+fn mpv_config_set_enabled(app: tauri::AppHandle, enabled: bool) -> Result<(), MpvConfigError> {
+    GeneralSettings::update(&app, |general| general.mpv_user_config = enabled)
+        .map_err(MpvConfigError::Io)
+}
+
+#[specta]
+#[tauri::command]
+fn mpv_scripts_list(app: tauri::AppHandle) -> Result<Vec<MpvScript>, MpvConfigError> {
+    mpv_config::list_scripts(&app)
+}
+
+#[specta]
+#[tauri::command]
+fn mpv_script_enable(app: tauri::AppHandle, name: String) -> Result<(), MpvConfigError> {
+    mpv_config::enable_script(&app, &name)
+}
+
+#[specta]
+#[tauri::command]
+fn mpv_script_disable(app: tauri::AppHandle, name: String) -> Result<(), MpvConfigError> {
+    mpv_config::disable_script(&app, &name)
+}
+
+/// Path of the mpv JSON IPC socket, or `None` while it is turned off
+#[specta]
+#[tauri::command]
+fn mpv_ipc_socket_path(app: tauri::AppHandle) -> Result<Option<String>, String> {
+    if !mpv_ipc::is_enabled(&app) {
+        return Ok(None);
+    }
+
+    let path = mpv_ipc::socket_path(&app)?;
+    Ok(Some(path.to_string_lossy().into_owned()))
+}
+
+/// Start or stop the mpv JSON IPC socket and remember the choice. Returns the
+/// socket path while it is on.
+#[specta]
+#[tauri::command]
+async fn mpv_ipc_set_enabled(
+    app: tauri::AppHandle,
+    enabled: bool,
+) -> Result<Option<String>, String> {
+    // mpv restarts its IPC server whenever the option changes; empty stops it
+    let path = if enabled {
+        Some(mpv_ipc::prepare_socket(&app)?)
+    } else {
+        None
+    };
+
+    render_request(&app, |reply| PlaybackEvent::SetProperty {
+        name: "input-ipc-server".to_string(),
+        value: MpvValue::String(path.clone().unwrap_or_default()),
+        reply,
+    })
+    .await?;
+    GeneralSettings::update(&app, |general| general.mpv_ipc_server = enabled)?;
+
+    log::info!(
+        "mpv IPC server {}",
+        if enabled { "started" } else { "stopped" }
+    );
+    Ok(path)
+}
+
+// ===== MPV PROPERTY COMMANDS =====
+
+/// Send a request to the render thread and wait for its reply
+async fn render_request<T>(
+    app: &tauri::AppHandle,
+    request: impl FnOnce(tokio::sync::oneshot::Sender<Result<T, String>>) -> PlaybackEvent,
+) -> Result<T, String> {
+    let (reply, response) = tokio::sync::oneshot::channel();
+    send_render_event(app, request(reply))?;
+    response
+        .await
+        .map_err(|_| "Render thread dropped the request".to_string())?
+}
+
+/// Read an allowlisted mpv property
+#[specta]
+#[tauri::command]
+async fn mpv_get_property(
+    app: tauri::AppHandle,
+    name: String,
+    format: MpvFormat,
+) -> Result<MpvValue, String> {
+    properties::check_allowed(&app, &name)?;
+    render_request(&app, |reply| PlaybackEvent::GetProperty {
+        name,
+        format,
+        reply,
+    })
+    .await
+}
+
+/// Write an allowlisted mpv property
+#[specta]
+#[tauri::command]
+async fn mpv_set_property(
+    app: tauri::AppHandle,
+    name: String,
+    value: MpvValue,
+) -> Result<(), String> {
+    properties::check_allowed(&app, &name)?;
+    render_request(&app, |reply| PlaybackEvent::SetProperty {
+        name,
+        value,
+        reply,
+    })
+    .await
+}
+
+/// Report changes of an allowlisted mpv property through `MpvPropertyChange`
+#[specta]
+#[tauri::command]
+async fn mpv_observe_property(
+    app: tauri::AppHandle,
+    name: String,
+    format: MpvFormat,
+) -> Result<(), String> {
+    properties::check_allowed(&app, &name)?;
+    render_request(&app, |reply| PlaybackEvent::ObserveProperty {
+        name,
+        format,
+        reply,
+    })
+    .await
+}
+
+#[specta]
+#[tauri::command]
+async fn mpv_unobserve_property(app: tauri::AppHandle, name: String) -> Result<(), String> {
+    render_request(&app, |reply| PlaybackEvent::UnobserveProperty {
+        name,
+        reply,
+    })
+    .await
+}
+
+// ===== SHADER COMMANDS =====
+
+/// Folder relative shader paths in presets resolve against
+#[specta]
+#[tauri::command]
+fn shaders_dir(app: tauri::AppHandle) -> Result<String, String> {
+    shaders::shaders_dir(&app).map(|dir| dir.to_string_lossy().into_owned())
+}
+
+#[specta]
+#[tauri::command]
+fn shaders_list_presets(app: tauri::AppHandle) -> Result<Vec<ShaderPreset>, String> {
+    shaders::list_presets(&app)
+}
+
+/// Create or replace a shader preset
+#[specta]
+#[tauri::command]
+fn shaders_save_preset(app: tauri::AppHandle, preset: ShaderPreset) -> Result<(), String> {
+    shaders::save_preset(&app, preset)
+}
+
+#[specta]
+#[tauri::command]
+fn shaders_delete_preset(app: tauri::AppHandle, name: String) -> Result<(), String> {
+    shaders::delete_preset(&app, &name)
+}
+
+/// Apply a shader preset to the player, or turn shaders off with `null`
+#[specta]
+#[tauri::command]
+fn shaders_apply(app: tauri::AppHandle, name: Option<String>) -> Result<(), String> {
+    let shaders = match &name {
+        Some(name) => shaders::resolve(&app, name)?,
+        None => Vec::new(),
+    };
+
+    send_render_event(
+        &app,
+        PlaybackEvent::Shaders {
+            preset: name,
+            shaders,
+        },
+    )
+}
+
+/// Set the preset applied when loading items of a library type
+#[specta]
+#[tauri::command]
+fn shaders_set_default(
+    app: tauri::AppHandle,
+    library_type: String,
+    name: Option<String>,
+) -> Result<(), String> {
+    GeneralSettings::update(&app, |general| match name {
+        Some(name) => {
+            general.shader_presets.insert(library_type, name);
+        }
+        None => {
+            general.shader_presets.remove(&library_type);
+        }
+    })
+}
+
+// ===== DOWNLOAD COMMANDS =====
+
+/// Queue an item for offline playback
+#[specta]
+#[tauri::command]
+fn downloads_enqueue(
+    downloads: tauri::State<'_, DownloadManager>,
+    request: DownloadRequest,
+) -> Result<DownloadEntry, String> {
+    downloads.enqueue(request)
+}
+
+/// List all queued, running and finished downloads
+#[specta]
+#[tauri::command]
+fn downloads_list(downloads: tauri::State<'_, DownloadManager>) -> Vec<DownloadEntry> {
+    downloads.list()
+}
+
+#[specta]
+#[tauri::command]
+fn downloads_pause(
+    downloads: tauri::State<'_, DownloadManager>,
+    item_id: String,
+) -> Result<(), String> {
+    downloads.pause(&item_id)
+}
+
+#[specta]
+#[tauri::command]
+fn downloads_resume(
+    downloads: tauri::State<'_, DownloadManager>,
+    item_id: String,
+) -> Result<(), String> {
+    downloads.resume(&item_id)
+}
+
+/// Remove a download from the queue, optionally deleting its files
+#[specta]
+#[tauri::command]
+async fn downloads_remove(
+    downloads: tauri::State<'_, DownloadManager>,
+    item_id: String,
+    delete_files: bool,
+) -> Result<(), String> {
+    downloads.remove(&item_id, delete_files).await
+}
+
+/// Persist download limits; downloads started from now on use them
+#[specta]
+#[tauri::command]
+fn downloads_settings(
+    app: tauri::AppHandle,
+    downloads: tauri::State<'_, DownloadManager>,
+    settings: DownloadSettings,
+) -> Result<(), String> {
+    GeneralSettings::update(&app, |general| general.downloads = settings)?;
+    downloads.schedule();
+    Ok(())
+}
+
 // ===== PICTURE IN PICTURE (PIP) COMMANDS =====
 
 /// Show PiP window (makes it visible)
@@ -574,6 +1170,53 @@ pub async fn run() {
             playback_change_audio,
             playback_clear,
             playback_load_subtitle,
+            playback_open_local,
+            playback_local_select,
+            playback_time_update_interval,
+            playback_stats_subscribe,
+            playback_stats_unsubscribe,
+            playback_cache_settings,
+            playback_cache_preset,
+            playback_sleep_policy,
+            playback_sleep_timer_set,
+            playback_sleep_timer_cancel,
+            discord_presence_settings,
+            control_api_settings,
+            control_api_regenerate_token,
+            launch_take_navigation,
+            jellyfin_set_session,
+            jellyfin_discover_servers,
+            jellyfin_server_info,
+            syncplay_list_groups,
+            syncplay_create_group,
+            syncplay_join_group,
+            syncplay_leave_group,
+            syncplay_pause,
+            syncplay_unpause,
+            syncplay_seek,
+            downloads_enqueue,
+            downloads_list,
+            downloads_pause,
+            downloads_resume,
+            downloads_remove,
+            downloads_settings,
+            mpv_config_status,
+            mpv_config_set_enabled,
+            mpv_scripts_list,
+            mpv_script_enable,
+            mpv_script_disable,
+            mpv_ipc_socket_path,
+            mpv_ipc_set_enabled,
+            shaders_dir,
+            shaders_list_presets,
+            shaders_save_preset,
+            shaders_delete_preset,
+            shaders_apply,
+            shaders_set_default,
+            mpv_get_property,
+            mpv_set_property,
+            mpv_observe_property,
+            mpv_unobserve_property,
             toggle_titlebar_hide,
             toggle_fullscreen,
             show_pip_window,
@@ -607,11 +1250,24 @@ pub async fn run() {
             AudioTrackChange,
             CacheTimeChange,
             PauseForCacheChange,
-            BufferingStateChange
+            BufferingStateChange,
+            PlaybackStatsChange,
+            DownloadProgressChange,
+            SourceFallbackEvent,
+            MpvPropertyChange,
+            NowPlayingChange,
+            MediaControlEvent,
+            SleepInhibitionChange,
+            SleepTimerChange,
+            SyncPlayEvent,
+            RemoteControlEvent,
+            NavigationRequest,
+            LocalQueueChange,
+            MpvConfigErrorChange
         ])
         .typ::<Track>()
         .error_handling(tauri_specta::ErrorHandlingMode::Throw)
-        .typ::<store::GeneralSettings>();
+        .typ::<GeneralSettings>();
 
     #[cfg(debug_assertions)]
     specta_builder
@@ -622,12 +1278,29 @@ pub async fn run() {
         .expect("Failed to export typescript bindings");
 
     let app = tauri::Builder::default()
+        // Must come first, so a second instance exits before doing anything else
+        .plugin(tauri_plugin_single_instance::init(|app, args, cwd| {
+            launch::handle_args(app, &args, &cwd);
+        }))
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_http::init())
         .plugin(tauri_plugin_store::Builder::new().build())
         .invoke_handler(specta_builder.invoke_handler())
+        // A reload or crash never unsubscribes, so drop the webview's stats
+        // subscriptions whenever it starts loading
+        .on_page_load(|webview, payload| {
+            if payload.event() == tauri::webview::PageLoadEvent::Started
+                && webview.try_state::<AppState>().is_some()
+            {
+                let event = PlaybackEvent::StatsReset {
+                    webview: webview.label().to_string(),
+                };
+                send_render_event(webview.app_handle(), event).ok();
+            }
+        })
         .setup(move |app| {
             let app_clone = app.handle().clone();
             specta_builder.mount_events(&app_clone);
@@ -682,22 +1355,76 @@ pub async fn run() {
             let app_state = AppState {
                 render_tx: render_tx.clone(),
                 pip_window: std::sync::Arc::new(std::sync::Mutex::new(None)),
+                history: std::sync::Arc::new(std::sync::Mutex::new(history::PlaybackHistory::new(
+                    app_clone.clone(),
+                ))),
+                jellyfin_session: std::sync::Arc::new(std::sync::RwLock::new(None)),
             };
 
+            let http_client = tauri_plugin_http::reqwest::Client::new();
+
+            // Downloads must be managed before the render thread starts loading files
+            app.manage(downloads::init(&app_clone, http_client.clone())?);
+            app.manage(discord::init(&app_clone));
+            app.manage(control_api::init(&app_clone, render_tx.clone()));
+            app.manage(local_media::init(&app_clone, render_tx.clone()));
+
+            // Everything talking to the signed in server shares one client
+            let jellyfin_client =
+                JellyfinClient::new(http_client.clone(), app_state.jellyfin_session.clone());
+
+            // Report playback to the Jellyfin server from the backend
+            let (report_tx, report_rx) = tokio::sync::mpsc::unbounded_channel();
+            tokio::spawn(jellyfin::reporter::run_reporter(
+                jellyfin_client.clone(),
+                report_rx,
+            ));
+
+            // Remote control and SyncPlay commands arrive over the session socket
+            let session_socket = SessionSocket::start();
+            app.manage(jellyfin::syncplay::init(
+                &app_clone,
+                render_tx.clone(),
+                jellyfin_client.clone(),
+                &session_socket,
+            ));
+            jellyfin::remote::init(
+                &app_clone,
+                render_tx.clone(),
+                jellyfin_client.clone(),
+                &session_socket,
+            );
+            app.manage(session_socket);
+            app.manage(jellyfin_client);
+
             // Move all MPV and OpenGL setup to a dedicated thread
             let window_clone = window.clone();
             let app_state_clone = app_state.clone();
             let get_pip_window =
                 Box::new(move || app_state_clone.pip_window.lock().unwrap().clone());
 
+            // Media keys and desktop media widgets
+            #[cfg(target_os = "linux")]
+            mpris::init(&app_clone, render_tx.clone());
+
             let tx = render_tx.clone();
             tokio::spawn(run_render_thread(
                 window_clone,
                 render_tx,
                 render_rx,
                 get_pip_window,
+                app_state.history.clone(),
+                report_tx,
             ));
 
+            match GeneralSettings::get(&app_clone) {
+                Ok(settings) => {
+                    let settings = settings.unwrap_or_default();
+                    tx.send(PlaybackEvent::CacheSettings(settings.cache)).ok();
+                }
+                Err(e) => log::error!("Failed to load cache settings: {}", e),
+            }
+
             let playback_tx = tx.clone();
             RequestPlayBackState::listen_any(&app_clone, move |event| {
                 if event.payload.pause {
@@ -736,11 +1463,15 @@ pub async fn run() {
 
             let playback_tx = tx.clone();
             RequestFileLoad::listen_any(&app_clone, move |event| {
+                let mut options = event.payload.options.unwrap_or_default();
+                options.start = options.start.or(event.payload.start_time);
+
                 playback_tx
-                    .send(PlaybackEvent::Load(
-                        event.payload.url,
-                        event.payload.start_time,
-                    ))
+                    .send(PlaybackEvent::Load {
+                        url: event.payload.url,
+                        options,
+                        http: event.payload.http,
+                    })
                     .ok();
             });
 
@@ -765,6 +1496,27 @@ pub async fn run() {
 
             app.manage(app_state);
 
+            // Open what square was launched with, now that the playback
+            // listeners are in place
+            app.manage(PendingNavigation::default());
+            let cwd = std::env::current_dir().unwrap_or_default();
+            let args: Vec<String> = std::env::args().skip(1).collect();
+            if let Some(request) = launch::parse_args(&args, &cwd) {
+                launch::dispatch(&app_clone, request, true);
+            }
+
+            #[cfg(any(target_os = "linux", target_os = "windows"))]
+            if let Err(e) = app.deep_link().register_all() {
+                log::warn!("Failed to register the square:// scheme: {}", e);
+            }
+            if let Ok(Some(urls)) = app.deep_link().get_current() {
+                launch::handle_deep_links(&app_clone, &urls, true);
+            }
+            let deep_link_app = app_clone.clone();
+            app.deep_link().on_open_url(move |event| {
+                launch::handle_deep_links(&deep_link_app, &event.urls(), false);
+            });
+
             Ok(())
         })
         .on_window_event(|_window, event| {
@@ -780,6 +1532,7 @@ pub async fn run() {
         match event {
             tauri::RunEvent::ExitRequested { code, .. } => {
                 println!("ExitRequested: {:?}", code);
+                app_state.history.lock().unwrap().flush();
             }
             tauri::RunEvent::WindowEvent { label, event, .. } => {
                 match event {
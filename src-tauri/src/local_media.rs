@@ -0,0 +1,366 @@
+//! Playback of local files and folders, outside the Jellyfin library. A
+//! folder becomes a queue in natural order, and subtitle files next to each
+//! video are added once it has loaded.
+
+use std::{
+    cmp::Ordering,
+    fs,
+    path::{Path, PathBuf},
+    sync::{mpsc::Sender, Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tauri_specta::Event;
+
+use crate::mpv::{
+    EOFEventChange, FileLoadedChange, LoadOptions, MediaControlAction, MediaControlEvent,
+    NowPlayingChange, PlaybackEvent,
+};
+use crate::store::GeneralSettings;
+
+const MEDIA_EXTENSIONS: &[&str] = &[
+    "3gp", "aac", "avi", "flac", "flv", "m2ts", "m4a", "m4v", "mka", "mkv", "mov", "mp3", "mp4",
+    "mpeg", "mpg", "ogg", "ogv", "opus", "ts", "wav", "webm", "wmv",
+];
+
+const SUBTITLE_EXTENSIONS: &[&str] = &["ass", "srt", "ssa", "sub", "sup", "vtt"];
+
+/// Subtitle name suffixes that describe the track rather than its language.
+const SUBTITLE_FLAGS: &[&str] = &["cc", "default", "forced", "full", "sdh", "signs", "songs"];
+
+/// The local queue, emitted when it is opened, advances or ends.
+#[derive(Debug, specta::Type, tauri_specta::Event, Serialize, Deserialize, Clone, Default)]
+pub struct LocalQueueChange {
+    pub files: Vec<String>,
+    /// Position of the playing file in `files`.
+    pub index: u32,
+}
+
+#[derive(Debug, Clone)]
+struct SubtitleFile {
+    path: PathBuf,
+    title: String,
+    lang: Option<String>,
+}
+
+#[derive(Default)]
+struct Queue {
+    files: Vec<PathBuf>,
+    index: usize,
+    /// Subtitles of the loading file, added once it has loaded.
+    pending_subtitles: Vec<SubtitleFile>,
+}
+
+/// Local playback queue, managed as app state.
+#[derive(Clone)]
+pub struct LocalPlayback {
+    app: AppHandle,
+    render_tx: Sender<PlaybackEvent>,
+    queue: Arc<Mutex<Queue>>,
+}
+
+pub fn init(app: &AppHandle, render_tx: Sender<PlaybackEvent>) -> LocalPlayback {
+    let local = LocalPlayback {
+        app: app.clone(),
+        render_tx,
+        queue: Arc::new(Mutex::new(Queue::default())),
+    };
+
+    let handler = local.clone();
+    FileLoadedChange::listen_any(app, move |_| handler.add_pending_subtitles());
+
+    let handler = local.clone();
+    EOFEventChange::listen_any(app, move |_| {
+        let auto_play_next = GeneralSettings::get(&handler.app)
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+            .auto_play_next;
+        if auto_play_next {
+            handler.skip(1);
+        }
+    });
+
+    let handler = local.clone();
+    MediaControlEvent::listen_any(app, move |event| match event.payload.action {
+        MediaControlAction::Next => handler.skip(1),
+        MediaControlAction::Previous => handler.skip(-1),
+    });
+
+    // Anything else loaded, or a clear, ends the local queue
+    let handler = local.clone();
+    NowPlayingChange::listen_any(app, move |event| {
+        let payload = event.payload;
+        if payload.item_id.is_some() || payload.title.is_none() {
+            handler.end();
+        }
+    });
+
+    local
+}
+
+impl LocalPlayback {
+    /// Queue `paths`, expanding folders, and play the first file from `start`
+    /// seconds.
+    pub fn open(&self, paths: &[PathBuf], start: Option<f64>) -> Result<LocalQueueChange, String> {
+        let mut files = Vec::new();
+        for path in paths {
+            if path.is_dir() {
+                files.extend(folder_media(path)?);
+            } else if path.is_file() {
+                files.push(path.clone());
+            } else {
+                return Err(format!("{} does not exist", path.display()));
+            }
+        }
+
+        if files.is_empty() {
+            return Err("No playable files found".to_string());
+        }
+
+        log::info!("Opening {} local files", files.len());
+        {
+            let mut queue = self.queue.lock().unwrap();
+            queue.files = files;
+            queue.index = 0;
+        }
+        self.play_current(start)
+    }
+
+    /// Jump to the file at `index` in the queue.
+    pub fn select(&self, index: u32) -> Result<LocalQueueChange, String> {
+        {
+            let mut queue = self.queue.lock().unwrap();
+            if index as usize >= queue.files.len() {
+                return Err(format!("No local file at position {}", index));
+            }
+            queue.index = index as usize;
+        }
+        self.play_current(None)
+    }
+
+    fn skip(&self, offset: isize) {
+        {
+            let mut queue = self.queue.lock().unwrap();
+            let Some(index) = queue.index.checked_add_signed(offset) else {
+                return;
+            };
+            if index >= queue.files.len() {
+                return;
+            }
+            queue.index = index;
+        }
+
+        if let Err(e) = self.play_current(None) {
+            log::error!("Failed to play the next local file: {}", e);
+        }
+    }
+
+    fn end(&self) {
+        {
+            let mut queue = self.queue.lock().unwrap();
+            if queue.files.is_empty() {
+                return;
+            }
+            *queue = Queue::default();
+        }
+
+        LocalQueueChange::default().emit(&self.app).ok();
+    }
+
+    fn play_current(&self, start: Option<f64>) -> Result<LocalQueueChange, String> {
+        let (path, change) = {
+            let mut queue = self.queue.lock().unwrap();
+            let path = queue.files[queue.index].clone();
+            queue.pending_subtitles = sibling_subtitles(&path);
+
+            let change = LocalQueueChange {
+                files: queue
+                    .files
+                    .iter()
+                    .map(|file| file.to_string_lossy().into_owned())
+                    .collect(),
+                index: queue.index as u32,
+            };
+            (path, change)
+        };
+
+        let options = LoadOptions {
+            start,
+            media_title: path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+        self.render_tx
+            .send(PlaybackEvent::Load {
+                url: path.to_string_lossy().into_owned(),
+                options,
+                http: None,
+            })
+            .map_err(|e| format!("Failed to send event to render thread: {}", e))?;
+
+        change.emit(&self.app).ok();
+        Ok(change)
+    }
+
+    /// `sub-add` needs a loaded file, so subtitles wait for `FileLoadedChange`.
+    fn add_pending_subtitles(&self) {
+        let subtitles = std::mem::take(&mut self.queue.lock().unwrap().pending_subtitles);
+
+        for subtitle in subtitles {
+            log::debug!("Adding local subtitle {}", subtitle.path.display());
+            self.render_tx
+                .send(PlaybackEvent::LoadSubtitle {
+                    url: subtitle.path.to_string_lossy().into_owned(),
+                    title: Some(subtitle.title),
+                    lang: subtitle.lang,
+                    http: None,
+                })
+                .ok();
+        }
+    }
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| extensions.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Media files directly inside `folder`, in natural order.
+fn folder_media(folder: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries =
+        fs::read_dir(folder).map_err(|e| format!("Failed to read {}: {}", folder.display(), e))?;
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && has_extension(path, MEDIA_EXTENSIONS))
+        .collect();
+
+    files.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+    Ok(files)
+}
+
+/// Compare names the way people count, so `Episode 2` sorts before `Episode 10`.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let take_number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                        digits.push(c);
+                    }
+                    digits
+                };
+                let x = take_number(&mut a);
+                let y = take_number(&mut b);
+                let (x_trimmed, y_trimmed) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+
+                let ordering = x_trimmed
+                    .len()
+                    .cmp(&y_trimmed.len())
+                    .then_with(|| x_trimmed.cmp(y_trimmed))
+                    .then_with(|| x.len().cmp(&y.len()));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+/// Subtitle files next to `video` named after it, e.g. `Movie.srt`,
+/// `Movie.en.srt` or `Movie.en.forced.ass` for `Movie.mkv`.
+fn sibling_subtitles(video: &Path) -> Vec<SubtitleFile> {
+    let (Some(folder), Some(stem)) = (video.parent(), video.file_stem()) else {
+        return Vec::new();
+    };
+    let stem = stem.to_string_lossy().to_lowercase();
+
+    let Ok(entries) = fs::read_dir(folder) else {
+        return Vec::new();
+    };
+
+    let mut subtitles: Vec<SubtitleFile> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && has_extension(path, SUBTITLE_EXTENSIONS))
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_string_lossy().into_owned();
+            let suffix = name.to_lowercase().strip_prefix(&stem)?.to_string();
+            if !suffix.is_empty() && !suffix.starts_with('.') {
+                return None;
+            }
+
+            Some(SubtitleFile {
+                lang: guess_language(&suffix),
+                title: path.file_name()?.to_string_lossy().into_owned(),
+                path,
+            })
+        })
+        .collect();
+
+    subtitles.sort_by(|a, b| natural_cmp(&a.title, &b.title));
+    subtitles
+}
+
+/// Language code from name suffixes like `.en`, `.eng.forced` or `.pt-BR.sdh`.
+fn guess_language(suffix: &str) -> Option<String> {
+    suffix
+        .split('.')
+        .filter(|part| !part.is_empty() && !SUBTITLE_FLAGS.contains(part))
+        .find(|part| {
+            let code = part.split(['-', '_']).next().unwrap_or_default();
+            (2..=3).contains(&code.len()) && code.chars().all(|c| c.is_ascii_alphabetic())
+        })
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_sort_by_value() {
+        assert_eq!(natural_cmp("Episode 2", "Episode 10"), Ordering::Less);
+        assert_eq!(natural_cmp("Episode 10", "Episode 2"), Ordering::Greater);
+        assert_eq!(natural_cmp("episode 3", "Episode 3"), Ordering::Equal);
+        assert_eq!(natural_cmp("Episode", "Episode 1"), Ordering::Less);
+    }
+
+    #[test]
+    fn leading_zeros_only_break_ties() {
+        assert_eq!(natural_cmp("S01E09", "S01E10"), Ordering::Less);
+        assert_eq!(natural_cmp("Episode 002", "Episode 10"), Ordering::Less);
+        assert_eq!(natural_cmp("Episode 2", "Episode 02"), Ordering::Less);
+        assert_eq!(natural_cmp("Episode 02", "Episode 02"), Ordering::Equal);
+    }
+
+    #[test]
+    fn languages_skip_track_flags() {
+        // Suffixes of `Movie.en.forced.ass` and `Movie.forced.srt`
+        assert_eq!(guess_language(".en.forced").as_deref(), Some("en"));
+        assert_eq!(guess_language(".forced"), None);
+        assert_eq!(guess_language(".eng.sdh").as_deref(), Some("eng"));
+        assert_eq!(guess_language(".pt-BR").as_deref(), Some("pt-BR"));
+        assert_eq!(guess_language(".pt_br.sdh").as_deref(), Some("pt_br"));
+        assert_eq!(guess_language(""), None);
+        assert_eq!(guess_language(".english"), None);
+    }
+}
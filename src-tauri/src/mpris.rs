@@ -0,0 +1,679 @@
+//! MPRIS2 D-Bus service, so media keys, desktop media widgets and `playerctl`
+//! can control playback on Linux.
+
+use std::{collections::HashMap, sync::mpsc::Sender, time::Instant};
+
+use tauri::{AppHandle, Manager};
+use tauri_specta::Event;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use zbus::{
+    connection, interface,
+    object_server::SignalEmitter,
+    zvariant::{ObjectPath, Value},
+};
+
+use crate::mpv::{
+    EOFEventChange, MediaControlAction, MediaControlEvent, NowPlayingChange, PlayBackStateChange,
+    PlayBackTimeChange, PlaybackEvent, SpeedEventChange, VolumeEventChange,
+};
+
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.square";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+
+/// Position jumps larger than this between two time updates count as a seek.
+const SEEK_THRESHOLD: f64 = 2.0;
+
+const MICROS_PER_SECOND: f64 = 1_000_000.0;
+
+/// Playback rates advertised as `MinimumRate` and `MaximumRate`.
+const MIN_RATE: f64 = 0.25;
+const MAX_RATE: f64 = 4.0;
+
+/// Player state change picked up from the render thread's events.
+enum StateChange {
+    NowPlaying(NowPlayingChange),
+    Paused(bool),
+    Time { position: f64, duration: f64 },
+    Volume(u8),
+    Speed(f64),
+    Ended,
+}
+
+/// Request from an MPRIS client that is handled by the app, not the player.
+enum AppAction {
+    Raise,
+    Quit,
+    MediaControl(MediaControlAction),
+}
+
+struct Root {
+    actions: UnboundedSender<AppAction>,
+}
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    fn raise(&self) {
+        self.actions.send(AppAction::Raise).ok();
+    }
+
+    fn quit(&self) {
+        self.actions.send(AppAction::Quit).ok();
+    }
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> String {
+        "square".to_string()
+    }
+
+    #[zbus(property)]
+    fn desktop_entry(&self) -> String {
+        "square".to_string()
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+struct Player {
+    actions: UnboundedSender<AppAction>,
+    render_tx: Sender<PlaybackEvent>,
+    now_playing: Option<NowPlayingChange>,
+    /// Bumped on every load so each file gets its own track id.
+    track: u64,
+    paused: bool,
+    position: f64,
+    duration: f64,
+    last_time_update: Instant,
+    volume: f64,
+    rate: f64,
+}
+
+impl Player {
+    fn new(actions: UnboundedSender<AppAction>, render_tx: Sender<PlaybackEvent>) -> Self {
+        Self {
+            actions,
+            render_tx,
+            now_playing: None,
+            track: 0,
+            paused: true,
+            position: 0.0,
+            duration: 0.0,
+            last_time_update: Instant::now(),
+            volume: 1.0,
+            rate: 1.0,
+        }
+    }
+
+    fn send(&self, event: PlaybackEvent) {
+        if self.render_tx.send(event).is_err() {
+            log::warn!("MPRIS: render thread is not running");
+        }
+    }
+
+    fn track_id(&self) -> ObjectPath<'static> {
+        if self.now_playing.is_none() {
+            return ObjectPath::from_static_str_unchecked(NO_TRACK);
+        }
+
+        ObjectPath::try_from(format!("/org/square/track/{}", self.track))
+            .unwrap_or_else(|_| ObjectPath::from_static_str_unchecked(NO_TRACK))
+    }
+
+    /// Record a time update. Returns `true` if the position jumped, which
+    /// MPRIS clients expect to hear about through `Seeked`.
+    fn update_time(&mut self, position: f64, duration: f64) -> bool {
+        let expected = if self.paused {
+            self.position
+        } else {
+            self.position + self.last_time_update.elapsed().as_secs_f64() * self.rate
+        };
+
+        self.position = position;
+        self.duration = duration;
+        self.last_time_update = Instant::now();
+
+        (position - expected).abs() > SEEK_THRESHOLD
+    }
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    fn next(&self) {
+        self.actions
+            .send(AppAction::MediaControl(MediaControlAction::Next))
+            .ok();
+    }
+
+    fn previous(&self) {
+        self.actions
+            .send(AppAction::MediaControl(MediaControlAction::Previous))
+            .ok();
+    }
+
+    fn pause(&self) {
+        self.send(PlaybackEvent::Pause);
+    }
+
+    fn play_pause(&self) {
+        self.send(if self.paused {
+            PlaybackEvent::Play
+        } else {
+            PlaybackEvent::Pause
+        });
+    }
+
+    /// Stopping pauses; closing the player is left to the frontend.
+    fn stop(&self) {
+        self.send(PlaybackEvent::Pause);
+    }
+
+    fn play(&self) {
+        self.send(PlaybackEvent::Play);
+    }
+
+    fn seek(&self, offset: i64) {
+        self.send(PlaybackEvent::Seek(offset as f64 / MICROS_PER_SECOND));
+    }
+
+    fn set_position(&self, track_id: ObjectPath<'_>, position: i64) {
+        // Requests for a track that is no longer current must be ignored
+        if track_id != self.track_id() || position < 0 {
+            return;
+        }
+        self.send(PlaybackEvent::AbsoluteSeek(
+            position as f64 / MICROS_PER_SECOND,
+        ));
+    }
+
+    fn open_uri(&self, _uri: String) -> zbus::fdo::Result<()> {
+        Err(zbus::fdo::Error::NotSupported(
+            "Opening URIs is not supported".to_string(),
+        ))
+    }
+
+    #[zbus(signal)]
+    async fn seeked(emitter: &SignalEmitter<'_>, position: i64) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> String {
+        match (&self.now_playing, self.paused) {
+            (None, _) => "Stopped",
+            (Some(_), true) => "Paused",
+            (Some(_), false) => "Playing",
+        }
+        .to_string()
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    #[zbus(property)]
+    fn set_rate(&mut self, rate: f64) {
+        if rate > 0.0 {
+            self.rate = rate.clamp(MIN_RATE, MAX_RATE);
+            self.send(PlaybackEvent::Speed(self.rate));
+        }
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        MIN_RATE
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        MAX_RATE
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, Value<'static>> {
+        let mut metadata = HashMap::new();
+        metadata.insert("mpris:trackid".to_string(), Value::from(self.track_id()));
+
+        if let Some(now_playing) = &self.now_playing {
+            if self.duration > 0.0 {
+                metadata.insert(
+                    "mpris:length".to_string(),
+                    Value::from((self.duration * MICROS_PER_SECOND) as i64),
+                );
+            }
+            if let Some(title) = &now_playing.title {
+                metadata.insert("xesam:title".to_string(), Value::from(title.clone()));
+            }
+            if let Some(artwork_url) = &now_playing.artwork_url {
+                metadata.insert("mpris:artUrl".to_string(), Value::from(artwork_url.clone()));
+            }
+        }
+
+        metadata
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        self.volume
+    }
+
+    #[zbus(property)]
+    fn set_volume(&mut self, volume: f64) {
+        self.volume = volume.clamp(0.0, 1.0);
+        self.send(PlaybackEvent::Volume((self.volume * 100.0).round() as u8));
+    }
+
+    /// Clients poll this; changes are only announced through `Seeked`.
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        (self.position * MICROS_PER_SECOND) as i64
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        self.now_playing.is_some()
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        self.now_playing.is_some()
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        self.now_playing.is_some()
+    }
+
+    #[zbus(property)]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+/// Start the MPRIS service on the session bus. Failing to reach the bus is
+/// logged and otherwise ignored; playback works without it.
+pub fn init(app: &AppHandle, render_tx: Sender<PlaybackEvent>) {
+    let (change_tx, change_rx) = unbounded_channel();
+    listen(app, change_tx);
+
+    let (action_tx, mut action_rx) = unbounded_channel();
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        while let Some(action) = action_rx.recv().await {
+            handle_action(&handle, action);
+        }
+    });
+
+    tauri::async_runtime::spawn(async move {
+        let result = async {
+            let builder = connection::Builder::session()?;
+            run(builder, action_tx, render_tx, change_rx).await
+        }
+        .await;
+
+        if let Err(e) = result {
+            log::warn!("MPRIS service unavailable: {}", e);
+        }
+    });
+}
+
+fn handle_action(app: &AppHandle, action: AppAction) {
+    match action {
+        AppAction::Raise => {
+            if let Some(window) = app.get_window("main") {
+                window.show().ok();
+                window.set_focus().ok();
+            }
+        }
+        AppAction::Quit => app.exit(0),
+        AppAction::MediaControl(action) => {
+            MediaControlEvent { action }.emit(app).ok();
+        }
+    }
+}
+
+fn listen(app: &AppHandle, change_tx: UnboundedSender<StateChange>) {
+    let tx = change_tx.clone();
+    NowPlayingChange::listen_any(app, move |event| {
+        tx.send(StateChange::NowPlaying(event.payload)).ok();
+    });
+
+    let tx = change_tx.clone();
+    PlayBackStateChange::listen_any(app, move |event| {
+        tx.send(StateChange::Paused(event.payload.pause)).ok();
+    });
+
+    let tx = change_tx.clone();
+    PlayBackTimeChange::listen_any(app, move |event| {
+        tx.send(StateChange::Time {
+            position: event.payload.position,
+            duration: event.payload.duration,
+        })
+        .ok();
+    });
+
+    let tx = change_tx.clone();
+    VolumeEventChange::listen_any(app, move |event| {
+        tx.send(StateChange::Volume(event.payload.percentage)).ok();
+    });
+
+    let tx = change_tx.clone();
+    SpeedEventChange::listen_any(app, move |event| {
+        tx.send(StateChange::Speed(event.payload.speed)).ok();
+    });
+
+    EOFEventChange::listen_any(app, move |_| {
+        change_tx.send(StateChange::Ended).ok();
+    });
+}
+
+/// Serve the MPRIS objects on the bus `builder` connects to and keep them in
+/// sync with the player. Taking a builder lets the service run on a private
+/// bus as well as the session bus.
+async fn run(
+    builder: connection::Builder<'_>,
+    actions: UnboundedSender<AppAction>,
+    render_tx: Sender<PlaybackEvent>,
+    mut change_rx: UnboundedReceiver<StateChange>,
+) -> zbus::Result<()> {
+    let connection = builder
+        .name(BUS_NAME)?
+        .serve_at(
+            OBJECT_PATH,
+            Root {
+                actions: actions.clone(),
+            },
+        )?
+        .serve_at(OBJECT_PATH, Player::new(actions, render_tx))?
+        .build()
+        .await?;
+    log::info!("MPRIS service registered as {}", BUS_NAME);
+
+    let player_ref = connection
+        .object_server()
+        .interface::<_, Player>(OBJECT_PATH)
+        .await?;
+    let emitter = player_ref.signal_emitter();
+
+    while let Some(change) = change_rx.recv().await {
+        let mut player = player_ref.get_mut().await;
+
+        let result = match change {
+            StateChange::NowPlaying(now_playing) => {
+                player.now_playing = now_playing.loaded.then_some(now_playing);
+                player.track += 1;
+                player.position = 0.0;
+                player.duration = 0.0;
+
+                let metadata = player.metadata_changed(emitter).await;
+                metadata.and(player.playback_status_changed(emitter).await)
+            }
+            StateChange::Paused(paused) => {
+                player.paused = paused;
+                player.last_time_update = Instant::now();
+                player.playback_status_changed(emitter).await
+            }
+            StateChange::Time { position, duration } => {
+                let duration_changed = player.duration != duration;
+                let seeked = player.update_time(position, duration);
+
+                let mut result = Ok(());
+                if duration_changed {
+                    result = result.and(player.metadata_changed(emitter).await);
+                }
+                if seeked {
+                    result = result.and(Player::seeked(emitter, player.position()).await);
+                }
+                result
+            }
+            StateChange::Volume(percentage) => {
+                player.volume = f64::from(percentage) / 100.0;
+                player.volume_changed(emitter).await
+            }
+            StateChange::Speed(speed) => {
+                player.rate = speed;
+                player.rate_changed(emitter).await
+            }
+            StateChange::Ended => {
+                player.paused = true;
+                player.playback_status_changed(emitter).await
+            }
+        };
+
+        if let Err(e) = result {
+            log::warn!("MPRIS: failed to publish player state: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader},
+        process::{Child, Command, Stdio},
+        sync::mpsc::{channel, Receiver},
+        time::Duration,
+    };
+
+    use zbus::{proxy::CacheProperties, Proxy};
+
+    use super::*;
+
+    /// A `dbus-daemon` of its own, so the test neither needs nor touches the
+    /// user's session bus.
+    struct PrivateBus {
+        daemon: Child,
+        address: String,
+    }
+
+    impl PrivateBus {
+        fn start() -> Self {
+            let mut daemon = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .expect("dbus-daemon is required for the MPRIS tests");
+
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.take().unwrap())
+                .read_line(&mut address)
+                .unwrap();
+
+            Self {
+                daemon,
+                address: address.trim().to_string(),
+            }
+        }
+
+        fn builder(&self) -> connection::Builder<'static> {
+            connection::Builder::address(self.address.as_str()).unwrap()
+        }
+    }
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            self.daemon.kill().ok();
+            self.daemon.wait().ok();
+        }
+    }
+
+    struct Service {
+        _bus: PrivateBus,
+        proxy: Proxy<'static>,
+        change_tx: UnboundedSender<StateChange>,
+        render_rx: Receiver<PlaybackEvent>,
+    }
+
+    impl Service {
+        async fn start() -> Self {
+            let bus = PrivateBus::start();
+            let (render_tx, render_rx) = channel();
+            let (change_tx, change_rx) = unbounded_channel();
+            let (action_tx, _action_rx) = unbounded_channel();
+            tokio::spawn(run(bus.builder(), action_tx, render_tx, change_rx));
+
+            let client = bus.builder().build().await.unwrap();
+            let proxy = zbus::proxy::Builder::new(&client)
+                .destination(BUS_NAME)
+                .unwrap()
+                .path(OBJECT_PATH)
+                .unwrap()
+                .interface("org.mpris.MediaPlayer2.Player")
+                .unwrap()
+                .cache_properties(CacheProperties::No)
+                .build()
+                .await
+                .unwrap();
+
+            let service = Self {
+                _bus: bus,
+                proxy,
+                change_tx,
+                render_rx,
+            };
+            // The name is only taken once the service is up
+            service.wait_for_status("Stopped").await;
+            service
+        }
+
+        async fn wait_for_status(&self, expected: &str) {
+            let wait = async {
+                loop {
+                    match self.proxy.get_property::<String>("PlaybackStatus").await {
+                        Ok(status) if status == expected => return,
+                        _ => tokio::time::sleep(Duration::from_millis(10)).await,
+                    }
+                }
+            };
+
+            tokio::time::timeout(Duration::from_secs(10), wait)
+                .await
+                .unwrap_or_else(|_| panic!("playback status never became {}", expected));
+        }
+
+        /// Call `method` and return what it sent to the render thread. The
+        /// event is sent before the reply, so it is already queued.
+        async fn call<B>(&self, method: &str, body: &B) -> Option<PlaybackEvent>
+        where
+            B: serde::Serialize + zbus::zvariant::DynamicType,
+        {
+            self.proxy.call::<_, _, ()>(method, body).await.unwrap();
+            self.render_rx.try_recv().ok()
+        }
+    }
+
+    fn now_playing(title: Option<&str>) -> NowPlayingChange {
+        NowPlayingChange {
+            title: title.map(str::to_string),
+            loaded: true,
+            ..NowPlayingChange::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn controls_reach_the_render_thread() {
+        let service = Service::start().await;
+
+        service
+            .change_tx
+            .send(StateChange::NowPlaying(now_playing(Some("Movie"))))
+            .unwrap();
+        service.change_tx.send(StateChange::Paused(false)).unwrap();
+        service.wait_for_status("Playing").await;
+
+        let event = service.call("PlayPause", &()).await;
+        assert!(matches!(event, Some(PlaybackEvent::Pause)), "{:?}", event);
+
+        service.change_tx.send(StateChange::Paused(true)).unwrap();
+        service.wait_for_status("Paused").await;
+        let event = service.call("PlayPause", &()).await;
+        assert!(matches!(event, Some(PlaybackEvent::Play)), "{:?}", event);
+
+        let event = service.call("Seek", &(-5_000_000i64,)).await;
+        assert!(
+            matches!(event, Some(PlaybackEvent::Seek(offset)) if offset == -5.0),
+            "{:?}",
+            event
+        );
+
+        let track = ObjectPath::try_from("/org/square/track/1").unwrap();
+        let event = service.call("SetPosition", &(&track, 30_000_000i64)).await;
+        assert!(
+            matches!(event, Some(PlaybackEvent::AbsoluteSeek(position)) if position == 30.0),
+            "{:?}",
+            event
+        );
+
+        // A position for a track that is no longer playing is ignored
+        let stale = ObjectPath::try_from("/org/square/track/0").unwrap();
+        let event = service.call("SetPosition", &(&stale, 30_000_000i64)).await;
+        assert!(event.is_none(), "{:?}", event);
+    }
+
+    #[tokio::test]
+    async fn untitled_items_are_not_stopped() {
+        let service = Service::start().await;
+
+        service
+            .change_tx
+            .send(StateChange::NowPlaying(now_playing(None)))
+            .unwrap();
+        service.change_tx.send(StateChange::Paused(false)).unwrap();
+        service.wait_for_status("Playing").await;
+
+        service
+            .change_tx
+            .send(StateChange::NowPlaying(NowPlayingChange::default()))
+            .unwrap();
+        service.wait_for_status("Stopped").await;
+    }
+
+    #[tokio::test]
+    async fn rates_are_clamped_to_the_advertised_range() {
+        let service = Service::start().await;
+
+        for (requested, expected) in [(10.0, MAX_RATE), (0.1, MIN_RATE), (1.5, 1.5)] {
+            service.proxy.set_property("Rate", requested).await.unwrap();
+            let event = service.render_rx.try_recv().ok();
+            assert!(
+                matches!(event, Some(PlaybackEvent::Speed(speed)) if speed == expected),
+                "{:?}",
+                event
+            );
+        }
+    }
+}
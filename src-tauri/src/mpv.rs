@@ -1,10 +1,11 @@
 // ===== DEPENDENCIES =====
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
+    sync::atomic::{AtomicBool, Ordering},
     sync::mpsc::{Receiver, Sender},
-    sync::Once,
-    time::Duration,
+    sync::{Arc, Mutex, Once},
+    time::{Duration, Instant},
 };
 
 use gl::types::GLsizei;
@@ -25,7 +26,17 @@ use serde::{Deserialize, Serialize};
 use tauri::{Manager, PhysicalSize, Window};
 use tauri_specta::Event;
 
-use crate::power::PlaybackSleepBlocker;
+use crate::downloads::DownloadManager;
+use crate::history::PlaybackHistory;
+use crate::jellyfin::reporter::{PlaybackReportInfo, ProgressTracker, ReportEvent};
+use crate::mpv_config;
+use crate::mpv_ipc;
+use crate::power::{PlaybackSleepBlocker, SleepInhibitionChange};
+use crate::properties::{self, MpvFormat, MpvValue, PropertyObservers};
+use crate::shaders;
+use crate::sleep_timer::{SleepTimer, SleepTimerMode};
+use crate::stats::PlaybackStats;
+use crate::store::{CacheSettings, GeneralSettings, SleepPolicy};
 
 // ===== OPENGL CONTEXT MANAGEMENT =====
 
@@ -93,17 +104,54 @@ impl OpenGLContext {
 
 // ===== MPV PLAYER MANAGEMENT =====
 
+/// mpv's own cache defaults, restored when the cache settings or their
+/// read-ahead are turned off.
+const MPV_CACHE_SECS: f64 = 1000.0;
+const MPV_DEMUXER_MAX_BYTES: &str = "150MiB";
+const MPV_DEMUXER_MAX_BACK_BYTES: &str = "50MiB";
+const MPV_CACHE_PAUSE_WAIT: f64 = 1.0;
+const MPV_DEMUXER_READAHEAD_SECS: f64 = 1.0;
+
+/// State handed to mpv's wakeup callback. `pending` coalesces bursts of
+/// wakeups into a single `PlaybackEvent::MpvEvents` until the render thread
+/// has drained the queue.
+struct MpvWakeup {
+    render_tx: Sender<PlaybackEvent>,
+    pending: AtomicBool,
+}
+
+unsafe extern "C" fn on_mpv_wakeup(data: *mut std::ffi::c_void) {
+    let wakeup = &*(data as *const MpvWakeup);
+    if !wakeup.pending.swap(true, Ordering::AcqRel) {
+        let _ = wakeup.render_tx.send(PlaybackEvent::MpvEvents);
+    }
+}
+
 /// MPV player management struct
 pub struct MpvPlayer {
     pub mpv: Mpv,
     pub render_context: RenderContext,
     pub window: Window,
     sleep_blocker: PlaybackSleepBlocker,
+    wakeup: Option<Box<MpvWakeup>>,
+    default_user_agent: String,
+    /// HTTP options of the currently loaded file, restored after a subtitle
+    /// load that brought its own.
+    http_options: Option<HttpOptions>,
+    /// mpv 0.38 added a playlist index argument to `loadfile`, before the
+    /// per-file options.
+    loadfile_takes_index: bool,
 }
 
 impl MpvPlayer {
     pub fn new(display: &Display, window: &Window) -> Result<Self, Box<dyn std::error::Error>> {
-        let mut mpv = Mpv::new()?;
+        let mut mpv = mpv_config::create_mpv(window.app_handle())?;
+
+        // Error messages reveal shader compile failures, which mpv otherwise
+        // only shows as black frames, and broken user config lines or scripts.
+        // Requested before the user config loads so none of those are missed.
+        mpv.request_log_messages("error")?;
+        mpv_config::load_user_config(window.app_handle(), &mpv);
 
         // Configure MPV properties
         mpv.set_property("vo", "libmpv")?;
@@ -118,15 +166,18 @@ impl MpvPlayer {
             mpv.set_property("log-file", "/tmp/mpv.log")?;
         };
 
+        mpv_ipc::start(window.app_handle(), &mpv);
+
         // Observe properties
         mpv.observe_property("pause", libmpv2::Format::Flag, 1)?;
-        mpv.observe_property("time-pos", libmpv2::Format::String, 2)?;
+        mpv.observe_property("time-pos", libmpv2::Format::Double, 2)?;
         mpv.observe_property("track-list", libmpv2::Format::String, 3)?;
         mpv.observe_property("volume", libmpv2::Format::Int64, 4)?;
         mpv.observe_property("aid", libmpv2::Format::String, 5)?;
         mpv.observe_property("sid", libmpv2::Format::String, 6)?;
         mpv.observe_property("speed", libmpv2::Format::Double, 7)?;
         mpv.observe_property("eof-reached", libmpv2::Format::Flag, 11)?;
+        mpv.observe_property("duration", libmpv2::Format::Double, 12)?;
         // Cache and buffering properties
         mpv.observe_property("demuxer-cache-time", libmpv2::Format::Double, 8)?;
         mpv.observe_property("cache-buffering-state", libmpv2::Format::Int64, 9)?;
@@ -134,6 +185,13 @@ impl MpvPlayer {
 
         mpv.disable_deprecated_events()?;
 
+        let default_user_agent = mpv.get_property::<String>("user-agent").unwrap_or_default();
+        let mpv_version = mpv
+            .get_property::<String>("mpv-version")
+            .unwrap_or_default();
+        let loadfile_takes_index = mpv_version_at_least(&mpv_version, (0, 38));
+        log::info!("Using {}", mpv_version);
+
         // Create render context
         let render_context = RenderContext::new(
             unsafe { mpv.ctx.as_mut() },
@@ -146,14 +204,124 @@ impl MpvPlayer {
             ],
         )?;
 
+        let app_handle = window.app_handle().clone();
         Ok(MpvPlayer {
             mpv,
             render_context,
             window: window.clone(),
-            sleep_blocker: PlaybackSleepBlocker::default(),
+            sleep_blocker: PlaybackSleepBlocker::new(
+                GeneralSettings::get(window.app_handle())
+                    .ok()
+                    .flatten()
+                    .unwrap_or_default()
+                    .sleep_policy,
+                Box::new(move |mechanism| {
+                    SleepInhibitionChange { mechanism }.emit(&app_handle).ok();
+                }),
+            ),
+            wakeup: None,
+            default_user_agent,
+            http_options: None,
+            loadfile_takes_index,
         })
     }
 
+    /// Set the HTTP header fields and user agent of the playing file only.
+    /// mpv drops file-local options when the file ends, so credentials never
+    /// leak into an unrelated load.
+    fn set_file_http_options(&mut self, http: Option<&HttpOptions>) -> Result<(), libmpv2::Error> {
+        let header_fields = http.map(HttpOptions::header_fields).unwrap_or_default();
+        let user_agent = http
+            .and_then(|http| http.user_agent.as_deref())
+            .unwrap_or(&self.default_user_agent);
+
+        self.mpv
+            .set_property("file-local-options/http-header-fields", header_fields)?;
+        self.mpv
+            .set_property("file-local-options/user-agent", user_agent)?;
+        Ok(())
+    }
+
+    /// Replace the `glsl-shaders` chain. An empty list turns shaders off.
+    pub fn set_shaders(&mut self, shaders: &[String]) -> Result<(), libmpv2::Error> {
+        self.mpv
+            .command("change-list", &["glsl-shaders", "clr", ""])?;
+        for shader in shaders {
+            self.mpv
+                .command("change-list", &["glsl-shaders", "append", shader])?;
+        }
+        Ok(())
+    }
+
+    /// Ask mpv to notify the render thread through `render_tx` whenever new
+    /// events are queued, instead of having the thread poll for them.
+    pub fn set_wakeup_callback(&mut self, render_tx: Sender<PlaybackEvent>) {
+        let wakeup = Box::new(MpvWakeup {
+            render_tx,
+            pending: AtomicBool::new(false),
+        });
+
+        unsafe {
+            libmpv2_sys::mpv_set_wakeup_callback(
+                self.mpv.ctx.as_ptr(),
+                Some(on_mpv_wakeup),
+                &*wakeup as *const MpvWakeup as *mut std::ffi::c_void,
+            );
+        }
+
+        self.wakeup = Some(wakeup);
+    }
+
+    /// Re-arm the wakeup callback. Must be called before draining mpv's event
+    /// queue so events arriving mid-drain schedule another pass.
+    pub fn clear_wakeup(&self) {
+        if let Some(wakeup) = &self.wakeup {
+            wakeup.pending.store(false, Ordering::Release);
+        }
+    }
+
+    pub fn apply_cache_settings(&mut self, settings: &CacheSettings) -> Result<(), libmpv2::Error> {
+        // mpv decides per stream whether to cache; the settings only size the cache
+        self.mpv.set_property("cache", "auto")?;
+
+        if settings.enabled {
+            self.mpv.set_property("cache-secs", settings.cache_secs)?;
+            self.mpv.set_property(
+                "demuxer-max-bytes",
+                format!("{}MiB", settings.max_forward_mib),
+            )?;
+            self.mpv.set_property(
+                "demuxer-max-back-bytes",
+                format!("{}MiB", settings.max_back_mib),
+            )?;
+        } else {
+            self.mpv.set_property("cache-secs", MPV_CACHE_SECS)?;
+            self.mpv
+                .set_property("demuxer-max-bytes", MPV_DEMUXER_MAX_BYTES)?;
+            self.mpv
+                .set_property("demuxer-max-back-bytes", MPV_DEMUXER_MAX_BACK_BYTES)?;
+        }
+
+        // Hold playback until `readahead_secs` are buffered, both at start and after a stall
+        let readahead = settings.enabled && settings.readahead_secs > 0.0;
+        self.mpv
+            .set_property("cache-pause-initial", if readahead { "yes" } else { "no" })?;
+        if readahead {
+            self.mpv
+                .set_property("cache-pause-wait", settings.readahead_secs)?;
+            self.mpv
+                .set_property("demuxer-readahead-secs", settings.readahead_secs)?;
+        } else {
+            self.mpv
+                .set_property("cache-pause-wait", MPV_CACHE_PAUSE_WAIT)?;
+            self.mpv
+                .set_property("demuxer-readahead-secs", MPV_DEMUXER_READAHEAD_SECS)?;
+        }
+
+        log::info!("Applied cache settings: {:?}", settings);
+        Ok(())
+    }
+
     fn should_prevent_sleep(&self) -> bool {
         let idle_active = self.mpv.get_property::<bool>("idle-active").unwrap_or(true);
         let paused = self.mpv.get_property::<bool>("pause").unwrap_or(true);
@@ -173,30 +341,46 @@ impl MpvPlayer {
         let loaded_file = !self.mpv.get_property::<bool>("idle-active").unwrap_or(true);
         match event {
             PlaybackEvent::Play => {
-                self.mpv.set_property("pause", false).unwrap();
+                if let Err(e) = self.mpv.set_property("pause", false) {
+                    log::warn!("Failed to resume playback: {}", e);
+                }
                 self.sync_sleep_prevention();
             }
             PlaybackEvent::Pause => {
-                self.mpv.set_property("pause", true).unwrap();
+                if let Err(e) = self.mpv.set_property("pause", true) {
+                    log::warn!("Failed to pause playback: {}", e);
+                }
                 self.sync_sleep_prevention();
             }
             PlaybackEvent::Seek(time) => {
                 if loaded_file {
-                    self.mpv
+                    if let Err(e) = self
+                        .mpv
                         .command("seek", &[&time.to_string(), "relative+exact"])
-                        .unwrap();
+                    {
+                        log::warn!("Failed to seek by {}: {}", time, e);
+                    }
                 }
             }
             PlaybackEvent::AbsoluteSeek(time) => {
-                self.mpv
-                    .command("seek", &[&time.to_string(), "absolute+exact"])
-                    .unwrap();
+                if loaded_file {
+                    if let Err(e) = self
+                        .mpv
+                        .command("seek", &[&time.to_string(), "absolute+exact"])
+                    {
+                        log::warn!("Failed to seek to {}: {}", time, e);
+                    }
+                }
             }
             PlaybackEvent::Volume(volume) => {
-                self.mpv.set_property("volume", volume as i64).unwrap();
+                if let Err(e) = self.mpv.set_property("volume", volume as i64) {
+                    log::warn!("Failed to set volume to {}: {}", volume, e);
+                }
             }
             PlaybackEvent::Speed(speed) => {
-                self.mpv.set_property("speed", speed).unwrap();
+                if let Err(e) = self.mpv.set_property("speed", speed) {
+                    log::warn!("Failed to set speed to {}: {}", speed, e);
+                }
             }
             PlaybackEvent::EndOfFile => {
                 self.release_sleep_prevention();
@@ -206,12 +390,29 @@ impl MpvPlayer {
                 eprintln!("Error: {}", error);
             }
             PlaybackEvent::ChangeSubtitle(subtitle) => {
-                self.mpv.set_property("sid", subtitle).unwrap();
+                if let Err(e) = self.mpv.set_property("sid", subtitle.as_str()) {
+                    log::warn!("Failed to select subtitle track {}: {}", subtitle, e);
+                }
             }
             PlaybackEvent::ChangeAudio(audio) => {
-                self.mpv.set_property("aid", audio).unwrap();
+                if let Err(e) = self.mpv.set_property("aid", audio.as_str()) {
+                    log::warn!("Failed to select audio track {}: {}", audio, e);
+                }
             }
-            PlaybackEvent::LoadSubtitle { url, title, lang } => {
+            PlaybackEvent::LoadSubtitle {
+                url,
+                title,
+                lang,
+                http,
+            } => {
+                // `sub-add` fetches synchronously, so subtitle-specific HTTP options
+                // only need to be in place for the duration of the command.
+                if let Some(http) = &http {
+                    if let Err(e) = self.set_file_http_options(Some(http)) {
+                        log::warn!("Failed to apply HTTP options for subtitle: {}", e);
+                    }
+                }
+
                 // `sub-add` with "auto" appends the subtitle to the track-list without
                 // force-selecting it, so the user's current sid / subtitle-off choice
                 // is preserved.  The track becomes available for manual selection via
@@ -228,21 +429,62 @@ impl MpvPlayer {
                     self.mpv.command("sub-add", &[&url, "auto"])
                 };
                 if let Err(e) = result {
-                    log::warn!("sub-add failed for '{}': {}", url, e);
+                    log::warn!("sub-add failed for '{}': {}", redact_url(&url), e);
+                }
+
+                if http.is_some() {
+                    let current = self.http_options.take();
+                    if let Err(e) = self.set_file_http_options(current.as_ref()) {
+                        log::warn!("Failed to restore HTTP options after subtitle load: {}", e);
+                    }
+                    self.http_options = current;
                 }
             }
-            PlaybackEvent::Load(url, start_time) => {
-                if let Some(start_time) = start_time {
-                    let start_option = format!("start={}", start_time);
-                    self.mpv
-                        .command("loadfile", &[&url, "replace", &start_option])
-                        .unwrap();
-                } else {
-                    self.mpv.command("loadfile", &[&url, "replace"]).unwrap();
+            PlaybackEvent::Load { url, options, http } => {
+                log::info!("Loading '{}' with {:?}", redact_url(&url), http);
+
+                // HTTP options travel with the file so they end with it
+                let per_file_options = options
+                    .to_mpv_options()
+                    .into_iter()
+                    .chain(HttpOptions::to_mpv_options(
+                        http.as_ref(),
+                        &self.default_user_agent,
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                self.http_options = http;
+
+                // Since mpv 0.38 the third argument is the playlist index, which
+                // `replace` ignores; the options follow it
+                let mut args = vec![url.as_str(), "replace"];
+                if self.loadfile_takes_index {
+                    args.push("-1");
+                }
+                args.push(&per_file_options);
+                let loaded = self
+                    .mpv
+                    .command("loadfile", &args)
+                    .and_then(|_| self.mpv.set_property("pause", false));
+                if let Err(e) = loaded {
+                    log::error!("Failed to load '{}': {}", redact_url(&url), e);
+                    ErrorEventChange {
+                        message: format!("Failed to load media: {}", e),
+                    }
+                    .emit(self.window.app_handle())
+                    .ok();
                 }
-                self.mpv.set_property("pause", false).unwrap();
                 self.sync_sleep_prevention();
             }
+            PlaybackEvent::SleepPolicy(policy) => {
+                self.sleep_blocker.set_policy(policy);
+                self.sync_sleep_prevention();
+            }
+            PlaybackEvent::CacheSettings(settings) => {
+                if let Err(e) = self.apply_cache_settings(&settings) {
+                    log::error!("Failed to apply cache settings: {}", e);
+                }
+            }
             PlaybackEvent::FileLoaded => {
                 // self.mpv.set_property("time-pos", "0").unwrap();
                 let time = self.mpv.get_property::<f64>("time-pos").unwrap();
@@ -261,6 +503,21 @@ impl MpvPlayer {
     }
 }
 
+impl Drop for MpvPlayer {
+    fn drop(&mut self) {
+        // Unregister before `wakeup` is freed; mpv may still call it while shutting down
+        if self.wakeup.is_some() {
+            unsafe {
+                libmpv2_sys::mpv_set_wakeup_callback(
+                    self.mpv.ctx.as_ptr(),
+                    None,
+                    std::ptr::null_mut(),
+                );
+            }
+        }
+    }
+}
+
 /// Render manager struct - handles OpenGL contexts and MPV rendering
 pub struct RenderManager {
     gl_contexts: HashMap<String, OpenGLContext>,
@@ -434,17 +691,221 @@ impl RenderManager {
     }
 }
 
+/// Default minimum time between two `PlayBackTimeChange` emissions.
+const DEFAULT_TIME_UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Rate limits `time-pos` updates sent to the frontend.
+///
+/// Regular position updates are dropped while inside the interval, but seeks
+/// and pause changes always emit right away so the UI never lags behind a user
+/// action.
+pub struct TimeReporter {
+    interval: Duration,
+    position: f64,
+    duration: f64,
+    last_emit: Option<Instant>,
+}
+
+impl Default for TimeReporter {
+    fn default() -> Self {
+        Self {
+            interval: DEFAULT_TIME_UPDATE_INTERVAL,
+            position: 0.0,
+            duration: 0.0,
+            last_emit: None,
+        }
+    }
+}
+
+impl TimeReporter {
+    pub fn set_interval(&mut self, interval_ms: u32) {
+        self.interval = Duration::from_millis(interval_ms as u64);
+        log::info!("Time update interval set to {}ms", interval_ms);
+    }
+
+    pub fn update_position(&mut self, position: f64, app_handle: &tauri::AppHandle) {
+        self.position = position;
+
+        if let Some(last_emit) = self.last_emit {
+            if last_emit.elapsed() < self.interval {
+                return;
+            }
+        }
+
+        self.emit(app_handle);
+    }
+
+    pub fn update_duration(&mut self, duration: f64) {
+        self.duration = duration;
+    }
+
+    pub fn emit(&mut self, app_handle: &tauri::AppHandle) {
+        let percent = if self.duration > 0.0 {
+            (self.position / self.duration * 100.0).clamp(0.0, 100.0)
+        } else {
+            0.0
+        };
+
+        PlayBackTimeChange {
+            position: self.position,
+            duration: self.duration,
+            percent,
+        }
+        .emit(app_handle)
+        .ok();
+        self.last_emit = Some(Instant::now());
+    }
+}
+
 enum SleepAction {
     None,
     Sync,
     Release,
 }
 
-/// Event handler struct
-pub struct EventHandler;
+/// Fallback sources of the current load. mpv reports a failed open as an
+/// error on `EndFile`; until playback has actually started, such an error moves
+/// on to the next source instead of leaving the player dead.
+#[derive(Default)]
+struct SourceFallback {
+    remaining: VecDeque<FallbackSource>,
+    /// Options of the original load; start position and track choices carry
+    /// over to every fallback.
+    options: LoadOptions,
+    http: Option<HttpOptions>,
+    attempt: u32,
+    active: bool,
+}
+
+impl SourceFallback {
+    fn begin(&mut self, options: &LoadOptions, http: Option<&HttpOptions>) {
+        let mut options = options.clone();
+        self.remaining = std::mem::take(&mut options.fallbacks).into();
+        self.options = options;
+        self.http = http.cloned();
+        self.attempt = 0;
+        self.active = true;
+    }
+
+    fn playback_started(&mut self) {
+        self.active = false;
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// The current source failed to load. Returns the next source to load, or
+    /// `None` once there is nothing left to try.
+    fn next_source(
+        &mut self,
+        reason: &str,
+        app_handle: &tauri::AppHandle,
+    ) -> Option<(String, LoadOptions, Option<HttpOptions>)> {
+        if !self.active {
+            return None;
+        }
+
+        let Some(source) = self.remaining.pop_front() else {
+            self.active = false;
+            log::error!("Failed to load media, no fallback sources left: {}", reason);
+            ErrorEventChange {
+                message: format!("Failed to load media: {}", reason),
+            }
+            .emit(app_handle)
+            .ok();
+            return None;
+        };
+
+        self.attempt += 1;
+        log::warn!(
+            "Source failed to load ({}), falling back to '{}'",
+            reason,
+            redact_url(&source.url)
+        );
+
+        SourceFallbackEvent {
+            reason: reason.to_string(),
+            label: source.label.clone(),
+            attempt: self.attempt,
+            remaining: self.remaining.len() as u32,
+        }
+        .emit(app_handle)
+        .ok();
+
+        let mut options = self.options.clone();
+        if source.report.is_some() {
+            options.report = source.report;
+        }
+
+        Some((
+            source.url,
+            options,
+            source.http.or_else(|| self.http.clone()),
+        ))
+    }
+}
+
+/// Event handler struct - owns the render thread's per-playback state that
+/// mpv events feed into
+pub struct EventHandler {
+    stats: PlaybackStats,
+    time_reporter: TimeReporter,
+    history: Arc<Mutex<PlaybackHistory>>,
+    progress: ProgressTracker,
+    fallback: SourceFallback,
+    /// Name of the shader preset currently applied.
+    shader_preset: Option<String>,
+    /// Whether `shader_preset` is a library default rather than the user's pick.
+    shader_from_library: bool,
+    properties: PropertyObservers,
+    sleep_timer: SleepTimer,
+}
 
 impl EventHandler {
+    pub fn new(
+        history: Arc<Mutex<PlaybackHistory>>,
+        report_tx: tokio::sync::mpsc::UnboundedSender<ReportEvent>,
+    ) -> Self {
+        Self {
+            stats: PlaybackStats::default(),
+            time_reporter: TimeReporter::default(),
+            history,
+            progress: ProgressTracker::new(report_tx),
+            fallback: SourceFallback::default(),
+            shader_preset: None,
+            shader_from_library: false,
+            properties: PropertyObservers::default(),
+            sleep_timer: SleepTimer::default(),
+        }
+    }
+
+    fn apply_shaders(
+        &mut self,
+        player: &mut MpvPlayer,
+        preset: Option<String>,
+        shaders: Vec<String>,
+        from_library: bool,
+    ) {
+        match player.set_shaders(&shaders) {
+            Ok(()) => {
+                log::info!("Applied shader preset {:?}", preset);
+                self.shader_preset = preset.filter(|_| !shaders.is_empty());
+                self.shader_from_library = from_library && self.shader_preset.is_some();
+            }
+            Err(e) => {
+                log::error!("Failed to apply shader preset {:?}: {}", preset, e);
+                ErrorEventChange {
+                    message: format!("Failed to apply shaders: {}", e),
+                }
+                .emit(player.window.app_handle())
+                .ok();
+            }
+        }
+    }
+
     fn handle_mpv_events(
+        &mut self,
         event: libmpv2::events::Event,
         window: &Window,
         render_tx: Sender<PlaybackEvent>,
@@ -452,11 +913,28 @@ impl EventHandler {
         let app_handle = window.app_handle();
         let mut sleep_action = SleepAction::None;
 
+        if let libmpv2::events::Event::PropertyChange {
+            change,
+            reply_userdata,
+            ..
+        } = &event
+        {
+            if self.stats.handle_property(*reply_userdata, change)
+                || self
+                    .properties
+                    .handle_property(*reply_userdata, change, app_handle)
+            {
+                return sleep_action;
+            }
+        }
+
         match event {
             libmpv2::events::Event::FileLoaded => {
                 //    render_tx.send(PlaybackEvent::FileLoaded).unwrap();
                 //window.emit("file-loaded", ()).unwrap();
                 render_tx.send(PlaybackEvent::FileLoaded).unwrap();
+                self.history.lock().unwrap().file_loaded();
+                self.progress.file_loaded();
             }
 
             libmpv2::events::Event::LogMessage {
@@ -466,6 +944,27 @@ impl EventHandler {
                 log_level: _,
             } => {
                 log::info!("{}: {} - {}", prefix, level, text);
+                mpv_config::report_log_message(app_handle, prefix, level, text);
+
+                if level == "error" && shaders::is_shader_error(prefix, text) {
+                    if let Some(preset) = self.shader_preset.take() {
+                        ErrorEventChange {
+                            message: format!(
+                                "Shader preset '{}' failed to load and was turned off: {}",
+                                preset,
+                                text.trim()
+                            ),
+                        }
+                        .emit(app_handle)
+                        .ok();
+                        render_tx
+                            .send(PlaybackEvent::Shaders {
+                                preset: None,
+                                shaders: Vec::new(),
+                            })
+                            .ok();
+                    }
+                }
             }
 
             libmpv2::events::Event::PropertyChange {
@@ -474,32 +973,50 @@ impl EventHandler {
                 reply_userdata: 1,
             } => {
                 PlayBackStateChange { pause }.emit(app_handle).ok();
+                self.time_reporter.emit(app_handle);
+                self.progress.set_paused(pause);
                 sleep_action = SleepAction::Sync;
                 //  window.emit("pause", pause).unwrap();
             }
             libmpv2::events::Event::PropertyChange {
                 name: "time-pos",
-                change: PropertyData::Str(time),
+                change: PropertyData::Double(time),
                 reply_userdata: 2,
             } => {
-                PlayBackTimeChange {
-                    position: time.to_string(),
-                }
-                .emit(app_handle)
-                .ok();
+                self.time_reporter.update_position(time, app_handle);
+                self.history.lock().unwrap().update_position(time);
+                self.progress.update_position(time);
                 //window.emit("playback-time", time).unwrap();
             }
 
+            libmpv2::events::Event::PropertyChange {
+                name: "duration",
+                change: PropertyData::Double(duration),
+                reply_userdata: 12,
+            } => {
+                self.time_reporter.update_duration(duration);
+                self.history.lock().unwrap().update_duration(duration);
+            }
+
+            // Fired once a seek has completed and playback resumes from the new position
+            libmpv2::events::Event::PlaybackRestart => {
+                self.time_reporter.emit(app_handle);
+                self.fallback.playback_started();
+            }
+
             libmpv2::events::Event::PropertyChange {
                 name: "volume",
                 change: PropertyData::Int64(volume),
                 reply_userdata: 4,
             } => {
-                VolumeEventChange {
-                    percentage: volume as u8,
+                // The sleep timer's fade is not the user's volume
+                if !self.sleep_timer.is_fading() {
+                    VolumeEventChange {
+                        percentage: volume as u8,
+                    }
+                    .emit(app_handle)
+                    .ok();
                 }
-                .emit(app_handle)
-                .ok();
             }
             libmpv2::events::Event::PropertyChange {
                 name: "track-list",
@@ -552,6 +1069,8 @@ impl EventHandler {
                 reply_userdata: 5,
             } => {
                 log::debug!("aid: {}", aid);
+                self.history.lock().unwrap().update_aid(aid);
+                self.progress.set_aid(aid);
 
                 let parsed = aid.parse().ok();
 
@@ -568,6 +1087,8 @@ impl EventHandler {
                 reply_userdata: 6,
             } => {
                 log::debug!("aid: {}", sid);
+                self.history.lock().unwrap().update_sid(sid);
+                self.progress.set_sid(sid);
                 let parsed = sid.parse().ok();
 
                 if let Some(id) = parsed {
@@ -624,7 +1145,10 @@ impl EventHandler {
             } => {
                 if reached {
                     log::debug!("end of file is reached");
+                    self.history.lock().unwrap().flush();
+                    self.progress.stop();
                     sleep_action = SleepAction::Release;
+                    self.sleep_timer.end_of_file(app_handle);
                     EOFEventChange.emit(app_handle).ok();
                 };
 
@@ -798,7 +1322,9 @@ pub struct RequestPlayBackState {
 
 #[derive(Debug, specta::Type, tauri_specta::Event, Serialize, Deserialize, Clone)]
 pub struct PlayBackTimeChange {
-    pub position: String,
+    pub position: f64,
+    pub duration: f64,
+    pub percent: f64,
 }
 
 #[derive(Debug, specta::Type, tauri_specta::Event, Serialize, Deserialize, Clone)]
@@ -817,7 +1343,218 @@ pub struct FileLoadedChange {
 #[derive(Debug, specta::Type, tauri_specta::Event, Serialize, Deserialize, Clone)]
 pub struct RequestFileLoad {
     pub url: String,
-    pub start_time: Option<f64>, // TODO: add audio_list and subtitle_list
+    pub start_time: Option<f64>,
+    #[serde(default)]
+    pub http: Option<HttpOptions>,
+    /// Per-load options; `options.start` takes precedence over `start_time`.
+    #[serde(default)]
+    pub options: Option<LoadOptions>,
+}
+
+/// mpv options that may be passed through `LoadOptions::extra`. Anything
+/// touching files, scripts, networking or output drivers is deliberately left out.
+const LOAD_OPTION_ALLOWLIST: &[&str] = &[
+    "ab-loop-a",
+    "ab-loop-b",
+    "alang",
+    "audio-delay",
+    "brightness",
+    "contrast",
+    "deinterlace",
+    "gamma",
+    "hue",
+    "loop-file",
+    "saturation",
+    "secondary-sub-visibility",
+    "slang",
+    "sub-delay",
+    "sub-pos",
+    "sub-scale",
+    "sub-visibility",
+    "video-aspect-override",
+    "video-rotate",
+    "video-zoom",
+];
+
+/// Options applied to a single `loadfile`, so tracks and timing are correct
+/// from the first frame instead of being switched after `FileLoadedChange`.
+#[derive(Debug, specta::Type, Serialize, Deserialize, Clone, Default)]
+pub struct LoadOptions {
+    /// Jellyfin item id, used to key local resume history and for progress
+    /// reporting. Not passed to mpv.
+    pub item_id: Option<String>,
+    /// Enables backend progress reporting to Jellyfin for `item_id`.
+    pub report: Option<PlaybackReportInfo>,
+    pub start: Option<f64>,
+    pub end: Option<f64>,
+    pub aid: Option<String>,
+    pub sid: Option<String>,
+    pub secondary_sid: Option<String>,
+    /// Title shown by the OS media controls and mpv's OSD.
+    pub media_title: Option<String>,
+    /// Episode line shown under the title by media integrations, e.g.
+    /// `S1:E2 Pilot`. Not passed to mpv.
+    #[serde(default)]
+    pub episode: Option<String>,
+    /// Poster or thumbnail shown by the OS media controls. Not passed to mpv.
+    #[serde(default)]
+    pub artwork_url: Option<String>,
+    /// Library type, e.g. `anime`, whose default shader preset is applied.
+    /// Not passed to mpv.
+    #[serde(default)]
+    pub library_type: Option<String>,
+    #[serde(default)]
+    pub audio_files: Vec<String>,
+    #[serde(default)]
+    pub sub_files: Vec<String>,
+    /// Additional per-file mpv options, restricted to `LOAD_OPTION_ALLOWLIST`.
+    #[serde(default)]
+    pub extra: HashMap<String, String>,
+    /// Sources tried in order when the main URL fails to load, e.g. a direct
+    /// stream followed by a lower bitrate transcode. Not passed to mpv.
+    #[serde(default)]
+    pub fallbacks: Vec<FallbackSource>,
+}
+
+/// Alternative source for a load, used when the previous one fails to open.
+#[derive(Debug, specta::Type, Serialize, Deserialize, Clone)]
+pub struct FallbackSource {
+    pub url: String,
+    /// HTTP options for this source; the original load's are used when unset.
+    #[serde(default)]
+    pub http: Option<HttpOptions>,
+    /// Short description for the UI, e.g. "Transcode (4 Mbps)".
+    pub label: Option<String>,
+    /// Replaces `LoadOptions::report`, as the play method usually changes.
+    pub report: Option<PlaybackReportInfo>,
+}
+
+impl LoadOptions {
+    /// Format as the `key=value` items of the comma separated list `loadfile`
+    /// expects.
+    fn to_mpv_options(&self) -> Vec<String> {
+        let mut options = Vec::new();
+
+        if let Some(start) = self.start {
+            options.push(format!("start={}", start));
+        }
+        if let Some(end) = self.end {
+            options.push(format!("end={}", end));
+        }
+        if let Some(aid) = &self.aid {
+            options.push(format!("aid={}", quote(aid)));
+        }
+        if let Some(sid) = &self.sid {
+            options.push(format!("sid={}", quote(sid)));
+        }
+        if let Some(secondary_sid) = &self.secondary_sid {
+            options.push(format!("secondary-sid={}", quote(secondary_sid)));
+        }
+        if let Some(media_title) = &self.media_title {
+            options.push(format!("force-media-title={}", quote(media_title)));
+        }
+        for audio_file in &self.audio_files {
+            options.push(format!("audio-files-append={}", quote(audio_file)));
+        }
+        for sub_file in &self.sub_files {
+            options.push(format!("sub-files-append={}", quote(sub_file)));
+        }
+        for (name, value) in &self.extra {
+            if !LOAD_OPTION_ALLOWLIST.contains(&name.as_str()) {
+                log::warn!("Ignoring per-file mpv option '{}': not allowed", name);
+                continue;
+            }
+            options.push(format!("{}={}", name, quote(value)));
+        }
+
+        options
+    }
+}
+
+/// Quote a `loadfile` option value as `%len%value`, so commas and `=` survive.
+fn quote(value: &str) -> String {
+    format!("%{}%{}", value.len(), value)
+}
+
+/// HTTP request options mpv should use when fetching a stream or subtitle,
+/// so credentials can travel in headers instead of the URL.
+#[derive(specta::Type, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpOptions {
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    pub user_agent: Option<String>,
+    /// Raw `Cookie` header value, e.g. `"a=1; b=2"`.
+    pub cookies: Option<String>,
+}
+
+impl HttpOptions {
+    /// Format the headers as an mpv string list. Commas are escaped since they
+    /// separate list items.
+    fn header_fields(&self) -> String {
+        let cookie = self.cookies.as_ref().map(|cookies| ("Cookie", cookies));
+        self.headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+            .chain(cookie)
+            .map(|(name, value)| {
+                format!("{}: {}", name, value)
+                    .replace('\\', "\\\\")
+                    .replace(',', "\\,")
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Per-file `loadfile` options for `http`. The user agent is always set
+    /// so a previous file's can't carry over.
+    fn to_mpv_options(http: Option<&Self>, default_user_agent: &str) -> Vec<String> {
+        let user_agent = http
+            .and_then(|http| http.user_agent.as_deref())
+            .unwrap_or(default_user_agent);
+
+        let mut options = vec![format!("user-agent={}", quote(user_agent))];
+        if let Some(header_fields) = http.map(Self::header_fields) {
+            if !header_fields.is_empty() {
+                options.push(format!("http-header-fields={}", quote(&header_fields)));
+            }
+        }
+        options
+    }
+}
+
+// Header values are usually credentials, so only names ever reach the logs
+impl std::fmt::Debug for HttpOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpOptions")
+            .field("headers", &self.headers.keys().collect::<Vec<_>>())
+            .field("user_agent", &self.user_agent)
+            .field("cookies", &self.cookies.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+/// Query parameters that carry credentials and must not be logged.
+const SENSITIVE_QUERY_KEYS: &[&str] = &["api_key", "apikey", "token", "access_token"];
+
+/// Strip credential query parameters from a URL before it is logged.
+pub fn redact_url(url: &str) -> String {
+    let Some((base, query)) = url.split_once('?') else {
+        return url.to_string();
+    };
+
+    let query = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if SENSITIVE_QUERY_KEYS.contains(&key.to_lowercase().as_str()) => {
+                format!("{}=<redacted>", key)
+            }
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+
+    format!("{}?{}", base, query)
 }
 
 #[derive(Debug, specta::Type, tauri_specta::Event, Serialize, Deserialize, Clone)]
@@ -854,6 +1591,19 @@ pub struct ErrorEventChange {
     pub message: String,
 }
 
+/// A source failed to load and playback moved on to the next fallback.
+#[derive(Debug, specta::Type, tauri_specta::Event, Serialize, Deserialize, Clone)]
+pub struct SourceFallbackEvent {
+    /// Error mpv reported for the failed source.
+    pub reason: String,
+    /// Label of the fallback now loading.
+    pub label: Option<String>,
+    /// 1-based index of the fallback now loading.
+    pub attempt: u32,
+    /// Fallbacks left after this one.
+    pub remaining: u32,
+}
+
 #[derive(Debug, specta::Type, tauri_specta::Event, Serialize, Deserialize, Clone)]
 pub struct SubtitleChangeEvent {
     pub index: String,
@@ -887,6 +1637,32 @@ pub struct AudioTrackChange {
 #[derive(Debug, specta::Type, tauri_specta::Event, Serialize, Deserialize, Clone)]
 pub struct RequestClearEvent;
 
+/// What the player is showing, for OS media integrations. Emitted on every
+/// load and when the player is cleared.
+#[derive(Debug, Default, specta::Type, tauri_specta::Event, Serialize, Deserialize, Clone)]
+pub struct NowPlayingChange {
+    pub item_id: Option<String>,
+    pub title: Option<String>,
+    pub episode: Option<String>,
+    pub artwork_url: Option<String>,
+    /// `false` once the player is cleared, even if the item had no title.
+    pub loaded: bool,
+}
+
+#[derive(Debug, specta::Type, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum MediaControlAction {
+    Next,
+    Previous,
+}
+
+/// Media control the backend cannot handle itself, e.g. a "next" media key,
+/// forwarded to the frontend which owns the queue.
+#[derive(Debug, specta::Type, tauri_specta::Event, Serialize, Deserialize, Clone)]
+pub struct MediaControlEvent {
+    pub action: MediaControlAction,
+}
+
 #[derive(Debug, specta::Type, tauri_specta::Event, Serialize, Deserialize, Clone)]
 pub struct CacheTimeChange {
     pub time: f64,
@@ -921,14 +1697,63 @@ pub enum PlaybackEvent {
     Error(String),
     ChangeSubtitle(String),
     ChangeAudio(String),
+    /// Select the audio track with this Jellyfin stream index.
+    AudioStreamIndex(u32),
+    /// Select the subtitle track with this Jellyfin stream index, `-1` for none.
+    SubtitleStreamIndex(i32),
     Resize(u32, u32),
-    Load(String, Option<f64>),
+    Load {
+        url: String,
+        options: LoadOptions,
+        http: Option<HttpOptions>,
+    },
     Clear,
     Redraw,
     FileLoaded,
     SwitchTarget(String),
     ResizePipWindow { width: u32, height: u32 },
     DestroyPipContext,
+    CacheSettings(CacheSettings),
+    SleepPolicy(SleepPolicy),
+    /// Start a sleep timer, or cancel it with `None`.
+    SleepTimer(Option<SleepTimerMode>),
+    /// mpv has queued events that should be drained with `wait_event`.
+    MpvEvents,
+    /// Start or stop one stats overlay subscription of a webview.
+    StatsSubscription { webview: String, subscribed: bool },
+    /// A webview started (re)loading; its stats subscriptions are gone.
+    StatsReset { webview: String },
+    /// Minimum interval in milliseconds between two `PlayBackTimeChange` events.
+    TimeUpdateInterval(u32),
+    /// Replace the `glsl-shaders` chain with the resolved files of `preset`.
+    Shaders {
+        preset: Option<String>,
+        shaders: Vec<String>,
+    },
+    /// Generic property API; the allowlist is checked before sending these.
+    #[serde(skip)]
+    GetProperty {
+        name: String,
+        format: MpvFormat,
+        reply: tokio::sync::oneshot::Sender<Result<MpvValue, String>>,
+    },
+    #[serde(skip)]
+    SetProperty {
+        name: String,
+        value: MpvValue,
+        reply: tokio::sync::oneshot::Sender<Result<(), String>>,
+    },
+    #[serde(skip)]
+    ObserveProperty {
+        name: String,
+        format: MpvFormat,
+        reply: tokio::sync::oneshot::Sender<Result<(), String>>,
+    },
+    #[serde(skip)]
+    UnobserveProperty {
+        name: String,
+        reply: tokio::sync::oneshot::Sender<Result<(), String>>,
+    },
     /// Load an external subtitle file/URL into mpv without replacing the current video.
     /// The subtitle will appear in the track-list so the existing UI can select it via sid.
     /// Optional `title` and `lang` are forwarded to mpv's `sub-add` so the track shows a
@@ -937,6 +1762,7 @@ pub enum PlaybackEvent {
         url: String,
         title: Option<String>,
         lang: Option<String>,
+        http: Option<HttpOptions>,
     },
 }
 
@@ -951,18 +1777,85 @@ pub struct Track {
     lang: Option<String>,
 }
 
+/// Whether an `mpv-version` string such as `mpv 0.38.0` or `mpv v0.39.0-45-g1234`
+/// is at least `minimum`. Versions that cannot be parsed, e.g. from unusual
+/// builds, count as current.
+fn mpv_version_at_least(version: &str, minimum: (u32, u32)) -> bool {
+    let version = version.trim_start_matches("mpv").trim_start();
+    let mut parts = version
+        .trim_start_matches('v')
+        .split(['.', '-'])
+        .map(|part| part.parse::<u32>().ok());
+
+    match (parts.next().flatten(), parts.next().flatten()) {
+        (Some(major), Some(minor)) => (major, minor) >= minimum,
+        _ => true,
+    }
+}
+
+/// Earliest time the render thread has scheduled work of its own, if any.
+fn next_deadline(stats: &PlaybackStats, sleep_timer: &SleepTimer) -> Option<Instant> {
+    [stats.next_flush(), sleep_timer.next_tick()]
+        .into_iter()
+        .flatten()
+        .min()
+}
+
+/// Block until an event arrives, or until `deadline` with `Ok(None)`. Without a
+/// deadline this only returns for an event, so an idle player never spins.
+fn wait_for_event(
+    render_rx: &Receiver<PlaybackEvent>,
+    deadline: Option<Instant>,
+) -> Result<Option<PlaybackEvent>, std::sync::mpsc::RecvError> {
+    match deadline {
+        Some(deadline) => {
+            match render_rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(event) => Ok(Some(event)),
+                Err(std::sync::mpsc::RecvTimeoutError::Timeout) => Ok(None),
+                Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+                    Err(std::sync::mpsc::RecvError)
+                }
+            }
+        }
+        None => render_rx.recv().map(Some),
+    }
+}
+
+/// Wait for events and scheduled work and pass each wakeup to `handle`, with
+/// `None` once a deadline from `deadline` passes, until the channel closes.
+fn run_event_loop<S>(
+    render_rx: &Receiver<PlaybackEvent>,
+    state: &mut S,
+    deadline: impl Fn(&S) -> Option<Instant>,
+    mut handle: impl FnMut(&mut S, Option<PlaybackEvent>),
+) {
+    loop {
+        match wait_for_event(render_rx, deadline(state)) {
+            Ok(received) => handle(state, received),
+            Err(_) => {
+                log::warn!("Render channel disconnected, stopping render thread");
+                break;
+            }
+        }
+    }
+}
+
 /// Main render thread function - handles MPV events and rendering
 pub async fn run_render_thread(
     window: Window,
     render_tx: Sender<PlaybackEvent>,
     render_rx: Receiver<PlaybackEvent>,
     get_pip_window: Box<dyn Fn() -> Option<Window> + Send + Sync>,
+    history: Arc<Mutex<PlaybackHistory>>,
+    report_tx: tokio::sync::mpsc::UnboundedSender<ReportEvent>,
 ) {
     // Create render manager with main window only; PiP context is created lazily
 
     let mut render_manager = RenderManager::new(&window).unwrap();
     log::info!("OpenGL context and MPV player created successfully on render thread");
 
+    let mut event_handler = EventHandler::new(history, report_tx);
+
     // Set up MPV update callback to trigger rendering on this thread
     render_manager.set_update_callback({
         let render_tx = render_tx.clone();
@@ -971,33 +1864,65 @@ pub async fn run_render_thread(
         }
     });
 
-    // Combined event loop - handle both MPV events and render signals
-    loop {
-        // Check for MPV events
-        let mpv_sleep_action = if let Some(event) = render_manager.wait_event(0.0) {
-            if let Ok(event) = event {
-                Some(EventHandler::handle_mpv_events(
-                    event,
-                    &window,
-                    render_tx.clone(),
-                ))
-            } else {
-                None
-            }
-        } else {
-            None
-        };
+    // Wake this thread whenever mpv has queued events, and drain anything that
+    // was queued before the callback was installed.
+    render_manager
+        .mpv_player
+        .set_wakeup_callback(render_tx.clone());
+    let _ = render_tx.send(PlaybackEvent::MpvEvents);
+
+    // Combined event loop - sleeps until mpv, the frontend or a pending
+    // stats flush or sleep timer tick has work for it
+    run_event_loop(
+        &render_rx,
+        &mut event_handler,
+        |event_handler| next_deadline(&event_handler.stats, &event_handler.sleep_timer),
+        |event_handler, received| {
+            if let Some(event) = received {
+                match event {
+                    PlaybackEvent::MpvEvents => {
+                        render_manager.mpv_player.clear_wakeup();
+
+                        loop {
+                            let sleep_action = match render_manager.wait_event(0.0) {
+                                Some(Ok(event)) => event_handler.handle_mpv_events(
+                                    event,
+                                    &window,
+                                    render_tx.clone(),
+                                ),
+                                Some(Err(e)) => {
+                                    log::warn!("Failed to read mpv event: {}", e);
+
+                                    // A failed `EndFile` surfaces as an error; try the next source
+                                    if let Some((url, options, http)) = event_handler
+                                        .fallback
+                                        .next_source(&e.to_string(), window.app_handle())
+                                    {
+                                        event_handler
+                                            .progress
+                                            .begin(options.item_id.clone(), options.report.clone());
+                                        render_manager.mpv_player.handle_playback_event(
+                                            PlaybackEvent::Load { url, options, http },
+                                        );
+                                    }
+                                    continue;
+                                }
+                                None => break,
+                            };
 
-        match mpv_sleep_action {
-            Some(SleepAction::Sync) => render_manager.mpv_player.sync_sleep_prevention(),
-            Some(SleepAction::Release) => render_manager.mpv_player.release_sleep_prevention(),
-            _ => {}
-        }
+                            match sleep_action {
+                                SleepAction::Sync => {
+                                    render_manager.mpv_player.sync_sleep_prevention()
+                                }
+                                SleepAction::Release => {
+                                    render_manager.mpv_player.release_sleep_prevention()
+                                }
+                                SleepAction::None => {}
+                            }
+                        }
 
-        // Check for render signals (non-blocking)
-        match render_rx.recv_timeout(Duration::from_millis(8)) {
-            Ok(event) => {
-                match event {
+                        event_handler.history.lock().unwrap().maybe_save();
+                    }
                     PlaybackEvent::Redraw => {
                         // Render to the active window
                         if render_manager.active_window == "pip" {
@@ -1023,7 +1948,104 @@ pub async fn run_render_thread(
                         }
                     }
                     PlaybackEvent::Clear => {
+                        event_handler.history.lock().unwrap().flush();
+                        event_handler.progress.stop();
+                        event_handler.fallback.clear();
                         render_manager.clear(&window);
+                        NowPlayingChange::default().emit(window.app_handle()).ok();
+                    }
+                    PlaybackEvent::Load {
+                        mut url,
+                        mut options,
+                        mut http,
+                    } => {
+                        // Prefer a downloaded copy over streaming
+                        let local_copy = options.item_id.as_deref().and_then(|item_id| {
+                            window
+                                .app_handle()
+                                .try_state::<DownloadManager>()
+                                .and_then(|downloads| downloads.local_copy(item_id))
+                        });
+                        if let Some(local_copy) = local_copy {
+                            log::info!("Playing downloaded copy {}", local_copy.media.display());
+                            url = local_copy.media.to_string_lossy().into_owned();
+                            http = None;
+                            options.sub_files.extend(
+                                local_copy
+                                    .subtitles
+                                    .iter()
+                                    .map(|path| path.to_string_lossy().into_owned()),
+                            );
+                        }
+
+                        let mut history = event_handler.history.lock().unwrap();
+                        history.begin(options.item_id.clone(), &url);
+
+                        let resume_playback = GeneralSettings::get(window.app_handle())
+                            .ok()
+                            .flatten()
+                            .unwrap_or_default()
+                            .resume_playback;
+
+                        if resume_playback && options.start.is_none() {
+                            if let Some(entry) = history.lookup(options.item_id.as_deref(), &url) {
+                                log::info!("Resuming from local history at {}s", entry.position);
+                                options.start = Some(entry.position);
+                                options.aid = options.aid.or(entry.aid);
+                                options.sid = options.sid.or(entry.sid);
+                            }
+                        }
+                        drop(history);
+
+                        event_handler
+                            .progress
+                            .begin(options.item_id.clone(), options.report.clone());
+                        event_handler.fallback.begin(&options, http.as_ref());
+
+                        NowPlayingChange {
+                            item_id: options.item_id.clone(),
+                            title: options.media_title.clone(),
+                            episode: options.episode.clone(),
+                            artwork_url: options.artwork_url.clone(),
+                            loaded: true,
+                        }
+                        .emit(window.app_handle())
+                        .ok();
+
+                        let library_default = match options.library_type.as_deref() {
+                            Some(library_type) => {
+                                shaders::library_default(window.app_handle(), library_type)
+                            }
+                            None => Ok(None),
+                        };
+                        match library_default {
+                            Ok(Some((preset, shaders))) => event_handler.apply_shaders(
+                                &mut render_manager.mpv_player,
+                                Some(preset),
+                                shaders,
+                                true,
+                            ),
+                            // Only another library's default gives way; a preset the
+                            // user applied stays
+                            Ok(None) if event_handler.shader_from_library => event_handler
+                                .apply_shaders(
+                                    &mut render_manager.mpv_player,
+                                    None,
+                                    Vec::new(),
+                                    false,
+                                ),
+                            Ok(None) => {}
+                            Err(e) => {
+                                log::error!("Failed to resolve default shader preset: {}", e);
+                                ErrorEventChange { message: e }
+                                    .emit(window.app_handle())
+                                    .ok();
+                            }
+                        }
+
+                        render_manager
+                            .mpv_player
+                            .handle_playback_event(PlaybackEvent::Load { url, options, http });
                     }
                     PlaybackEvent::SwitchTarget(target) => {
                         if target == "pip" && !render_manager.gl_contexts.contains_key("pip") {
@@ -1046,16 +2068,170 @@ pub async fn run_render_thread(
                     PlaybackEvent::DestroyPipContext => {
                         render_manager.remove_pip_context();
                     }
+                    PlaybackEvent::TimeUpdateInterval(interval_ms) => {
+                        event_handler.time_reporter.set_interval(interval_ms);
+                    }
+                    PlaybackEvent::Shaders { preset, shaders } => {
+                        event_handler.apply_shaders(
+                            &mut render_manager.mpv_player,
+                            preset,
+                            shaders,
+                            false,
+                        );
+                    }
+                    PlaybackEvent::GetProperty {
+                        name,
+                        format,
+                        reply,
+                    } => {
+                        let _ = reply.send(properties::get(
+                            &render_manager.mpv_player.mpv,
+                            &name,
+                            format,
+                        ));
+                    }
+                    PlaybackEvent::SetProperty { name, value, reply } => {
+                        let _ = reply.send(properties::set(
+                            &render_manager.mpv_player.mpv,
+                            &name,
+                            value,
+                        ));
+                    }
+                    PlaybackEvent::ObserveProperty {
+                        name,
+                        format,
+                        reply,
+                    } => {
+                        let _ = reply.send(event_handler.properties.observe(
+                            &render_manager.mpv_player.mpv,
+                            name,
+                            format,
+                        ));
+                    }
+                    PlaybackEvent::UnobserveProperty { name, reply } => {
+                        let _ = reply.send(
+                            event_handler
+                                .properties
+                                .unobserve(&render_manager.mpv_player.mpv, &name),
+                        );
+                    }
+                    PlaybackEvent::AudioStreamIndex(index) => {
+                        match event_handler.progress.audio_track(index) {
+                            Some(aid) => render_manager
+                                .mpv_player
+                                .handle_playback_event(PlaybackEvent::ChangeAudio(aid.to_string())),
+                            None => log::warn!("No audio track for stream index {}", index),
+                        }
+                    }
+                    PlaybackEvent::SubtitleStreamIndex(index) => {
+                        let sid = match u32::try_from(index) {
+                            Ok(index) => event_handler.progress.subtitle_track(index),
+                            Err(_) => None,
+                        };
+                        match sid {
+                            Some(sid) => render_manager.mpv_player.handle_playback_event(
+                                PlaybackEvent::ChangeSubtitle(sid.to_string()),
+                            ),
+                            None if index < 0 => render_manager.mpv_player.handle_playback_event(
+                                PlaybackEvent::ChangeSubtitle("no".to_string()),
+                            ),
+                            None => log::warn!("No subtitle track for stream index {}", index),
+                        }
+                    }
+                    PlaybackEvent::Volume(volume) if event_handler.sleep_timer.is_fading() => {
+                        event_handler
+                            .sleep_timer
+                            .set_user_volume(volume, &render_manager.mpv_player);
+                    }
+                    PlaybackEvent::SleepTimer(mode) => {
+                        event_handler
+                            .sleep_timer
+                            .set(mode, &mut render_manager.mpv_player);
+                    }
+                    PlaybackEvent::StatsSubscription {
+                        webview,
+                        subscribed,
+                    } => {
+                        let mpv = &render_manager.mpv_player.mpv;
+                        if subscribed {
+                            event_handler.stats.subscribe(mpv, &webview);
+                        } else {
+                            event_handler.stats.unsubscribe(mpv, &webview);
+                        }
+                    }
+                    PlaybackEvent::StatsReset { webview } => {
+                        event_handler
+                            .stats
+                            .reset(&render_manager.mpv_player.mpv, &webview);
+                    }
                     _ => render_manager.mpv_player.handle_playback_event(event),
                 }
             }
-            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
-            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
-                log::warn!("Render channel disconnected, stopping render thread");
-                break;
-            }
+
+            event_handler.stats.flush(window.app_handle());
+            event_handler
+                .sleep_timer
+                .tick(&mut render_manager.mpv_player);
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct IdleLoop {
+        stats: PlaybackStats,
+        sleep_timer: SleepTimer,
+        iterations: usize,
+        mpv_events: usize,
+    }
+
+    #[test]
+    fn compares_mpv_versions() {
+        assert!(mpv_version_at_least("mpv 0.38.0", (0, 38)));
+        assert!(mpv_version_at_least("mpv v0.39.0-45-g1234abcd", (0, 38)));
+        assert!(mpv_version_at_least("mpv 1.0.0", (0, 38)));
+        assert!(!mpv_version_at_least("mpv 0.37.0", (0, 38)));
+        assert!(!mpv_version_at_least("mpv v0.35.1-UNKNOWN", (0, 38)));
+        assert!(mpv_version_at_least("mpv git-2024", (0, 38)));
+    }
+
+    /// An idle render loop must sleep, and a burst of mpv wakeups must reach
+    /// it as a single `MpvEvents` until the queue is drained.
+    #[test]
+    fn idle_render_loop_does_not_spin() {
+        let (render_tx, render_rx) = std::sync::mpsc::channel();
+        let wakeup = Box::new(MpvWakeup {
+            render_tx,
+            pending: AtomicBool::new(false),
+        });
+
+        let render_loop = std::thread::spawn(move || {
+            let mut state = IdleLoop::default();
+            run_event_loop(
+                &render_rx,
+                &mut state,
+                |state| next_deadline(&state.stats, &state.sleep_timer),
+                |state, received| {
+                    state.iterations += 1;
+                    if matches!(received, Some(PlaybackEvent::MpvEvents)) {
+                        state.mpv_events += 1;
+                    }
+                },
+            );
+            (state.iterations, state.mpv_events)
+        });
+
+        for _ in 0..100 {
+            unsafe { on_mpv_wakeup(&*wakeup as *const MpvWakeup as *mut std::ffi::c_void) };
         }
+        std::thread::sleep(Duration::from_millis(300));
+        drop(wakeup);
 
-        //std::thread::sleep(std::time::Duration::from_millis(fps.into())); // ~40 FPS
+        let (iterations, mpv_events) = render_loop.join().unwrap();
+        assert_eq!(mpv_events, 1);
+        assert_eq!(iterations, 1);
     }
 }
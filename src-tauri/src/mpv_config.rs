@@ -0,0 +1,409 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use libmpv2::Mpv;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tauri_specta::Event;
+
+use crate::store::GeneralSettings;
+
+const SCRIPTS_DIR: &str = "scripts";
+/// Disabled scripts are moved here, out of reach of mpv's script autoloading.
+const DISABLED_SCRIPTS_DIR: &str = "scripts-disabled";
+
+/// Most problems kept for `status`; a crashing script can log without end.
+const MAX_PROBLEMS: usize = 50;
+
+/// Error from loading or managing the user mpv configuration.
+#[derive(Debug, specta::Type, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", tag = "kind", content = "message")]
+pub enum MpvConfigError {
+    /// The user configuration mode is turned off.
+    Disabled,
+    /// No script with that name exists.
+    ScriptNotFound(String),
+    /// A script with that name is already in the target folder.
+    ScriptExists(String),
+    /// The name is not a plain file name inside the scripts folder.
+    InvalidScriptName(String),
+    /// Reading or writing the config folder failed.
+    Io(String),
+    /// mpv refused the configuration; playback runs with the built-in defaults.
+    Mpv(String),
+    /// mpv could not apply a config file line, as `<file>:<line>: <reason>`.
+    ConfigLine(String),
+    /// A script failed to load or run, as `<script>: <error>`.
+    ScriptFailed(String),
+}
+
+impl std::fmt::Display for MpvConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MpvConfigError::Disabled => write!(f, "user mpv configuration is disabled"),
+            MpvConfigError::ScriptNotFound(name) => write!(f, "script '{}' not found", name),
+            MpvConfigError::ScriptExists(name) => {
+                write!(f, "a script named '{}' already exists", name)
+            }
+            MpvConfigError::InvalidScriptName(name) => {
+                write!(f, "'{}' is not a valid script name", name)
+            }
+            MpvConfigError::Io(e) => write!(f, "config folder error: {}", e),
+            MpvConfigError::Mpv(e) => write!(f, "mpv rejected the user configuration: {}", e),
+            MpvConfigError::ConfigLine(e) => write!(f, "invalid config line {}", e),
+            MpvConfigError::ScriptFailed(e) => write!(f, "script error in {}", e),
+        }
+    }
+}
+
+impl From<std::io::Error> for MpvConfigError {
+    fn from(e: std::io::Error) -> Self {
+        MpvConfigError::Io(e.to_string())
+    }
+}
+
+#[derive(Debug, specta::Type, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum MpvScriptKind {
+    Lua,
+    JavaScript,
+    /// Folder with a `main.lua` or `main.js`.
+    Directory,
+}
+
+#[derive(Debug, specta::Type, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MpvScript {
+    pub name: String,
+    pub kind: MpvScriptKind,
+    pub enabled: bool,
+}
+
+#[derive(Debug, specta::Type, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MpvConfigStatus {
+    /// Whether the user configuration is turned on in the settings.
+    pub enabled: bool,
+    /// Whether the running player was started with it. Toggling the setting
+    /// takes effect on the next launch.
+    pub loaded: bool,
+    pub config_dir: String,
+    /// Why the configuration could not be loaded at startup, if it failed.
+    pub error: Option<MpvConfigError>,
+    /// Broken config lines and failing scripts reported by mpv since launch.
+    pub problems: Vec<MpvConfigError>,
+}
+
+/// A broken config line or failing script, as mpv reports it.
+#[derive(Debug, specta::Type, tauri_specta::Event, Serialize, Deserialize, Clone)]
+pub struct MpvConfigErrorChange {
+    pub error: MpvConfigError,
+}
+
+/// Outcome of creating the player, recorded for `status`.
+static STARTUP: Mutex<Option<Result<(), MpvConfigError>>> = Mutex::new(None);
+
+/// mpv client names of the loaded user scripts, which prefix their log messages.
+static SCRIPTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+static PROBLEMS: Mutex<Vec<MpvConfigError>> = Mutex::new(Vec::new());
+
+/// Folder handed to mpv as `config-dir`, holding `mpv.conf`, `input.conf`
+/// and the `scripts` folder.
+pub fn config_dir(app: &AppHandle) -> Result<PathBuf, MpvConfigError> {
+    app.path()
+        .app_config_dir()
+        .map(|dir| dir.join("mpv"))
+        .map_err(|e| MpvConfigError::Io(e.to_string()))
+}
+
+fn is_enabled(app: &AppHandle) -> bool {
+    GeneralSettings::get(app)
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+        .mpv_user_config
+}
+
+/// Create the mpv instance, pointed at the user configuration folder when it
+/// is turned on. If mpv rejects it the player falls back to the built-in
+/// defaults and the error is kept for `status`, so a broken configuration
+/// never leaves the app without a player.
+///
+/// `mpv.conf` and the scripts are left to `load_user_config`.
+pub fn create_mpv(app: &AppHandle) -> Result<Mpv, libmpv2::Error> {
+    if !is_enabled(app) {
+        return Mpv::new();
+    }
+
+    let result = config_dir(app).and_then(|dir| {
+        fs::create_dir_all(dir.join(SCRIPTS_DIR))?;
+        let input_conf = dir.join("input.conf");
+        let dir = dir.to_string_lossy().into_owned();
+
+        Mpv::with_initializer(|init| {
+            init.set_property("config-dir", dir.as_str())?;
+            init.set_property("config", "no")?;
+            init.set_property("load-scripts", "no")?;
+            if input_conf.is_file() {
+                init.set_property("input-conf", input_conf.to_string_lossy().as_ref())?;
+            }
+            Ok(())
+        })
+        .map_err(|e| MpvConfigError::Mpv(e.to_string()))
+    });
+
+    match result {
+        Ok(mpv) => {
+            *STARTUP.lock().unwrap() = Some(Ok(()));
+            Ok(mpv)
+        }
+        Err(e) => {
+            log::error!("Failed to load user mpv configuration: {}", e);
+            *STARTUP.lock().unwrap() = Some(Err(e));
+            Mpv::new()
+        }
+    }
+}
+
+/// Load `mpv.conf` and the enabled scripts into a player from `create_mpv`.
+/// mpv only logs broken lines and script errors, so this has to run after
+/// `request_log_messages` for `report_log_message` to see them.
+pub fn load_user_config(app: &AppHandle, mpv: &Mpv) {
+    if !matches!(*STARTUP.lock().unwrap(), Some(Ok(()))) {
+        return;
+    }
+
+    if let Err(e) = load_config_files(app, mpv) {
+        log::error!("Failed to load user mpv configuration: {}", e);
+        *STARTUP.lock().unwrap() = Some(Err(e));
+        return;
+    }
+    log::info!("Loaded user mpv configuration");
+}
+
+fn load_config_files(app: &AppHandle, mpv: &Mpv) -> Result<(), MpvConfigError> {
+    let dir = config_dir(app)?;
+
+    let config = dir.join("mpv.conf");
+    if config.is_file() {
+        let config = config.to_string_lossy().into_owned();
+        mpv.command("load-config-file", &[config.as_str()])
+            .map_err(|e| MpvConfigError::Mpv(e.to_string()))?;
+    }
+
+    for script in list_scripts(app)?.into_iter().filter(|s| s.enabled) {
+        let path = dir.join(SCRIPTS_DIR).join(&script.name);
+        let path = path.to_string_lossy().into_owned();
+
+        match mpv.command("load-script", &[path.as_str()]) {
+            Ok(()) => SCRIPTS.lock().unwrap().push(script_client_name(&script)),
+            Err(e) => add_problem(MpvConfigError::ScriptFailed(format!(
+                "{}: {}",
+                script.name, e
+            ))),
+        }
+    }
+
+    Ok(())
+}
+
+/// Name mpv gives a script's client, and so its log prefix: the file name
+/// without extension, or the folder name.
+fn script_client_name(script: &MpvScript) -> String {
+    match script.kind {
+        MpvScriptKind::Directory => script.name.clone(),
+        MpvScriptKind::Lua | MpvScriptKind::JavaScript => Path::new(&script.name)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| script.name.clone()),
+    }
+}
+
+/// Keep `error` for `status`, once.
+fn add_problem(error: MpvConfigError) -> bool {
+    let mut problems = PROBLEMS.lock().unwrap();
+    if problems.len() >= MAX_PROBLEMS || problems.contains(&error) {
+        return false;
+    }
+
+    problems.push(error);
+    true
+}
+
+/// Check an mpv log message for a broken config line or a failing user
+/// script, and report it to the frontend.
+pub fn report_log_message(app: &AppHandle, prefix: &str, level: &str, text: &str) {
+    if !matches!(level, "fatal" | "error") {
+        return;
+    }
+
+    let is_script = SCRIPTS.lock().unwrap().iter().any(|name| name == prefix);
+    let Some(error) = classify_log_message(prefix, text, is_script) else {
+        return;
+    };
+
+    log::warn!("User mpv configuration problem: {}", error);
+    if add_problem(error.clone()) {
+        MpvConfigErrorChange { error }.emit(app).ok();
+    }
+}
+
+fn classify_log_message(prefix: &str, text: &str, is_script: bool) -> Option<MpvConfigError> {
+    let text = text.trim();
+    if is_script {
+        return Some(MpvConfigError::ScriptFailed(format!(
+            "{}: {}",
+            prefix, text
+        )));
+    }
+
+    // Config file errors start with their location, e.g. `/path/mpv.conf:3: ...`
+    let (location, _) = text.split_once(": ")?;
+    let (file, line) = location.rsplit_once(':')?;
+    (file.ends_with(".conf") && line.parse::<u32>().is_ok())
+        .then(|| MpvConfigError::ConfigLine(text.to_string()))
+}
+
+pub fn status(app: &AppHandle) -> Result<MpvConfigStatus, MpvConfigError> {
+    let startup = STARTUP.lock().unwrap().clone();
+
+    Ok(MpvConfigStatus {
+        enabled: is_enabled(app),
+        loaded: matches!(startup, Some(Ok(()))),
+        config_dir: config_dir(app)?.to_string_lossy().into_owned(),
+        error: startup.and_then(Result::err),
+        problems: PROBLEMS.lock().unwrap().clone(),
+    })
+}
+
+/// List enabled and disabled scripts, sorted by name.
+pub fn list_scripts(app: &AppHandle) -> Result<Vec<MpvScript>, MpvConfigError> {
+    let dir = config_dir(app)?;
+    let mut scripts = Vec::new();
+
+    for (folder, enabled) in [(SCRIPTS_DIR, true), (DISABLED_SCRIPTS_DIR, false)] {
+        let folder = dir.join(folder);
+        if !folder.is_dir() {
+            continue;
+        }
+
+        for entry in fs::read_dir(&folder)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if let Some(kind) = script_kind(&entry.path()) {
+                scripts.push(MpvScript {
+                    name,
+                    kind,
+                    enabled,
+                });
+            }
+        }
+    }
+
+    scripts.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(scripts)
+}
+
+/// Move a script into the scripts folder. Takes effect on the next launch.
+pub fn enable_script(app: &AppHandle, name: &str) -> Result<(), MpvConfigError> {
+    move_script(app, name, DISABLED_SCRIPTS_DIR, SCRIPTS_DIR)
+}
+
+/// Move a script out of the scripts folder. mpv cannot unload a running
+/// script, so this takes effect on the next launch.
+pub fn disable_script(app: &AppHandle, name: &str) -> Result<(), MpvConfigError> {
+    move_script(app, name, SCRIPTS_DIR, DISABLED_SCRIPTS_DIR)
+}
+
+fn move_script(app: &AppHandle, name: &str, from: &str, to: &str) -> Result<(), MpvConfigError> {
+    if !is_enabled(app) {
+        return Err(MpvConfigError::Disabled);
+    }
+
+    if name.is_empty()
+        || name.starts_with('.')
+        || name.contains(['/', '\\'])
+        || Path::new(name).components().count() != 1
+    {
+        return Err(MpvConfigError::InvalidScriptName(name.to_string()));
+    }
+
+    let dir = config_dir(app)?;
+    let source = dir.join(from).join(name);
+    let target = dir.join(to).join(name);
+
+    if target.exists() {
+        return Err(MpvConfigError::ScriptExists(name.to_string()));
+    }
+    if script_kind(&source).is_none() {
+        return Err(MpvConfigError::ScriptNotFound(name.to_string()));
+    }
+
+    fs::create_dir_all(dir.join(to))?;
+    fs::rename(&source, &target)?;
+    log::info!("Moved mpv script '{}' to {}", name, to);
+    Ok(())
+}
+
+fn script_kind(path: &Path) -> Option<MpvScriptKind> {
+    if path.is_dir() {
+        let has_main = ["main.lua", "main.js"]
+            .iter()
+            .any(|main| path.join(main).is_file());
+        return has_main.then_some(MpvScriptKind::Directory);
+    }
+
+    match path.extension()?.to_str()? {
+        "lua" => Some(MpvScriptKind::Lua),
+        "js" => Some(MpvScriptKind::JavaScript),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_config_lines_and_script_errors() {
+        assert_eq!(
+            classify_log_message(
+                "cplayer",
+                "/home/user/.config/mpv/mpv.conf:3: setting option foo='1' failed.\n",
+                false
+            ),
+            Some(MpvConfigError::ConfigLine(
+                "/home/user/.config/mpv/mpv.conf:3: setting option foo='1' failed.".to_string()
+            ))
+        );
+        assert_eq!(
+            classify_log_message(
+                "cplayer",
+                "C:\\Users\\user\\mpv\\input.conf:12: unknown key 'CTRL+'",
+                false
+            ),
+            Some(MpvConfigError::ConfigLine(
+                "C:\\Users\\user\\mpv\\input.conf:12: unknown key 'CTRL+'".to_string()
+            ))
+        );
+        assert_eq!(
+            classify_log_message("skip", "Lua error: attempt to call a nil value\n", true),
+            Some(MpvConfigError::ScriptFailed(
+                "skip: Lua error: attempt to call a nil value".to_string()
+            ))
+        );
+
+        assert_eq!(
+            classify_log_message("vo/gpu", "Failed to compile shader", false),
+            None
+        );
+        assert_eq!(
+            classify_log_message("ffmpeg", "http: HTTP error 404 Not Found", false),
+            None
+        );
+    }
+}
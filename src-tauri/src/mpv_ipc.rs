@@ -0,0 +1,84 @@
+//! Opt-in mpv JSON IPC server (`input-ipc-server`), for scripts that drive
+//! the player directly. Changes made through it are picked up by the regular
+//! property observers, so the frontend stays in sync.
+
+use std::{fs, path::PathBuf};
+
+use libmpv2::Mpv;
+use tauri::{AppHandle, Manager};
+
+use crate::store::GeneralSettings;
+
+const SOCKET_NAME: &str = "mpv.sock";
+
+pub fn is_enabled(app: &AppHandle) -> bool {
+    GeneralSettings::get(app)
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+        .mpv_ipc_server
+}
+
+/// Per-user folder holding the socket. Prefers the runtime dir, which is
+/// private to the user on Linux.
+fn socket_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let base = app
+        .path()
+        .runtime_dir()
+        .or_else(|_| app.path().app_cache_dir())
+        .map_err(|e| format!("No folder for the mpv IPC socket: {}", e))?;
+
+    Ok(base.join(&app.config().identifier))
+}
+
+pub fn socket_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(socket_dir(app)?.join(SOCKET_NAME))
+}
+
+/// Create the socket folder readable only by the current user and clear a
+/// socket left behind by a crash. Returns the path to hand to mpv.
+#[cfg(unix)]
+pub fn prepare_socket(app: &AppHandle) -> Result<String, String> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    let dir = socket_dir(app)?;
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    // The folder may predate this feature with looser permissions
+    fs::set_permissions(&dir, fs::Permissions::from_mode(0o700))
+        .map_err(|e| format!("Failed to restrict {}: {}", dir.display(), e))?;
+
+    let path = dir.join(SOCKET_NAME);
+    if path.exists() {
+        fs::remove_file(&path)
+            .map_err(|e| format!("Failed to remove stale {}: {}", path.display(), e))?;
+    }
+
+    Ok(path.to_string_lossy().into_owned())
+}
+
+#[cfg(not(unix))]
+pub fn prepare_socket(_app: &AppHandle) -> Result<String, String> {
+    Err("The mpv IPC socket is only supported on Unix".to_string())
+}
+
+/// Start the IPC server on a new player when the setting is on.
+pub fn start(app: &AppHandle, mpv: &Mpv) {
+    if !is_enabled(app) {
+        return;
+    }
+
+    let result = prepare_socket(app).and_then(|path| {
+        mpv.set_property("input-ipc-server", path.as_str())
+            .map_err(|e| format!("mpv rejected the IPC socket: {}", e))?;
+        Ok(path)
+    });
+
+    match result {
+        Ok(path) => log::info!("mpv IPC server listening on {}", path),
+        Err(e) => log::error!("Failed to start the mpv IPC server: {}", e),
+    }
+}
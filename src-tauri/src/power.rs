@@ -2,18 +2,247 @@
 use objc2::{rc::Retained, runtime::ProtocolObject};
 #[cfg(target_os = "macos")]
 use objc2_foundation::{NSActivityOptions, NSObjectProtocol, NSProcessInfo, NSString};
+use serde::{Deserialize, Serialize};
 
-/// Keeps macOS awake while video playback is active.
+use crate::store::SleepPolicy;
+
+/// How sleep is currently being blocked.
+#[derive(Debug, specta::Type, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum SleepInhibitor {
+    /// `NSProcessInfo` activity on macOS.
+    MacosActivity,
+    /// `org.freedesktop.ScreenSaver.Inhibit` on the session bus.
+    ScreenSaver,
+    /// systemd-logind inhibitor lock on the system bus.
+    Logind,
+}
+
+/// Emitted when sleep blocking starts, stops or switches mechanism.
+#[derive(Debug, specta::Type, tauri_specta::Event, Serialize, Deserialize, Clone)]
+pub struct SleepInhibitionChange {
+    /// `null` while nothing is blocked.
+    pub mechanism: Option<SleepInhibitor>,
+}
+
+/// Called with the new mechanism whenever sleep blocking starts, stops or
+/// switches mechanism.
+pub type InhibitorCallback = Box<dyn Fn(Option<SleepInhibitor>) + Send>;
+
+#[cfg(target_os = "linux")]
+enum LinuxInhibitor {
+    ScreenSaver {
+        cookie: u32,
+    },
+    /// logind releases the lock when the file descriptor is closed.
+    Logind {
+        _fd: zbus::zvariant::OwnedFd,
+    },
+}
+
+#[cfg(target_os = "linux")]
+impl LinuxInhibitor {
+    fn mechanism(&self) -> SleepInhibitor {
+        match self {
+            LinuxInhibitor::ScreenSaver { .. } => SleepInhibitor::ScreenSaver,
+            LinuxInhibitor::Logind { .. } => SleepInhibitor::Logind,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+enum InhibitRequest {
+    Acquire { policy: SleepPolicy, reason: String },
+    Release,
+}
+
+/// Owns the D-Bus connections and the active inhibitor on a thread of its
+/// own, so the blocking D-Bus calls never stall the render thread.
+#[cfg(target_os = "linux")]
+struct InhibitWorker {
+    session: Option<zbus::blocking::Connection>,
+    system: Option<zbus::blocking::Connection>,
+    inhibitor: Option<LinuxInhibitor>,
+    on_change: InhibitorCallback,
+}
+
+#[cfg(target_os = "linux")]
+impl InhibitWorker {
+    fn spawn(on_change: InhibitorCallback) -> std::sync::mpsc::Sender<InhibitRequest> {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let worker = InhibitWorker {
+            session: None,
+            system: None,
+            inhibitor: None,
+            on_change,
+        };
+
+        if let Err(e) = std::thread::Builder::new()
+            .name("sleep-inhibitor".to_string())
+            .spawn(move || worker.run(rx))
+        {
+            log::error!("Failed to start the sleep inhibitor thread: {}", e);
+        }
+        tx
+    }
+
+    fn run(mut self, requests: std::sync::mpsc::Receiver<InhibitRequest>) {
+        while let Ok(request) = requests.recv() {
+            let previous = self.mechanism();
+            match request {
+                InhibitRequest::Acquire { policy, reason } => self.acquire(policy, &reason),
+                InhibitRequest::Release => self.release(),
+            }
+
+            let mechanism = self.mechanism();
+            if mechanism != previous {
+                (self.on_change)(mechanism);
+            }
+        }
+        self.release();
+    }
+
+    fn mechanism(&self) -> Option<SleepInhibitor> {
+        self.inhibitor.as_ref().map(LinuxInhibitor::mechanism)
+    }
+
+    /// The session bus connection, opened on first use and kept afterwards.
+    fn session(&mut self) -> zbus::Result<&zbus::blocking::Connection> {
+        if self.session.is_none() {
+            self.session = Some(zbus::blocking::Connection::session()?);
+        }
+        Ok(self.session.as_ref().unwrap())
+    }
+
+    /// The system bus connection, opened on first use and kept afterwards.
+    fn system(&mut self) -> zbus::Result<&zbus::blocking::Connection> {
+        if self.system.is_none() {
+            self.system = Some(zbus::blocking::Connection::system()?);
+        }
+        Ok(self.system.as_ref().unwrap())
+    }
+
+    fn screen_saver(&mut self, reason: &str) -> zbus::Result<LinuxInhibitor> {
+        let reply = self.session()?.call_method(
+            Some("org.freedesktop.ScreenSaver"),
+            "/org/freedesktop/ScreenSaver",
+            Some("org.freedesktop.ScreenSaver"),
+            "Inhibit",
+            &("square", reason),
+        )?;
+        let cookie: u32 = reply.body().deserialize()?;
+
+        Ok(LinuxInhibitor::ScreenSaver { cookie })
+    }
+
+    /// `what` is a colon separated list of logind inhibitor types, e.g. `idle:sleep`.
+    fn logind(&mut self, what: &str, reason: &str) -> zbus::Result<LinuxInhibitor> {
+        let reply = self.system()?.call_method(
+            Some("org.freedesktop.login1"),
+            "/org/freedesktop/login1",
+            Some("org.freedesktop.login1.Manager"),
+            "Inhibit",
+            &(what, "square", reason, "block"),
+        )?;
+        let fd: zbus::zvariant::OwnedFd = reply.body().deserialize()?;
+
+        Ok(LinuxInhibitor::Logind { _fd: fd })
+    }
+
+    /// Blocking the display falls back to a logind lock, which also keeps the
+    /// screen on. Blocking only system sleep has no fallback: the ScreenSaver
+    /// interface would keep the display on as well.
+    fn acquire(&mut self, policy: SleepPolicy, reason: &str) {
+        if self.inhibitor.is_some() {
+            return;
+        }
+
+        let result = match policy {
+            SleepPolicy::Display => self.screen_saver(reason).or_else(|e| {
+                log::warn!("ScreenSaver sleep inhibitor unavailable: {}", e);
+                self.logind("idle:sleep", reason)
+            }),
+            SleepPolicy::System => self.logind("sleep", reason),
+            SleepPolicy::Never => return,
+        };
+
+        match result {
+            Ok(inhibitor) => {
+                log::info!(
+                    "Enabled sleep prevention for active playback via {:?}",
+                    inhibitor.mechanism()
+                );
+                self.inhibitor = Some(inhibitor);
+            }
+            Err(e) => log::warn!(
+                "No sleep inhibitor available, {:?} sleep may interrupt playback: {}",
+                policy,
+                e
+            ),
+        }
+    }
+
+    fn release(&mut self) {
+        let Some(inhibitor) = self.inhibitor.take() else {
+            return;
+        };
+
+        if let LinuxInhibitor::ScreenSaver { cookie } = inhibitor {
+            let result = self.session().and_then(|connection| {
+                connection.call_method(
+                    Some("org.freedesktop.ScreenSaver"),
+                    "/org/freedesktop/ScreenSaver",
+                    Some("org.freedesktop.ScreenSaver"),
+                    "UnInhibit",
+                    &(cookie,),
+                )
+            });
+            if let Err(e) = result {
+                log::warn!("Failed to release ScreenSaver inhibitor: {}", e);
+            }
+        }
+        log::info!("Released sleep prevention for playback");
+    }
+}
+
+/// Keeps the display or system awake while video playback is active,
+/// according to the configured `SleepPolicy`.
 pub struct PlaybackSleepBlocker {
+    policy: SleepPolicy,
+    /// Whether blocking was requested. On Linux the inhibitor thread decides
+    /// which mechanism, if any, actually blocks sleep.
+    enabled: bool,
     #[cfg(target_os = "macos")]
     activity: Option<Retained<ProtocolObject<dyn NSObjectProtocol>>>,
+    #[cfg(target_os = "macos")]
+    on_change: InhibitorCallback,
+    #[cfg(target_os = "linux")]
+    requests: std::sync::mpsc::Sender<InhibitRequest>,
 }
 
 impl PlaybackSleepBlocker {
-    pub fn new() -> Self {
+    pub fn new(policy: SleepPolicy, on_change: InhibitorCallback) -> Self {
+        #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+        let _ = on_change;
+
         Self {
+            policy,
+            enabled: false,
             #[cfg(target_os = "macos")]
             activity: None,
+            #[cfg(target_os = "macos")]
+            on_change,
+            #[cfg(target_os = "linux")]
+            requests: InhibitWorker::spawn(on_change),
+        }
+    }
+
+    /// Change the policy. An active block is released; the caller re-syncs
+    /// to acquire it again under the new policy.
+    pub fn set_policy(&mut self, policy: SleepPolicy) {
+        if self.policy != policy {
+            self.disable();
+            self.policy = policy;
         }
     }
 
@@ -26,29 +255,52 @@ impl PlaybackSleepBlocker {
     }
 
     pub fn enable(&mut self, reason: &str) {
+        if self.enabled || self.policy == SleepPolicy::Never {
+            return;
+        }
+        self.enabled = true;
+
         #[cfg(target_os = "macos")]
         {
-            if self.activity.is_some() {
-                return;
-            }
-
-            let options = NSActivityOptions::IdleSystemSleepDisabled
-                | NSActivityOptions::IdleDisplaySleepDisabled;
+            let options = match self.policy {
+                SleepPolicy::Display => {
+                    NSActivityOptions::IdleSystemSleepDisabled
+                        | NSActivityOptions::IdleDisplaySleepDisabled
+                }
+                SleepPolicy::System => NSActivityOptions::IdleSystemSleepDisabled,
+                SleepPolicy::Never => return,
+            };
             let reason = NSString::from_str(reason);
             let activity =
                 NSProcessInfo::processInfo().beginActivityWithOptions_reason(options, &reason);
 
             self.activity = Some(activity);
+            (self.on_change)(Some(SleepInhibitor::MacosActivity));
             log::info!("Enabled macOS sleep prevention for active playback");
         }
 
-        #[cfg(not(target_os = "macos"))]
+        #[cfg(target_os = "linux")]
+        {
+            self.requests
+                .send(InhibitRequest::Acquire {
+                    policy: self.policy,
+                    reason: reason.to_string(),
+                })
+                .ok();
+        }
+
+        #[cfg(not(any(target_os = "macos", target_os = "linux")))]
         {
             let _ = reason;
         }
     }
 
     pub fn disable(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        self.enabled = false;
+
         #[cfg(target_os = "macos")]
         {
             if let Some(activity) = self.activity.take() {
@@ -56,15 +308,21 @@ impl PlaybackSleepBlocker {
                 unsafe {
                     process_info.endActivity(&*activity);
                 }
+                (self.on_change)(None);
                 log::info!("Released macOS sleep prevention for playback");
             }
         }
+
+        #[cfg(target_os = "linux")]
+        {
+            self.requests.send(InhibitRequest::Release).ok();
+        }
     }
 }
 
 impl Default for PlaybackSleepBlocker {
     fn default() -> Self {
-        Self::new()
+        Self::new(SleepPolicy::default(), Box::new(|_| {}))
     }
 }
 
@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+
+use libmpv2::{events::PropertyData, Format, Mpv};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tauri_specta::Event;
+
+use crate::store::GeneralSettings;
+
+/// Properties the generic property API may read, write and observe.
+const PROPERTY_ALLOWLIST: &[&str] = &[
+    "ab-loop-a",
+    "ab-loop-b",
+    "audio-delay",
+    "audio-params",
+    "brightness",
+    "chapter",
+    "chapter-list",
+    "contrast",
+    "current-tracks",
+    "deband",
+    "deinterlace",
+    "demuxer-cache-state",
+    "gamma",
+    "hue",
+    "hwdec",
+    "hwdec-current",
+    "loop-file",
+    "loop-playlist",
+    "media-title",
+    "metadata",
+    "mute",
+    "saturation",
+    "secondary-sid",
+    "secondary-sub-visibility",
+    "sub-ass-override",
+    "sub-delay",
+    "sub-font-size",
+    "sub-pos",
+    "sub-scale",
+    "sub-visibility",
+    "video-aspect-override",
+    "video-params",
+    "video-rotate",
+    "video-sync",
+    "video-zoom",
+    "volume-max",
+];
+
+/// Never accessible, even when added to `GeneralSettings::mpv_properties`:
+/// these reach the file system, load code or open sockets.
+const PROPERTY_DENYLIST: &[&str] = &[
+    "config",
+    "config-dir",
+    "dump-cache",
+    "external-files",
+    "glsl-shaders",
+    "include",
+    "input-conf",
+    "input-ipc-client",
+    "input-ipc-server",
+    "load-scripts",
+    "log-file",
+    "screenshot-directory",
+    "screenshot-template",
+    "script-opts",
+    "scripts",
+    "stream-record",
+    "sub-files",
+    "watch-later-directory",
+    "ytdl-raw-options",
+];
+
+/// Prefixes that reach an option under another name, e.g. `options/config-dir`.
+const OPTION_PREFIXES: &[&str] = &["options/", "file-local-options/", "option-info/"];
+
+/// Observer ids handed out to the generic property API start here, clear of
+/// the player and stats observers.
+const FIRST_OBSERVER_ID: u64 = 1000;
+
+#[derive(Debug, specta::Type, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum MpvFormat {
+    Flag,
+    Int,
+    Double,
+    String,
+    /// Structured value (lists and maps), exchanged as JSON.
+    Node,
+}
+
+#[derive(Debug, specta::Type, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", tag = "format", content = "value")]
+pub enum MpvValue {
+    Flag(bool),
+    /// Integers travel as numbers, exact up to 2^53.
+    Int(f64),
+    Double(f64),
+    String(String),
+    Node(serde_json::Value),
+}
+
+/// An observed property changed. `value` is `null` while the property is
+/// unavailable, e.g. `video-params` before a file is loaded.
+#[derive(Debug, specta::Type, tauri_specta::Event, Serialize, Deserialize, Clone)]
+pub struct MpvPropertyChange {
+    pub name: String,
+    pub value: Option<MpvValue>,
+}
+
+/// Whether `name` reaches a denied property, directly, through one of
+/// `OPTION_PREFIXES` or as a sub-property like `option-info/config-dir/default-value`.
+fn is_denied(name: &str) -> bool {
+    let mut name = name;
+    while let Some(rest) = OPTION_PREFIXES
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+    {
+        name = rest;
+    }
+
+    let base = name.split('/').next().unwrap_or_default();
+    PROPERTY_DENYLIST.contains(&base)
+}
+
+/// Check a property against the built-in allowlist and the user additions.
+pub fn check_allowed(app: &AppHandle, name: &str) -> Result<(), String> {
+    if is_denied(name) {
+        return Err(format!("mpv property '{}' is not accessible", name));
+    }
+    if PROPERTY_ALLOWLIST.contains(&name) {
+        return Ok(());
+    }
+
+    let extra = GeneralSettings::get(app)?
+        .unwrap_or_default()
+        .mpv_properties;
+    if extra.iter().any(|extra| extra == name) {
+        return Ok(());
+    }
+
+    Err(format!("mpv property '{}' is not in the allowlist", name))
+}
+
+pub fn get(mpv: &Mpv, name: &str, format: MpvFormat) -> Result<MpvValue, String> {
+    let value = match format {
+        MpvFormat::Flag => mpv.get_property::<bool>(name).map(MpvValue::Flag),
+        MpvFormat::Int => mpv
+            .get_property::<i64>(name)
+            .map(|v| MpvValue::Int(v as f64)),
+        MpvFormat::Double => mpv.get_property::<f64>(name).map(MpvValue::Double),
+        MpvFormat::String => mpv.get_property::<String>(name).map(MpvValue::String),
+        // mpv formats node properties as JSON when read as a string
+        MpvFormat::Node => mpv
+            .get_property::<String>(name)
+            .map(|v| MpvValue::Node(parse_node(&v))),
+    };
+
+    value.map_err(|e| format!("Failed to get mpv property '{}': {}", name, e))
+}
+
+pub fn set(mpv: &Mpv, name: &str, value: MpvValue) -> Result<(), String> {
+    let result = match value {
+        MpvValue::Flag(v) => mpv.set_property(name, v),
+        MpvValue::Int(v) => mpv.set_property(name, v as i64),
+        MpvValue::Double(v) => mpv.set_property(name, v),
+        MpvValue::String(v) => mpv.set_property(name, v),
+        MpvValue::Node(v) => mpv.set_property(name, node_to_option(&v)?),
+    };
+
+    result.map_err(|e| format!("Failed to set mpv property '{}': {}", name, e))
+}
+
+fn parse_node(value: &str) -> serde_json::Value {
+    serde_json::from_str(value).unwrap_or_else(|_| serde_json::Value::String(value.to_string()))
+}
+
+/// Format a JSON value in mpv's option syntax: lists as `a,b` and maps as
+/// `k=v,k2=v2`, escaping separators with a backslash.
+fn node_to_option(value: &serde_json::Value) -> Result<String, String> {
+    use serde_json::Value;
+
+    let escape = |value: &str| value.replace('\\', "\\\\").replace(',', "\\,");
+    let scalar = |value: &Value| match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Bool(b) => Ok(if *b { "yes" } else { "no" }.to_string()),
+        Value::Number(n) => Ok(n.to_string()),
+        _ => Err("nested mpv node values cannot be set".to_string()),
+    };
+
+    match value {
+        Value::Array(items) => items
+            .iter()
+            .map(|item| scalar(item).map(|item| escape(&item)))
+            .collect::<Result<Vec<_>, _>>()
+            .map(|items| items.join(",")),
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, item)| scalar(item).map(|item| format!("{}={}", key, escape(&item))))
+            .collect::<Result<Vec<_>, _>>()
+            .map(|items| items.join(",")),
+        Value::Null => Ok(String::new()),
+        value => scalar(value),
+    }
+}
+
+/// Properties observed through the generic property API, all reported with
+/// `MpvPropertyChange`.
+pub struct PropertyObservers {
+    observed: HashMap<u64, (String, MpvFormat)>,
+    next_id: u64,
+}
+
+impl Default for PropertyObservers {
+    fn default() -> Self {
+        Self {
+            observed: HashMap::new(),
+            next_id: FIRST_OBSERVER_ID,
+        }
+    }
+}
+
+impl PropertyObservers {
+    /// Start observing `name`. Observing an already observed property only
+    /// updates its format.
+    pub fn observe(&mut self, mpv: &Mpv, name: String, format: MpvFormat) -> Result<(), String> {
+        if let Some(id) = self.id_of(&name) {
+            mpv.unobserve_property(id)
+                .map_err(|e| format!("Failed to unobserve mpv property '{}': {}", name, e))?;
+            self.observed.remove(&id);
+        }
+
+        let id = self.next_id;
+        let mpv_format = match format {
+            MpvFormat::Flag => Format::Flag,
+            MpvFormat::Int => Format::Int64,
+            MpvFormat::Double => Format::Double,
+            MpvFormat::String | MpvFormat::Node => Format::String,
+        };
+
+        mpv.observe_property(&name, mpv_format, id)
+            .map_err(|e| format!("Failed to observe mpv property '{}': {}", name, e))?;
+        self.next_id += 1;
+        self.observed.insert(id, (name, format));
+        Ok(())
+    }
+
+    pub fn unobserve(&mut self, mpv: &Mpv, name: &str) -> Result<(), String> {
+        let Some(id) = self.id_of(name) else {
+            return Ok(());
+        };
+
+        self.observed.remove(&id);
+        mpv.unobserve_property(id)
+            .map_err(|e| format!("Failed to unobserve mpv property '{}': {}", name, e))
+    }
+
+    fn id_of(&self, name: &str) -> Option<u64> {
+        self.observed
+            .iter()
+            .find(|(_, (observed, _))| observed == name)
+            .map(|(id, _)| *id)
+    }
+
+    /// Emit a change for one of our observers. Returns `true` if the event
+    /// belonged to them, so the caller can skip its own matching.
+    pub fn handle_property(
+        &self,
+        reply_userdata: u64,
+        change: &PropertyData,
+        app_handle: &AppHandle,
+    ) -> bool {
+        let Some((name, format)) = self.observed.get(&reply_userdata) else {
+            return false;
+        };
+
+        let value = match (format, change) {
+            (MpvFormat::Flag, PropertyData::Flag(v)) => Some(MpvValue::Flag(*v)),
+            (MpvFormat::Int, PropertyData::Int64(v)) => Some(MpvValue::Int(*v as f64)),
+            (MpvFormat::Double, PropertyData::Double(v)) => Some(MpvValue::Double(*v)),
+            (MpvFormat::String, PropertyData::Str(v)) => Some(MpvValue::String(v.to_string())),
+            (MpvFormat::Node, PropertyData::Str(v)) => Some(MpvValue::Node(parse_node(v))),
+            _ => None,
+        };
+
+        MpvPropertyChange {
+            name: name.clone(),
+            value,
+        }
+        .emit(app_handle)
+        .ok();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn denylist_covers_option_prefixes_and_sub_properties() {
+        assert!(is_denied("config-dir"));
+        assert!(is_denied("options/config-dir"));
+        assert!(is_denied("file-local-options/input-ipc-server"));
+        assert!(is_denied("option-info/log-file/default-value"));
+        assert!(is_denied("options/options/script-opts"));
+        assert!(is_denied("options/ytdl-raw-options"));
+        assert!(is_denied("file-local-options/glsl-shaders"));
+        assert!(is_denied("sub-files"));
+
+        assert!(!is_denied("options/volume-max"));
+        assert!(!is_denied("metadata/by-key/title"));
+        assert!(!is_denied("configuration"));
+    }
+}
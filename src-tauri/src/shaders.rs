@@ -0,0 +1,186 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_store::StoreExt;
+
+use crate::store::GeneralSettings;
+
+const SHADER_STORE: &str = "shaders";
+const PRESETS_KEY: &str = "presets";
+
+/// Named `glsl-shaders` chain, e.g. an Anime4K mode.
+#[derive(Debug, specta::Type, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ShaderPreset {
+    pub name: String,
+    /// Shader files applied in order, relative to the shaders folder or absolute.
+    pub shaders: Vec<String>,
+}
+
+/// Folder shader files are kept in, created on first use.
+pub fn shaders_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))?
+        .join("shaders");
+
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create shaders directory: {}", e))?;
+    Ok(dir)
+}
+
+pub fn list_presets(app: &AppHandle) -> Result<Vec<ShaderPreset>, String> {
+    let store = app
+        .store(SHADER_STORE)
+        .map_err(|e| format!("Failed to open shader store: {}", e))?;
+
+    match store.get(PRESETS_KEY) {
+        Some(value) => serde_json::from_value(value)
+            .map_err(|e| format!("Failed to deserialize shader presets: {}", e)),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn save_presets(app: &AppHandle, presets: &[ShaderPreset]) -> Result<(), String> {
+    let store = app
+        .store(SHADER_STORE)
+        .map_err(|e| format!("Failed to open shader store: {}", e))?;
+
+    store.set(PRESETS_KEY, json!(presets));
+    store.save().map_err(|e| e.to_string())
+}
+
+/// Add a preset, replacing any existing one with the same name.
+pub fn save_preset(app: &AppHandle, preset: ShaderPreset) -> Result<(), String> {
+    if preset.name.trim().is_empty() {
+        return Err("Shader preset name cannot be empty".to_string());
+    }
+
+    let mut presets = list_presets(app)?;
+    match presets.iter_mut().find(|p| p.name == preset.name) {
+        Some(existing) => *existing = preset,
+        None => presets.push(preset),
+    }
+    save_presets(app, &presets)
+}
+
+/// Remove a preset and any library defaults pointing at it.
+pub fn delete_preset(app: &AppHandle, name: &str) -> Result<(), String> {
+    let mut presets = list_presets(app)?;
+    presets.retain(|p| p.name != name);
+    save_presets(app, &presets)?;
+
+    GeneralSettings::update(app, |general| {
+        general.shader_presets.retain(|_, preset| preset != name)
+    })
+}
+
+/// Resolve a preset to the absolute shader paths mpv should load.
+pub fn resolve(app: &AppHandle, name: &str) -> Result<Vec<String>, String> {
+    let preset = list_presets(app)?
+        .into_iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| format!("Shader preset '{}' not found", name))?;
+    let dir = shaders_dir(app)?;
+
+    preset
+        .shaders
+        .iter()
+        .map(|shader| {
+            let path = if Path::new(shader).is_absolute() {
+                PathBuf::from(shader)
+            } else {
+                dir.join(shader)
+            };
+
+            if !path.is_file() {
+                return Err(format!("Shader file '{}' not found", path.display()));
+            }
+            Ok(path.to_string_lossy().into_owned())
+        })
+        .collect()
+}
+
+/// Default preset name and shader paths for a library type, `None` when the
+/// library type has no default.
+pub fn library_default(
+    app: &AppHandle,
+    library_type: &str,
+) -> Result<Option<(String, Vec<String>)>, String> {
+    let preset = GeneralSettings::get(app)?
+        .unwrap_or_default()
+        .shader_presets
+        .get(library_type)
+        .cloned();
+
+    match preset {
+        Some(name) => {
+            let shaders = resolve(app, &name)?;
+            Ok(Some((name, shaders)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Log prefixes of the renderer, which compiles the shaders.
+const RENDERER_LOG_PREFIXES: &[&str] = &["vo/gpu", "libmpv_render"];
+
+/// Parts of mpv's messages about user shaders that fail to load, parse or compile.
+const SHADER_ERROR_MARKERS: &[&str] = &[
+    "glsl",
+    "user shader",
+    "user-shader",
+    "shader compile",
+    "compile shader",
+    "compiling shader",
+];
+
+/// Whether an mpv error log line comes from a shader failing to load or
+/// compile. mpv renders black frames in that case rather than failing.
+pub fn is_shader_error(prefix: &str, text: &str) -> bool {
+    if !RENDERER_LOG_PREFIXES
+        .iter()
+        .any(|renderer| prefix.starts_with(renderer))
+    {
+        return false;
+    }
+
+    let text = text.to_lowercase();
+    SHADER_ERROR_MARKERS
+        .iter()
+        .any(|marker| text.contains(marker))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_only_renderer_shader_errors() {
+        assert!(is_shader_error(
+            "vo/gpu/opengl",
+            "shader compile log (status=0): 0:12(3): error: syntax error"
+        ));
+        assert!(is_shader_error(
+            "vo/gpu",
+            "Failed to parse user shader Anime4K_Upscale_CNN_x2_M.glsl"
+        ));
+        assert!(is_shader_error(
+            "libmpv_render",
+            "GLSL compilation failed for fragment shader"
+        ));
+
+        assert!(!is_shader_error("vo/gpu", "Failed to create swapchain"));
+        assert!(!is_shader_error(
+            "ffmpeg",
+            "http: HTTP error 404 Not Found (shaders/Anime4K.glsl)"
+        ));
+        assert!(!is_shader_error(
+            "cplayer",
+            "Error parsing option glsl-shaders"
+        ));
+    }
+}
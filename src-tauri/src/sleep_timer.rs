@@ -0,0 +1,241 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tauri_specta::Event;
+
+use crate::mpv::{MpvPlayer, PlaybackEvent, VolumeEventChange};
+
+/// Volume is faded out over this many seconds before the timer stops playback.
+const FADE_DURATION: f64 = 30.0;
+
+const COUNTDOWN_INTERVAL: Duration = Duration::from_secs(1);
+const FADE_STEP: Duration = Duration::from_millis(250);
+
+#[derive(Debug, specta::Type, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase", tag = "mode", content = "count")]
+pub enum SleepTimerMode {
+    /// Stop after this many minutes, whether playing or paused.
+    AfterMinutes(u32),
+    /// Stop when the current item ends.
+    EndOfItem,
+    /// Stop when this many more items have played after the current one.
+    AfterEpisodes(u32),
+}
+
+/// Sleep timer countdown, emitted every second while a timer is set and
+/// whenever it is set, cancelled or expires.
+#[derive(Debug, specta::Type, tauri_specta::Event, Serialize, Deserialize, Clone)]
+pub struct SleepTimerChange {
+    /// `null` once the timer is cancelled or expired.
+    pub mode: Option<SleepTimerMode>,
+    /// Seconds until playback stops, when known.
+    pub remaining_secs: Option<f64>,
+    /// Items still to play after the current one in the episode based modes.
+    pub episodes_left: Option<u32>,
+    pub fading: bool,
+    /// The timer stopped playback; the UI should not auto-play the next item.
+    pub expired: bool,
+}
+
+/// Sleep timer driven by the render thread, so it keeps running in PiP and
+/// with the main window hidden.
+#[derive(Default)]
+pub struct SleepTimer {
+    mode: Option<SleepTimerMode>,
+    deadline: Option<Instant>,
+    episodes_left: u32,
+    /// The user's volume, restored once playback is stopped. The faded volume
+    /// is only ever set on mpv, never reported as the user's.
+    fade_from: Option<f64>,
+    stop_pending: bool,
+    last_tick: Option<Instant>,
+}
+
+impl SleepTimer {
+    /// Start a new timer, or cancel the current one with `None`.
+    pub fn set(&mut self, mode: Option<SleepTimerMode>, player: &mut MpvPlayer) {
+        self.restore_volume(player);
+        *self = Self {
+            mode,
+            ..Self::default()
+        };
+
+        match mode {
+            Some(SleepTimerMode::AfterMinutes(minutes)) => {
+                self.deadline = Some(Instant::now() + Duration::from_secs(minutes as u64 * 60));
+            }
+            Some(SleepTimerMode::AfterEpisodes(episodes)) => self.episodes_left = episodes,
+            Some(SleepTimerMode::EndOfItem) | None => {}
+        }
+
+        log::info!("Sleep timer set to {:?}", mode);
+        self.emit(player, false);
+    }
+
+    /// The current item reached its end. An expiry is announced right away,
+    /// before the caller reports the end of file, so the UI knows not to
+    /// auto-play the next item.
+    pub fn end_of_file(&mut self, app_handle: &AppHandle) {
+        if !matches!(
+            self.mode,
+            Some(SleepTimerMode::EndOfItem | SleepTimerMode::AfterEpisodes(_))
+        ) {
+            return;
+        }
+
+        if self.episodes_left > 0 {
+            self.episodes_left -= 1;
+            return;
+        }
+
+        self.stop_pending = true;
+        SleepTimerChange {
+            mode: None,
+            remaining_secs: Some(0.0),
+            episodes_left: None,
+            fading: false,
+            expired: true,
+        }
+        .emit(app_handle)
+        .ok();
+    }
+
+    /// When the render thread should wake up for the next countdown or fade step.
+    pub fn next_tick(&self) -> Option<Instant> {
+        self.mode?;
+
+        if self.stop_pending {
+            return Some(Instant::now());
+        }
+
+        let interval = if self.fade_from.is_some() {
+            FADE_STEP
+        } else {
+            COUNTDOWN_INTERVAL
+        };
+        Some(
+            self.last_tick
+                .map(|last_tick| last_tick + interval)
+                .unwrap_or_else(Instant::now),
+        )
+    }
+
+    /// Advance the countdown: fade the volume, stop playback once the timer
+    /// runs out and emit the remaining time.
+    pub fn tick(&mut self, player: &mut MpvPlayer) {
+        if self.mode.is_none() {
+            return;
+        }
+
+        if self.stop_pending {
+            self.stop(player);
+            return;
+        }
+
+        if self.next_tick().is_some_and(|next| next > Instant::now()) {
+            return;
+        }
+        self.last_tick = Some(Instant::now());
+
+        if let Some(remaining) = self.remaining(player) {
+            if remaining <= 0.0 && self.deadline.is_some() {
+                self.stop(player);
+                return;
+            }
+
+            if remaining <= FADE_DURATION {
+                self.fade(player, remaining);
+            }
+        }
+
+        self.emit(player, false);
+    }
+
+    /// Seconds until the timer stops playback, when known.
+    fn remaining(&self, player: &MpvPlayer) -> Option<f64> {
+        match self.mode? {
+            SleepTimerMode::AfterMinutes(_) => Some(
+                self.deadline?
+                    .saturating_duration_since(Instant::now())
+                    .as_secs_f64(),
+            ),
+            _ if self.episodes_left == 0 => {
+                player.mpv.get_property::<f64>("playtime-remaining").ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether mpv's volume is currently lowered by the fade.
+    pub fn is_fading(&self) -> bool {
+        self.fade_from.is_some()
+    }
+
+    /// The user changed the volume mid-fade. The fade continues from the new
+    /// volume, which is also what is restored.
+    pub fn set_user_volume(&mut self, volume: u8, player: &MpvPlayer) {
+        self.fade_from = Some(volume as f64);
+        VolumeEventChange { percentage: volume }
+            .emit(player.window.app_handle())
+            .ok();
+    }
+
+    fn fade(&mut self, player: &mut MpvPlayer, remaining: f64) {
+        if self.fade_from.is_none() {
+            self.fade_from = player.mpv.get_property::<f64>("volume").ok();
+        }
+
+        if let Some(volume) = self.fade_from {
+            let volume = volume * (remaining / FADE_DURATION).clamp(0.0, 1.0);
+            if let Err(e) = player.mpv.set_property("volume", volume) {
+                log::warn!("Sleep timer failed to fade volume: {}", e);
+            }
+        }
+    }
+
+    fn restore_volume(&mut self, player: &mut MpvPlayer) {
+        if let Some(volume) = self.fade_from.take() {
+            if let Err(e) = player.mpv.set_property("volume", volume) {
+                log::warn!("Sleep timer failed to restore volume: {}", e);
+            }
+        }
+    }
+
+    fn stop(&mut self, player: &mut MpvPlayer) {
+        log::info!("Sleep timer expired, pausing playback");
+
+        player.handle_playback_event(PlaybackEvent::Pause);
+        self.restore_volume(player);
+        player.release_sleep_prevention();
+
+        let announced = self.stop_pending;
+        *self = Self::default();
+        if !announced {
+            self.emit(player, true);
+        }
+    }
+
+    fn emit(&self, player: &MpvPlayer, expired: bool) {
+        let episodes_left = match self.mode {
+            Some(SleepTimerMode::EndOfItem | SleepTimerMode::AfterEpisodes(_)) => {
+                Some(self.episodes_left)
+            }
+            _ => None,
+        };
+
+        SleepTimerChange {
+            mode: self.mode,
+            remaining_secs: if expired {
+                Some(0.0)
+            } else {
+                self.remaining(player)
+            },
+            episodes_left,
+            fading: self.fade_from.is_some(),
+            expired,
+        }
+        .emit(player.window.app_handle())
+        .ok();
+    }
+}
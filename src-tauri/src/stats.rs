@@ -0,0 +1,186 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use libmpv2::{events::PropertyData, Format, Mpv};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tauri_specta::Event;
+
+/// Minimum time between two `PlaybackStatsChange` emissions.
+const STATS_EMIT_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// Observer ids reserved for the stats overlay. They live in their own range so
+/// they can be unobserved without touching the always-on player observers.
+const STATS_OBSERVERS: &[(&str, Format, u64)] = &[
+    ("frame-drop-count", Format::Int64, 100),
+    ("decoder-frame-drop-count", Format::Int64, 101),
+    ("vo-delayed-frame-count", Format::Int64, 102),
+    ("estimated-vf-fps", Format::Double, 103),
+    ("container-fps", Format::Double, 104),
+    ("display-fps", Format::Double, 105),
+    ("video-bitrate", Format::Double, 106),
+    ("audio-bitrate", Format::Double, 107),
+    ("hwdec-current", Format::String, 108),
+    ("avsync", Format::Double, 109),
+    ("cache-speed", Format::Int64, 110),
+    ("demuxer-cache-duration", Format::Double, 111),
+    ("demuxer-cache-idle", Format::Flag, 112),
+    ("demuxer-via-network", Format::Flag, 113),
+];
+
+/// Snapshot of mpv performance counters for the "stats for nerds" overlay.
+#[derive(Debug, Default, specta::Type, tauri_specta::Event, Serialize, Deserialize, Clone)]
+pub struct PlaybackStatsChange {
+    pub dropped_frames: Option<u32>,
+    pub decoder_dropped_frames: Option<u32>,
+    pub delayed_frames: Option<u32>,
+    pub estimated_fps: Option<f64>,
+    pub container_fps: Option<f64>,
+    pub display_fps: Option<f64>,
+    pub video_bitrate: Option<f64>,
+    pub audio_bitrate: Option<f64>,
+    pub hwdec_current: Option<String>,
+    pub av_sync: Option<f64>,
+    pub cache_speed: Option<f64>,
+    pub demuxer_cache_duration: Option<f64>,
+    pub demuxer_cache_idle: Option<bool>,
+    pub demuxer_via_network: Option<bool>,
+}
+
+/// Collects stats property changes while a subscription is active and emits
+/// them to the frontend at most once per `STATS_EMIT_INTERVAL`.
+///
+/// Nothing is observed while there are no subscribers, so playback without the
+/// overlay open pays no cost for it. Subscriptions are counted per webview so a
+/// reloaded or crashed webview can be dropped along with its subscriptions.
+#[derive(Default)]
+pub struct PlaybackStats {
+    subscribers: HashMap<String, usize>,
+    snapshot: PlaybackStatsChange,
+    dirty: bool,
+    last_emit: Option<Instant>,
+}
+
+impl PlaybackStats {
+    pub fn is_active(&self) -> bool {
+        !self.subscribers.is_empty()
+    }
+
+    pub fn subscribe(&mut self, mpv: &Mpv, webview: &str) {
+        let was_active = self.is_active();
+        *self.subscribers.entry(webview.to_string()).or_default() += 1;
+        if !was_active {
+            Self::observe(mpv);
+        }
+    }
+
+    pub fn unsubscribe(&mut self, mpv: &Mpv, webview: &str) {
+        let Some(count) = self.subscribers.get_mut(webview) else {
+            return;
+        };
+
+        *count -= 1;
+        if *count == 0 {
+            self.subscribers.remove(webview);
+        }
+        if !self.is_active() {
+            self.unobserve(mpv);
+        }
+    }
+
+    /// Drop every subscription of `webview`, e.g. after it reloaded.
+    pub fn reset(&mut self, mpv: &Mpv, webview: &str) {
+        if self.subscribers.remove(webview).is_some() && !self.is_active() {
+            self.unobserve(mpv);
+        }
+    }
+
+    fn observe(mpv: &Mpv) {
+        for (name, format, id) in STATS_OBSERVERS {
+            if let Err(e) = mpv.observe_property(name, *format, *id) {
+                log::warn!("Failed to observe stats property '{}': {}", name, e);
+            }
+        }
+        log::info!("Playback stats subscription started");
+    }
+
+    fn unobserve(&mut self, mpv: &Mpv) {
+        for (name, _, id) in STATS_OBSERVERS {
+            if let Err(e) = mpv.unobserve_property(*id) {
+                log::warn!("Failed to unobserve stats property '{}': {}", name, e);
+            }
+        }
+        self.snapshot = PlaybackStatsChange::default();
+        self.dirty = false;
+        self.last_emit = None;
+        log::info!("Playback stats subscription stopped");
+    }
+
+    /// Record a property change. Returns `true` if the event belonged to the
+    /// stats observers, so the caller can skip its own matching.
+    pub fn handle_property(&mut self, reply_userdata: u64, change: &PropertyData) -> bool {
+        if !self.is_active()
+            || !STATS_OBSERVERS
+                .iter()
+                .any(|(_, _, id)| *id == reply_userdata)
+        {
+            return false;
+        }
+
+        let snapshot = &mut self.snapshot;
+        match (reply_userdata, change) {
+            (100, PropertyData::Int64(v)) => snapshot.dropped_frames = Some(*v as u32),
+            (101, PropertyData::Int64(v)) => snapshot.decoder_dropped_frames = Some(*v as u32),
+            (102, PropertyData::Int64(v)) => snapshot.delayed_frames = Some(*v as u32),
+            (103, PropertyData::Double(v)) => snapshot.estimated_fps = Some(*v),
+            (104, PropertyData::Double(v)) => snapshot.container_fps = Some(*v),
+            (105, PropertyData::Double(v)) => snapshot.display_fps = Some(*v),
+            (106, PropertyData::Double(v)) => snapshot.video_bitrate = Some(*v),
+            (107, PropertyData::Double(v)) => snapshot.audio_bitrate = Some(*v),
+            (108, PropertyData::Str(v)) => snapshot.hwdec_current = Some(v.to_string()),
+            (109, PropertyData::Double(v)) => snapshot.av_sync = Some(*v),
+            (110, PropertyData::Int64(v)) => snapshot.cache_speed = Some(*v as f64),
+            (111, PropertyData::Double(v)) => snapshot.demuxer_cache_duration = Some(*v),
+            (112, PropertyData::Flag(v)) => snapshot.demuxer_cache_idle = Some(*v),
+            (113, PropertyData::Flag(v)) => snapshot.demuxer_via_network = Some(*v),
+            _ => return true,
+        }
+
+        self.dirty = true;
+        true
+    }
+
+    /// When the next throttled emission is due, if one is pending. Lets the
+    /// render thread sleep until then instead of polling.
+    pub fn next_flush(&self) -> Option<Instant> {
+        if !self.dirty || !self.is_active() {
+            return None;
+        }
+
+        Some(
+            self.last_emit
+                .map(|last_emit| last_emit + STATS_EMIT_INTERVAL)
+                .unwrap_or_else(Instant::now),
+        )
+    }
+
+    /// Emit the latest snapshot if something changed and the throttle window
+    /// has elapsed.
+    pub fn flush(&mut self, app_handle: &AppHandle) {
+        if !self.dirty || !self.is_active() {
+            return;
+        }
+
+        if let Some(last_emit) = self.last_emit {
+            if last_emit.elapsed() < STATS_EMIT_INTERVAL {
+                return;
+            }
+        }
+
+        self.snapshot.clone().emit(app_handle).ok();
+        self.dirty = false;
+        self.last_emit = Some(Instant::now());
+    }
+}
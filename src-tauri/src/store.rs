@@ -1,9 +1,15 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use specta::Type;
 use tauri::{AppHandle, Wry};
 use tauri_plugin_store::StoreExt;
 
+use crate::control_api::ControlApiSettings;
+use crate::discord::DiscordSettings;
+use crate::downloads::DownloadSettings;
+
 #[derive(Serialize, Deserialize, Type, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct GeneralSettings {
@@ -13,6 +19,30 @@ pub struct GeneralSettings {
     pub audio_language: Option<String>,
     pub auto_play_next: bool,
     pub resume_playback: bool,
+    #[serde(default)]
+    pub cache: CacheSettings,
+    #[serde(default)]
+    pub downloads: DownloadSettings,
+    /// Load `mpv.conf`, `input.conf` and scripts from the app's mpv config
+    /// folder. Applied on the next launch.
+    #[serde(default)]
+    pub mpv_user_config: bool,
+    /// Default shader preset name per library type, e.g. `anime`.
+    #[serde(default)]
+    pub shader_presets: HashMap<String, String>,
+    /// mpv properties the generic property API may access in addition to
+    /// its built-in allowlist.
+    #[serde(default)]
+    pub mpv_properties: Vec<String>,
+    #[serde(default)]
+    pub sleep_policy: SleepPolicy,
+    #[serde(default)]
+    pub discord: DiscordSettings,
+    #[serde(default)]
+    pub control_api: ControlApiSettings,
+    /// Serve mpv's JSON IPC protocol on a per-user Unix socket.
+    #[serde(default)]
+    pub mpv_ipc_server: bool,
 }
 
 impl Default for GeneralSettings {
@@ -24,10 +54,93 @@ impl Default for GeneralSettings {
             audio_language: Some("en".to_string()),
             auto_play_next: true,
             resume_playback: true,
+            cache: CacheSettings::default(),
+            downloads: DownloadSettings::default(),
+            mpv_user_config: false,
+            shader_presets: HashMap::new(),
+            mpv_properties: Vec::new(),
+            sleep_policy: SleepPolicy::default(),
+            discord: DiscordSettings::default(),
+            control_api: ControlApiSettings::default(),
+            mpv_ipc_server: false,
+        }
+    }
+}
+
+/// Network cache and read-ahead settings applied to mpv.
+#[derive(Serialize, Deserialize, Type, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheSettings {
+    /// Use these limits instead of mpv's defaults. mpv still decides per
+    /// stream whether to cache at all.
+    pub enabled: bool,
+    /// How many seconds of media mpv tries to keep buffered ahead.
+    pub cache_secs: f64,
+    /// Upper bound for the forward demuxer cache, in MiB.
+    pub max_forward_mib: u32,
+    /// Upper bound for already played data kept for seeking back, in MiB.
+    pub max_back_mib: u32,
+    /// Seconds of media to buffer before playback starts or resumes after a stall.
+    pub readahead_secs: f64,
+}
+
+#[derive(Serialize, Deserialize, Type, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum CachePreset {
+    Lan,
+    Wan,
+    MobileHotspot,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self::preset(CachePreset::Lan)
+    }
+}
+
+impl CacheSettings {
+    pub fn preset(preset: CachePreset) -> Self {
+        match preset {
+            CachePreset::Lan => Self {
+                enabled: true,
+                cache_secs: 60.0,
+                max_forward_mib: 150,
+                max_back_mib: 50,
+                readahead_secs: 0.0,
+            },
+            CachePreset::Wan => Self {
+                enabled: true,
+                cache_secs: 180.0,
+                max_forward_mib: 400,
+                max_back_mib: 100,
+                readahead_secs: 5.0,
+            },
+            // Slow and bursty: buffer as much as possible and wait longer
+            // before starting so short drops don't stall playback.
+            CachePreset::MobileHotspot => Self {
+                enabled: true,
+                cache_secs: 600.0,
+                max_forward_mib: 800,
+                max_back_mib: 50,
+                readahead_secs: 15.0,
+            },
         }
     }
 }
 
+/// What to keep awake while a video is playing.
+#[derive(Serialize, Deserialize, Type, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum SleepPolicy {
+    /// Keep the display on, which also prevents system sleep.
+    #[default]
+    Display,
+    /// Let the display sleep but keep the system awake.
+    System,
+    /// Do not block sleep.
+    Never,
+}
+
 impl GeneralSettings {
     pub fn get(app: &AppHandle<Wry>) -> Result<Option<Self>, String> {
         match app.store("store").map(|s| s.get("general_settings")) {
@@ -42,10 +42,12 @@ async playbackSpeed(speed: number) : Promise<void> {
     await TAURI_INVOKE("playback_speed", { speed });
 },
 /**
- * Load a media file URL
+ * Load a media file URL. `http` carries headers, user agent and cookies for
+ * authenticated streams so credentials stay out of the URL. `options` holds
+ * per-file settings such as start time and preselected tracks.
  */
-async playbackLoad(url: string) : Promise<void> {
-    await TAURI_INVOKE("playback_load", { url });
+async playbackLoad(url: string, http?: HttpOptions | null, options?: LoadOptions | null) : Promise<void> {
+    await TAURI_INVOKE("playback_load", { url, http: http ?? null, options: options ?? null });
 },
 /**
  * Change subtitle track
@@ -70,9 +72,272 @@ async playbackClear() : Promise<void> {
  * The subtitle will appear in mpv's track-list so the existing UI can select it via sid.
  * `title` and `lang` are forwarded to mpv's `sub-add` command so the track displays a
  * human-friendly name (e.g. "English") instead of the raw URL.
+ * `http` is only used for this subtitle; the video's HTTP options are kept.
  */
-async playbackLoadSubtitle(url: string, title?: string | null, lang?: string | null) : Promise<void> {
-    await TAURI_INVOKE("playback_load_subtitle", { url, title: title ?? null, lang: lang ?? null });
+async playbackLoadSubtitle(url: string, title?: string | null, lang?: string | null, http?: HttpOptions | null) : Promise<void> {
+    await TAURI_INVOKE("playback_load_subtitle", { url, title: title ?? null, lang: lang ?? null, http: http ?? null });
+},
+/**
+ * Play local files or folders. Folders are queued in natural order, and
+ * subtitles next to each video are added once it has loaded.
+ */
+async playbackOpenLocal(paths: string[]) : Promise<LocalQueueChange> {
+    return await TAURI_INVOKE("playback_open_local", { paths });
+},
+/**
+ * Jump to another file of the local queue
+ */
+async playbackLocalSelect(index: number) : Promise<LocalQueueChange> {
+    return await TAURI_INVOKE("playback_local_select", { index });
+},
+/**
+ * Set the minimum interval in milliseconds between two time position updates
+ */
+async playbackTimeUpdateInterval(intervalMs: number) : Promise<void> {
+    await TAURI_INVOKE("playback_time_update_interval", { intervalMs });
+},
+/**
+ * Start receiving `PlaybackStatsChange` events for a stats overlay.
+ * Every call must be paired with `playback_stats_unsubscribe`.
+ */
+async playbackStatsSubscribe() : Promise<void> {
+    await TAURI_INVOKE("playback_stats_subscribe");
+},
+/**
+ * Stop receiving `PlaybackStatsChange` events
+ */
+async playbackStatsUnsubscribe() : Promise<void> {
+    await TAURI_INVOKE("playback_stats_unsubscribe");
+},
+/**
+ * Persist network cache settings and apply them to the running player
+ */
+async playbackCacheSettings(settings: CacheSettings) : Promise<null> {
+    return await TAURI_INVOKE("playback_cache_settings", { settings });
+},
+/**
+ * Replace the cache settings with one of the built-in presets and apply it
+ */
+async playbackCachePreset(preset: CachePreset) : Promise<CacheSettings> {
+    return await TAURI_INVOKE("playback_cache_preset", { preset });
+},
+/**
+ * Choose what stays awake during playback and apply it immediately
+ */
+async playbackSleepPolicy(policy: SleepPolicy) : Promise<null> {
+    return await TAURI_INVOKE("playback_sleep_policy", { policy });
+},
+/**
+ * Start a sleep timer, replacing any running one
+ */
+async playbackSleepTimerSet(mode: SleepTimerMode) : Promise<null> {
+    return await TAURI_INVOKE("playback_sleep_timer_set", { mode });
+},
+/**
+ * Cancel the running sleep timer
+ */
+async playbackSleepTimerCancel() : Promise<null> {
+    return await TAURI_INVOKE("playback_sleep_timer_cancel");
+},
+/**
+ * Persist the Discord Rich Presence settings and apply them immediately.
+ * Enabling fails without a Discord application id.
+ */
+async discordPresenceSettings(settings: DiscordSettings) : Promise<null> {
+    return await TAURI_INVOKE("discord_presence_settings", { settings });
+},
+/**
+ * Persist the control API settings and restart it. A token is generated
+ * when enabling without one; the saved settings are returned.
+ */
+async controlApiSettings(settings: ControlApiSettings) : Promise<ControlApiSettings> {
+    return await TAURI_INVOKE("control_api_settings", { settings });
+},
+/**
+ * Replace the control API token and restart the API with it
+ */
+async controlApiRegenerateToken() : Promise<ControlApiSettings> {
+    return await TAURI_INVOKE("control_api_regenerate_token");
+},
+/**
+ * Navigation requested by the link or arguments square was launched with.
+ * Later requests arrive as `NavigationRequest` events.
+ */
+async launchTakeNavigation() : Promise<NavigationRequest | null> {
+    return await TAURI_INVOKE("launch_take_navigation");
+},
+/**
+ * Share the signed in Jellyfin session with the backend, or clear it on sign out.
+ * Sessions on unsupported servers are refused, which keeps progress
+ * reporting, SyncPlay and remote control off
+ */
+async jellyfinSetSession(session: JellyfinSession | null) : Promise<null> {
+    return await TAURI_INVOKE("jellyfin_set_session", { session });
+},
+/**
+ * Find Jellyfin servers on the local network. Waits `timeout_ms`
+ * (default 2000, at most 10000) for replies.
+ */
+async jellyfinDiscoverServers(timeoutMs?: number | null) : Promise<DiscoveredServer[]> {
+    return await TAURI_INVOKE("jellyfin_discover_servers", { timeoutMs: timeoutMs ?? null });
+},
+/**
+ * Check that `url` points at a supported Jellyfin server and return its
+ * details, before signing in
+ */
+async jellyfinServerInfo(url: string) : Promise<JellyfinServerInfo> {
+    return await TAURI_INVOKE("jellyfin_server_info", { url });
+},
+/**
+ * List the SyncPlay groups on the signed in server
+ */
+async syncplayListGroups() : Promise<SyncPlayGroup[]> {
+    return await TAURI_INVOKE("syncplay_list_groups");
+},
+/**
+ * Create a SyncPlay group and join it
+ */
+async syncplayCreateGroup(name: string) : Promise<null> {
+    return await TAURI_INVOKE("syncplay_create_group", { name });
+},
+async syncplayJoinGroup(groupId: string) : Promise<null> {
+    return await TAURI_INVOKE("syncplay_join_group", { groupId });
+},
+async syncplayLeaveGroup() : Promise<null> {
+    return await TAURI_INVOKE("syncplay_leave_group");
+},
+/**
+ * Ask the group to pause; used instead of `playback_pause` while in a group
+ */
+async syncplayPause() : Promise<null> {
+    return await TAURI_INVOKE("syncplay_pause");
+},
+/**
+ * Ask the group to play; used instead of `playback_play` while in a group
+ */
+async syncplayUnpause() : Promise<null> {
+    return await TAURI_INVOKE("syncplay_unpause");
+},
+/**
+ * Ask the group to seek to `position` seconds
+ */
+async syncplaySeek(position: number) : Promise<null> {
+    return await TAURI_INVOKE("syncplay_seek", { position });
+},
+/**
+ * Queue an item for offline playback
+ */
+async downloadsEnqueue(request: DownloadRequest) : Promise<DownloadEntry> {
+    return await TAURI_INVOKE("downloads_enqueue", { request });
+},
+/**
+ * List all queued, running and finished downloads
+ */
+async downloadsList() : Promise<DownloadEntry[]> {
+    return await TAURI_INVOKE("downloads_list");
+},
+async downloadsPause(itemId: string) : Promise<null> {
+    return await TAURI_INVOKE("downloads_pause", { itemId });
+},
+async downloadsResume(itemId: string) : Promise<null> {
+    return await TAURI_INVOKE("downloads_resume", { itemId });
+},
+/**
+ * Remove a download from the queue, optionally deleting its files
+ */
+async downloadsRemove(itemId: string, deleteFiles: boolean) : Promise<null> {
+    return await TAURI_INVOKE("downloads_remove", { itemId, deleteFiles });
+},
+/**
+ * Persist download limits; downloads started from now on use them
+ */
+async downloadsSettings(settings: DownloadSettings) : Promise<null> {
+    return await TAURI_INVOKE("downloads_settings", { settings });
+},
+/**
+ * Whether the user mpv configuration is enabled and loaded, and where it lives
+ */
+async mpvConfigStatus() : Promise<MpvConfigStatus> {
+    return await TAURI_INVOKE("mpv_config_status");
+},
+/**
+ * Turn the user mpv configuration on or off; applied on the next launch
+ */
+async mpvConfigSetEnabled(enabled: boolean) : Promise<null> {
+    return await TAURI_INVOKE("mpv_config_set_enabled", { enabled });
+},
+async mpvScriptsList() : Promise<MpvScript[]> {
+    return await TAURI_INVOKE("mpv_scripts_list");
+},
+async mpvScriptEnable(name: string) : Promise<null> {
+    return await TAURI_INVOKE("mpv_script_enable", { name });
+},
+async mpvScriptDisable(name: string) : Promise<null> {
+    return await TAURI_INVOKE("mpv_script_disable", { name });
+},
+/**
+ * Path of the mpv JSON IPC socket, or `None` while it is turned off
+ */
+async mpvIpcSocketPath() : Promise<string | null> {
+    return await TAURI_INVOKE("mpv_ipc_socket_path");
+},
+/**
+ * Start or stop the mpv JSON IPC socket and remember the choice. Returns the
+ * socket path while it is on.
+ */
+async mpvIpcSetEnabled(enabled: boolean) : Promise<string | null> {
+    return await TAURI_INVOKE("mpv_ipc_set_enabled", { enabled });
+},
+/**
+ * Folder relative shader paths in presets resolve against
+ */
+async shadersDir() : Promise<string> {
+    return await TAURI_INVOKE("shaders_dir");
+},
+async shadersListPresets() : Promise<ShaderPreset[]> {
+    return await TAURI_INVOKE("shaders_list_presets");
+},
+/**
+ * Create or replace a shader preset
+ */
+async shadersSavePreset(preset: ShaderPreset) : Promise<null> {
+    return await TAURI_INVOKE("shaders_save_preset", { preset });
+},
+async shadersDeletePreset(name: string) : Promise<null> {
+    return await TAURI_INVOKE("shaders_delete_preset", { name });
+},
+/**
+ * Apply a shader preset to the player, or turn shaders off with `null`
+ */
+async shadersApply(name: string | null) : Promise<null> {
+    return await TAURI_INVOKE("shaders_apply", { name });
+},
+/**
+ * Set the preset applied when loading items of a library type
+ */
+async shadersSetDefault(libraryType: string, name: string | null) : Promise<null> {
+    return await TAURI_INVOKE("shaders_set_default", { libraryType, name });
+},
+/**
+ * Read an allowlisted mpv property
+ */
+async mpvGetProperty(name: string, format: MpvFormat) : Promise<MpvValue> {
+    return await TAURI_INVOKE("mpv_get_property", { name, format });
+},
+/**
+ * Write an allowlisted mpv property
+ */
+async mpvSetProperty(name: string, value: MpvValue) : Promise<null> {
+    return await TAURI_INVOKE("mpv_set_property", { name, value });
+},
+/**
+ * Report changes of an allowlisted mpv property through `MpvPropertyChange`
+ */
+async mpvObserveProperty(name: string, format: MpvFormat) : Promise<null> {
+    return await TAURI_INVOKE("mpv_observe_property", { name, format });
+},
+async mpvUnobserveProperty(name: string) : Promise<null> {
+    return await TAURI_INVOKE("mpv_unobserve_property", { name });
 },
 async toggleTitlebarHide(hide: boolean) : Promise<null> {
     return await TAURI_INVOKE("toggle_titlebar_hide", { hide });
@@ -132,12 +397,21 @@ audioChangeEvent: AudioChangeEvent,
 audioTrackChange: AudioTrackChange,
 bufferingStateChange: BufferingStateChange,
 cacheTimeChange: CacheTimeChange,
+downloadProgressChange: DownloadProgressChange,
 eofEventChange: EOFEventChange,
 errorEventChange: ErrorEventChange,
 fileLoadedChange: FileLoadedChange,
+localQueueChange: LocalQueueChange,
+mediaControlEvent: MediaControlEvent,
+mpvConfigErrorChange: MpvConfigErrorChange,
+mpvPropertyChange: MpvPropertyChange,
+navigationRequest: NavigationRequest,
+nowPlayingChange: NowPlayingChange,
 pauseForCacheChange: PauseForCacheChange,
 playBackStateChange: PlayBackStateChange,
 playBackTimeChange: PlayBackTimeChange,
+playbackStatsChange: PlaybackStatsChange,
+remoteControlEvent: RemoteControlEvent,
 requestAudioEvent: RequestAudioEvent,
 requestClearEvent: RequestClearEvent,
 requestFileLoad: RequestFileLoad,
@@ -146,21 +420,34 @@ requestSeekEvent: RequestSeekEvent,
 requestSpeedEvent: RequestSpeedEvent,
 requestSubtitleEvent: RequestSubtitleEvent,
 requestVolumeEvent: RequestVolumeEvent,
+sleepInhibitionChange: SleepInhibitionChange,
+sleepTimerChange: SleepTimerChange,
+sourceFallbackEvent: SourceFallbackEvent,
 speedEventChange: SpeedEventChange,
 subtitleChangeEvent: SubtitleChangeEvent,
 subtitleTrackChange: SubtitleTrackChange,
+syncPlayEvent: SyncPlayEvent,
 volumeEventChange: VolumeEventChange
 }>({
 audioChangeEvent: "audio-change-event",
 audioTrackChange: "audio-track-change",
 bufferingStateChange: "buffering-state-change",
 cacheTimeChange: "cache-time-change",
+downloadProgressChange: "download-progress-change",
 eofEventChange: "eof-event-change",
 errorEventChange: "error-event-change",
 fileLoadedChange: "file-loaded-change",
+localQueueChange: "local-queue-change",
+mediaControlEvent: "media-control-event",
+mpvConfigErrorChange: "mpv-config-error-change",
+mpvPropertyChange: "mpv-property-change",
+navigationRequest: "navigation-request",
+nowPlayingChange: "now-playing-change",
 pauseForCacheChange: "pause-for-cache-change",
 playBackStateChange: "play-back-state-change",
 playBackTimeChange: "play-back-time-change",
+playbackStatsChange: "playback-stats-change",
+remoteControlEvent: "remote-control-event",
 requestAudioEvent: "request-audio-event",
 requestClearEvent: "request-clear-event",
 requestFileLoad: "request-file-load",
@@ -169,9 +456,13 @@ requestSeekEvent: "request-seek-event",
 requestSpeedEvent: "request-speed-event",
 requestSubtitleEvent: "request-subtitle-event",
 requestVolumeEvent: "request-volume-event",
+sleepInhibitionChange: "sleep-inhibition-change",
+sleepTimerChange: "sleep-timer-change",
+sourceFallbackEvent: "source-fallback-event",
 speedEventChange: "speed-event-change",
 subtitleChangeEvent: "subtitle-change-event",
 subtitleTrackChange: "subtitle-track-change",
+syncPlayEvent: "sync-play-event",
 volumeEventChange: "volume-event-change"
 })
 
@@ -184,29 +475,519 @@ volumeEventChange: "volume-event-change"
 export type AudioChangeEvent = { index: string }
 export type AudioTrackChange = { tracks: Track[] }
 export type BufferingStateChange = { buffered: number }
+export type CachePreset = "lan" | "wan" | "mobileHotspot"
+/**
+ * Network cache and read-ahead settings applied to mpv.
+ */
+export type CacheSettings = { 
+/**
+ * Use these limits instead of mpv's defaults. mpv still decides per
+ * stream whether to cache at all.
+ */
+enabled: boolean; 
+/**
+ * How many seconds of media mpv tries to keep buffered ahead.
+ */
+cacheSecs: number; 
+/**
+ * Upper bound for the forward demuxer cache, in MiB.
+ */
+maxForwardMib: number; 
+/**
+ * Upper bound for already played data kept for seeking back, in MiB.
+ */
+maxBackMib: number; 
+/**
+ * Seconds of media to buffer before playback starts or resumes after a stall.
+ */
+readaheadSecs: number }
 export type CacheTimeChange = { time: number }
+/**
+ * Which interfaces the control API listens on.
+ */
+export type ControlApiBind = 
+/**
+ * Only this machine.
+ */
+"localhost" | 
+/**
+ * Every interface, so other devices on the network can connect.
+ */
+"lan"
+/**
+ * Control API settings, stored in `GeneralSettings`.
+ */
+export type ControlApiSettings = { enabled: boolean; bind: ControlApiBind; port: number; 
+/**
+ * Sent as `Authorization: Bearer <token>` or a `token` query parameter.
+ */
+token: string }
+/**
+ * Rich Presence settings, stored in `GeneralSettings`.
+ */
+export type DiscordSettings = { enabled: boolean; 
+/**
+ * Show that something is playing without its title or episode.
+ */
+privacyMode: boolean; 
+/**
+ * Discord application id the presence is published under. Defaults to
+ * the app's own application, if the build has one.
+ */
+clientId: string }
+/**
+ * A server that answered the discovery broadcast.
+ */
+export type DiscoveredServer = { id: string; name: string; 
+/**
+ * Base URL the server advertises, e.g. `http://192.168.1.10:8096`.
+ */
+address: string }
+/**
+ * A persisted entry of the download queue. Byte counts are `f64` so they
+ * export as plain numbers to the frontend.
+ */
+export type DownloadEntry = { request: DownloadRequest; state: DownloadState; downloadedBytes: number; totalBytes: number | null; 
+/**
+ * `ETag` or `Last-Modified` of the response the partial file came from,
+ * sent as `If-Range` on resume so a changed file is downloaded afresh.
+ */
+validator?: string | null }
+export type DownloadProgressChange = { item_id: string; state: DownloadState; downloaded_bytes: number; total_bytes: number | null; bytes_per_second: number }
+export type DownloadRequest = { itemId: string; title: string; url: string; 
+/**
+ * File name of the media, including its extension.
+ */
+fileName: string; http?: HttpOptions | null; subtitles?: SubtitleDownload[] }
+/**
+ * Download limits, stored in `GeneralSettings`.
+ */
+export type DownloadSettings = { maxParallel: number; 
+/**
+ * Total bandwidth cap across all downloads in KiB/s, `None` for unlimited.
+ */
+bandwidthLimitKib: number | null; 
+/**
+ * Free space to leave on the disk, in MiB.
+ */
+minFreeSpaceMib: number }
+export type DownloadState = { state: "queued" } | { state: "downloading" } | { state: "paused" } | { state: "completed" } | { state: "failed"; message: string }
 export type EOFEventChange = null
 export type ErrorEventChange = { message: string }
+/**
+ * Alternative source for a load, used when the previous one fails to open.
+ */
+export type FallbackSource = { url: string; 
+/**
+ * HTTP options for this source; the original load's are used when unset.
+ */
+http?: HttpOptions | null; 
+/**
+ * Short description for the UI, e.g. "Transcode (4 Mbps)".
+ */
+label: string | null; 
+/**
+ * Replaces `LoadOptions::report`, as the play method usually changes.
+ */
+report: PlaybackReportInfo | null }
 export type FileLoadedChange = { duration: number; current_time: number }
-export type GeneralSettings = { volume: number; playbackSpeed: number; subtitleLanguage: string | null; audioLanguage: string | null; autoPlayNext: boolean; resumePlayback: boolean }
+export type GeneralSettings = { volume: number; playbackSpeed: number; subtitleLanguage: string | null; audioLanguage: string | null; autoPlayNext: boolean; resumePlayback: boolean; cache?: CacheSettings; downloads?: DownloadSettings; 
+/**
+ * Load `mpv.conf`, `input.conf` and scripts from the app's mpv config
+ * folder. Applied on the next launch.
+ */
+mpvUserConfig?: boolean; 
+/**
+ * Default shader preset name per library type, e.g. `anime`.
+ */
+shaderPresets?: { [key in string]: string }; 
+/**
+ * mpv properties the generic property API may access in addition to
+ * its built-in allowlist.
+ */
+mpvProperties?: string[]; sleepPolicy?: SleepPolicy; discord?: DiscordSettings; controlApi?: ControlApiSettings; 
+/**
+ * Serve mpv's JSON IPC protocol on a per-user Unix socket.
+ */
+mpvIpcServer?: boolean }
+/**
+ * HTTP request options mpv should use when fetching a stream or subtitle,
+ * so credentials can travel in headers instead of the URL.
+ */
+export type HttpOptions = { headers?: { [key in string]: string }; userAgent: string | null; 
+/**
+ * Raw `Cookie` header value, e.g. `"a=1; b=2"`.
+ */
+cookies: string | null }
 /**
  * Response returned to the frontend for integration validation.
  */
 export type IntegrationCheckResponse = { status: number; statusText: string; body: string; ok: boolean }
+export type JellyfinError = { kind: "notSignedIn" } | 
+/**
+ * The access token was rejected; the user has to sign in again.
+ */
+{ kind: "unauthorized" } | 
+/**
+ * The user is not allowed to do this.
+ */
+{ kind: "forbidden"; message: string } | { kind: "notFound"; message: string } | 
+/**
+ * Any other error status, with the start of the response body.
+ */
+{ kind: "server"; message: string } | 
+/**
+ * The server could not be reached.
+ */
+{ kind: "network"; message: string } | 
+/**
+ * The response did not match the expected model.
+ */
+{ kind: "decode"; message: string } | 
+/**
+ * The server is older than `MIN_SERVER_VERSION`.
+ */
+{ kind: "unsupportedVersion"; message: string }
+/**
+ * Basic details of a server, for the sign in screen.
+ */
+export type JellyfinServerInfo = { id: string; name: string; version: string }
+/**
+ * Credentials of the Jellyfin server the frontend is signed in to, so the
+ * backend can talk to it on its own.
+ */
+export type JellyfinSession = { serverUrl: string; accessToken: string; userId: string; deviceId: string; deviceName: string }
+/**
+ * Options applied to a single `loadfile`, so tracks and timing are correct
+ * from the first frame instead of being switched after `FileLoadedChange`.
+ */
+export type JsonValue = null | boolean | number | string | JsonValue[] | { [key in string]: JsonValue }
+export type LoadOptions = { 
+/**
+ * Jellyfin item id, used to key local resume history and for progress
+ * reporting. Not passed to mpv.
+ */
+item_id: string | null; 
+/**
+ * Enables backend progress reporting to Jellyfin for `item_id`.
+ */
+report: PlaybackReportInfo | null; start: number | null; end: number | null; aid: string | null; sid: string | null; secondary_sid: string | null; 
+/**
+ * Title shown by the OS media controls and mpv's OSD.
+ */
+media_title: string | null; 
+/**
+ * Episode line shown under the title by media integrations, e.g.
+ * `S1:E2 Pilot`. Not passed to mpv.
+ */
+episode?: string | null; 
+/**
+ * Poster or thumbnail shown by the OS media controls. Not passed to mpv.
+ */
+artwork_url?: string | null; 
+/**
+ * Library type, e.g. `anime`, whose default shader preset is applied.
+ * Not passed to mpv.
+ */
+library_type?: string | null; audio_files?: string[]; sub_files?: string[]; 
+/**
+ * Additional per-file mpv options, restricted to `LOAD_OPTION_ALLOWLIST`.
+ */
+extra?: { [key in string]: string }; 
+/**
+ * Sources tried in order when the main URL fails to load, e.g. a direct
+ * stream followed by a lower bitrate transcode. Not passed to mpv.
+ */
+fallbacks?: FallbackSource[] }
+/**
+ * The local queue, emitted when it is opened, advances or ends.
+ */
+export type LocalQueueChange = { files: string[]; 
+/**
+ * Position of the playing file in `files`.
+ */
+index: number }
+export type MediaControlAction = "next" | "previous"
+/**
+ * Media control the backend cannot handle itself, e.g. a "next" media key,
+ * forwarded to the frontend which owns the queue.
+ */
+export type MediaControlEvent = { action: MediaControlAction }
+/**
+ * Error from loading or managing the user mpv configuration.
+ */
+export type MpvConfigError = 
+/**
+ * The user configuration mode is turned off.
+ */
+{ kind: "disabled" } | 
+/**
+ * No script with that name exists.
+ */
+{ kind: "scriptNotFound"; message: string } | 
+/**
+ * A script with that name is already in the target folder.
+ */
+{ kind: "scriptExists"; message: string } | 
+/**
+ * The name is not a plain file name inside the scripts folder.
+ */
+{ kind: "invalidScriptName"; message: string } | 
+/**
+ * Reading or writing the config folder failed.
+ */
+{ kind: "io"; message: string } | 
+/**
+ * mpv refused the configuration; playback runs with the built-in defaults.
+ */
+{ kind: "mpv"; message: string } | 
+/**
+ * mpv could not apply a config file line, as `<file>:<line>: <reason>`.
+ */
+{ kind: "configLine"; message: string } | 
+/**
+ * A script failed to load or run, as `<script>: <error>`.
+ */
+{ kind: "scriptFailed"; message: string }
+/**
+ * A broken config line or failing script, as mpv reports it.
+ */
+export type MpvConfigErrorChange = { error: MpvConfigError }
+export type MpvConfigStatus = { 
+/**
+ * Whether the user configuration is turned on in the settings.
+ */
+enabled: boolean; 
+/**
+ * Whether the running player was started with it. Toggling the setting
+ * takes effect on the next launch.
+ */
+loaded: boolean; configDir: string; 
+/**
+ * Why the configuration could not be loaded at startup, if it failed.
+ */
+error: MpvConfigError | null; 
+/**
+ * Broken config lines and failing scripts reported by mpv since launch.
+ */
+problems: MpvConfigError[] }
+export type MpvFormat = "flag" | "int" | "double" | "string" | 
+/**
+ * Structured value (lists and maps), exchanged as JSON.
+ */
+"node"
+/**
+ * An observed property changed. `value` is `null` while the property is
+ * unavailable, e.g. `video-params` before a file is loaded.
+ */
+export type MpvPropertyChange = { name: string; value: MpvValue | null }
+export type MpvScript = { name: string; kind: MpvScriptKind; enabled: boolean }
+export type MpvScriptKind = "lua" | "javaScript" | 
+/**
+ * Folder with a `main.lua` or `main.js`.
+ */
+"directory"
+export type MpvValue = { format: "flag"; value: boolean } | 
+/**
+ * Integers travel as numbers, exact up to 2^53.
+ */
+{ format: "int"; value: number } | { format: "double"; value: number } | { format: "string"; value: string } | { format: "node"; value: JsonValue }
+/**
+ * Ask the frontend to navigate somewhere, e.g. from a `square://` link.
+ */
+export type NavigationRequest = { kind: "item"; item_id: string }
+/**
+ * What the player is showing, for OS media integrations. Emitted on every
+ * load and when the player is cleared.
+ */
+export type NowPlayingChange = { item_id: string | null; title: string | null; episode: string | null; artwork_url: string | null; 
+/**
+ * `false` once the player is cleared, even if the item had no title.
+ */
+loaded: boolean }
 export type PauseForCacheChange = { pause: boolean }
 export type PlayBackStateChange = { pause: boolean }
-export type PlayBackTimeChange = { position: string }
+export type PlayBackTimeChange = { position: number; duration: number; percent: number }
+/**
+ * Jellyfin specific details of a load, supplied by the frontend alongside the
+ * item id so the backend can report playback to the server.
+ */
+export type PlaybackReportInfo = { mediaSourceId: string | null; playSessionId: string | null; 
+/**
+ * `DirectPlay`, `DirectStream` or `Transcode`.
+ */
+playMethod: string; 
+/**
+ * Jellyfin stream index of each audio track, in mpv track order.
+ */
+audioStreamIndexes?: number[]; 
+/**
+ * Jellyfin stream index of each subtitle track, in mpv track order.
+ */
+subtitleStreamIndexes?: number[] }
+/**
+ * Snapshot of mpv performance counters for the "stats for nerds" overlay.
+ */
+export type PlaybackStatsChange = { dropped_frames: number | null; decoder_dropped_frames: number | null; delayed_frames: number | null; estimated_fps: number | null; container_fps: number | null; display_fps: number | null; video_bitrate: number | null; audio_bitrate: number | null; hwdec_current: string | null; av_sync: number | null; cache_speed: number | null; demuxer_cache_duration: number | null; demuxer_cache_idle: boolean | null; demuxer_via_network: boolean | null }
+/**
+ * Remote control requests the frontend has to carry out, as they need the
+ * library and navigation state only it has.
+ */
+export type RemoteControlEvent = 
+/**
+ * Load `item_ids` and start at `start_index`.
+ */
+{ kind: "play"; item_ids: string[]; start_index: number; start_position: number; media_source_id: string | null; audio_stream_index: number | null; subtitle_stream_index: number | null } | 
+/**
+ * Playback was stopped; leave the player.
+ */
+{ kind: "stop" } | { kind: "displayMessage"; header: string; text: string; timeout_ms: number | null }
 export type RequestAudioEvent = { index: string }
 export type RequestClearEvent = null
-export type RequestFileLoad = { url: string; start_time: number | null }
+export type RequestFileLoad = { url: string; start_time: number | null; http?: HttpOptions | null; 
+/**
+ * Per-load options; `options.start` takes precedence over `start_time`.
+ */
+options?: LoadOptions | null }
 export type RequestPlayBackState = { pause: boolean }
 export type RequestSeekEvent = { position: number; absolute: boolean }
 export type RequestSpeedEvent = { speed: number }
 export type RequestSubtitleEvent = { index: string }
 export type RequestVolumeEvent = { percentage: number }
+/**
+ * Named `glsl-shaders` chain, e.g. an Anime4K mode.
+ */
+export type ShaderPreset = { name: string; 
+/**
+ * Shader files applied in order, relative to the shaders folder or absolute.
+ */
+shaders: string[] }
+/**
+ * Emitted when sleep blocking starts, stops or switches mechanism.
+ */
+export type SleepInhibitionChange = { 
+/**
+ * `null` while nothing is blocked.
+ */
+mechanism: SleepInhibitor | null }
+/**
+ * How sleep is currently being blocked.
+ */
+export type SleepInhibitor = 
+/**
+ * `NSProcessInfo` activity on macOS.
+ */
+"macosActivity" | 
+/**
+ * `org.freedesktop.ScreenSaver.Inhibit` on the session bus.
+ */
+"screenSaver" | 
+/**
+ * systemd-logind inhibitor lock on the system bus.
+ */
+"logind"
+/**
+ * What to keep awake while a video is playing.
+ */
+export type SleepPolicy = 
+/**
+ * Keep the display on, which also prevents system sleep.
+ */
+"display" | 
+/**
+ * Let the display sleep but keep the system awake.
+ */
+"system" | 
+/**
+ * Do not block sleep.
+ */
+"never"
+/**
+ * Sleep timer countdown, emitted every second while a timer is set and
+ * whenever it is set, cancelled or expires.
+ */
+export type SleepTimerChange = { 
+/**
+ * `null` once the timer is cancelled or expired.
+ */
+mode: SleepTimerMode | null; 
+/**
+ * Seconds until playback stops, when known.
+ */
+remaining_secs: number | null; 
+/**
+ * Items still to play after the current one in the episode based modes.
+ */
+episodes_left: number | null; fading: boolean; 
+/**
+ * The timer stopped playback; the UI should not auto-play the next item.
+ */
+expired: boolean }
+export type SleepTimerMode = 
+/**
+ * Stop after this many minutes, whether playing or paused.
+ */
+{ mode: "afterMinutes"; count: number } | 
+/**
+ * Stop when the current item ends.
+ */
+{ mode: "endOfItem" } | 
+/**
+ * Stop when this many more items have played after the current one.
+ */
+{ mode: "afterEpisodes"; count: number }
+/**
+ * A source failed to load and playback moved on to the next fallback.
+ */
+export type SourceFallbackEvent = { 
+/**
+ * Error mpv reported for the failed source.
+ */
+reason: string; 
+/**
+ * Label of the fallback now loading.
+ */
+label: string | null; 
+/**
+ * 1-based index of the fallback now loading.
+ */
+attempt: number; 
+/**
+ * Fallbacks left after this one.
+ */
+remaining: number }
 export type SpeedEventChange = { speed: number }
 export type SubtitleChangeEvent = { index: string }
+/**
+ * External subtitle to store next to the downloaded media.
+ */
+export type SubtitleDownload = { url: string; 
+/**
+ * Language suffix used in the file name, e.g. `en` for `movie.en.srt`.
+ */
+lang: string | null; 
+/**
+ * File extension without the dot, e.g. `srt`.
+ */
+extension: string }
 export type SubtitleTrackChange = { tracks: Track[] }
+/**
+ * Group changes pushed by the server, for the SyncPlay UI. The frontend
+ * loads the item named by `playQueue`; playback control is handled here.
+ */
+export type SyncPlayEvent = { kind: "groupJoined"; group: SyncPlayGroup } | { kind: "groupLeft" } | { kind: "userJoined"; user: string } | { kind: "userLeft"; user: string } | { kind: "stateUpdate"; state: string; reason: string } | { kind: "playQueue"; item_ids: string[]; playing_index: number | null; start_position: number; is_playing: boolean } | 
+/**
+ * The group does not exist or the library is not accessible.
+ */
+{ kind: "error"; message: string }
+/**
+ * SyncPlay group as listed by the server.
+ */
+export type SyncPlayGroup = { groupId: string; groupName: string; 
+/**
+ * `Idle`, `Waiting`, `Paused` or `Playing`.
+ */
+state: string; participants: string[] }
 export type Track = { id: number; type: string; title?: string | null; lang?: string | null }
 export type VolumeEventChange = { percentage: number }
 